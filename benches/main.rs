@@ -1,6 +1,8 @@
 use criterion::{black_box, criterion_group, criterion_main, Criterion};
+use microdsp::common::{autocorr_fft, autocorr_fft_size};
 use microdsp::mpm::MpmPitchDetector;
 use microdsp::mpm::MpmPitchResult;
+use microdsp::sfnov::SpectralFluxNoveltyDetector;
 
 fn run_mpm_benchmark(id: &str, c: &mut Criterion, window_size: usize, lag_count: usize) {
     let mut result = MpmPitchResult::new(window_size, lag_count);
@@ -27,6 +29,25 @@ fn result_benchmarks(c: &mut Criterion) {
     run_mpm_benchmark("Window 2048, lag 2048", c, 2048, 2048);
 }
 
+fn autocorr_benchmarks(c: &mut Criterion) {
+    // Includes the power spectrum loop vectorized by the simd feature
+    let window_size = 2048;
+    let lag_count = 1024;
+    let window: Vec<f32> = (0..window_size).map(|i| (i as f32).sin()).collect();
+    let mut result = vec![0.0; autocorr_fft_size(window_size, lag_count)];
+    let mut scratch_buffer = vec![0.0; result.len()];
+    c.bench_function("Autocorr FFT, window 2048, lag 1024", |b| {
+        b.iter(|| {
+            autocorr_fft(
+                black_box(&window[..]),
+                &mut result,
+                &mut scratch_buffer,
+                lag_count,
+            )
+        })
+    });
+}
+
 fn run_detector_benchmark(
     id: &str,
     c: &mut Criterion,
@@ -43,7 +64,7 @@ fn run_detector_benchmark(
     let input_buffer = vec![0.0; window_size];
 
     c.bench_function(id, |b| {
-        b.iter(|| detector.process(black_box(&input_buffer[..]), |_| {}))
+        b.iter(|| detector.process(black_box(&input_buffer[..]), |_, _, _| {}))
     });
 }
 fn detector_benchmarks(c: &mut Criterion) {
@@ -54,5 +75,25 @@ fn detector_benchmarks(c: &mut Criterion) {
     run_detector_benchmark("Window 2048, downsampling 16", c, 2048, 16);
 }
 
-criterion_group!(benches, detector_benchmarks, result_benchmarks);
+fn run_sfnov_benchmark(id: &str, c: &mut Criterion, window_size: usize) {
+    let mut detector = SpectralFluxNoveltyDetector::new(window_size);
+    let input_buffer: Vec<f32> = (0..window_size).map(|i| (i as f32).sin()).collect();
+
+    c.bench_function(id, |b| {
+        b.iter(|| detector.process(black_box(&input_buffer[..]), |_, _| {}))
+    });
+}
+fn sfnov_benchmarks(c: &mut Criterion) {
+    run_sfnov_benchmark("Spectral flux, window 256", c, 256);
+    run_sfnov_benchmark("Spectral flux, window 1024", c, 1024);
+    run_sfnov_benchmark("Spectral flux, window 2048", c, 2048);
+}
+
+criterion_group!(
+    benches,
+    autocorr_benchmarks,
+    detector_benchmarks,
+    result_benchmarks,
+    sfnov_benchmarks
+);
 criterion_main!(benches);
@@ -1,6 +1,12 @@
 use hound;
 
 pub fn read_wav(path: String) -> Result<(u16, Vec<f32>), hound::Error> {
+    let (spec, samples) = read_wav_with_spec(path)?;
+    Ok((spec.channels, samples))
+}
+
+/// Reads the spec and the samples of a WAV file, opening and parsing it once.
+pub fn read_wav_with_spec(path: String) -> Result<(hound::WavSpec, Vec<f32>), hound::Error> {
     let reader = hound::WavReader::open(path);
     match reader {
         Ok(mut reader) => {
@@ -11,7 +17,7 @@ pub fn read_wav(path: String) -> Result<(u16, Vec<f32>), hound::Error> {
                     return (sample.unwrap() as f32) * scale;
                 })
                 .collect();
-            return Ok((reader.spec().channels, samples));
+            return Ok((reader.spec(), samples));
         }
         Err(error) => return Err(error),
     }
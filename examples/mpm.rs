@@ -5,12 +5,7 @@ use dev_helpers::note_number_to_string;
 use dev_helpers::AudioHost;
 use dev_helpers::AudioProcessor;
 
-use microdsp::mpm::MpmPitchDetector;
-
-struct PitchReading {
-    midi_note_number: f32,
-    frequency: f32,
-}
+use microdsp::mpm::{MpmPitchDetector, PitchSnapshot};
 
 struct MPMAudioProcessor {
     pitch_detector: MpmPitchDetector,
@@ -24,21 +19,18 @@ impl MPMAudioProcessor {
     }
 }
 
-impl AudioProcessor<PitchReading> for MPMAudioProcessor {
+impl AudioProcessor<PitchSnapshot> for MPMAudioProcessor {
     fn process(
         &mut self,
         in_buffer: &[f32],
         _: &mut [f32],
         _: usize,
-        to_main_thread: &mut dev_helpers::rtrb::Producer<PitchReading>,
-        _: &mut dev_helpers::rtrb::Consumer<PitchReading>,
+        to_main_thread: &mut dev_helpers::rtrb::Producer<PitchSnapshot>,
+        _: &mut dev_helpers::rtrb::Consumer<PitchSnapshot>,
     ) -> bool {
-        self.pitch_detector.process(in_buffer, |result| {
+        self.pitch_detector.process(in_buffer, |result, _, _| {
             if result.is_tone() {
-                let _ = to_main_thread.push(PitchReading {
-                    midi_note_number: result.midi_note_number,
-                    frequency: result.frequency,
-                });
+                let _ = to_main_thread.push(result.snapshot());
             }
         });
 
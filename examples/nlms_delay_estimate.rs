@@ -53,8 +53,17 @@ fn main() {
     println!("");
     let mut filter = NlmsFilter::new(FILTER_ORDER, MU, EPS);
     let mut e = vec![];
-    for (s2, ds2) in signal_1.iter().zip(delayed_signal_2.iter()) {
+    println!("Time (s)    Mean squared error");
+    println!("------------------------------");
+    for (n, (s2, ds2)) in signal_1.iter().zip(delayed_signal_2.iter()).enumerate() {
         e.push(filter.update(*s2, *ds2));
+        if n % (SAMPLE_RATE as usize) == 0 {
+            println!(
+                "{:<8.1}    {:.7}",
+                (n as f32) / (SAMPLE_RATE as f32),
+                filter.mse()
+            );
+        }
         let mut max_val = 0.0;
         let mut max_idx = 0;
         for (i, h) in filter.h().iter().enumerate() {
@@ -68,6 +77,7 @@ fn main() {
             delay_estimate_histogram[max_idx].max_value = max_val
         }
     }
+    println!();
     let e_output_path = "example_data/nlms_example_delay_estimate_e.wav";
     let _ = wav::write_wav(e_output_path.into(), SAMPLE_RATE, 1, &e);
     println!("Wrote output signal");
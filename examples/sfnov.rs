@@ -1,5 +1,6 @@
 use dev_helpers::rtrb;
 use dev_helpers::{AudioHost, AudioProcessor};
+use microdsp::common::TriggerDetector;
 use microdsp::sfnov::{HardKneeCompression, SpectralFluxNoveltyDetector};
 use std::thread;
 use std::time::Duration;
@@ -32,7 +33,7 @@ impl AudioProcessor<DetectorMessage> for NoveltyDetectorProcessor {
         to_main_thread: &mut rtrb::Producer<DetectorMessage>,
         _: &mut rtrb::Consumer<DetectorMessage>,
     ) -> bool {
-        self.detector.process(in_buffer, |novelty| {
+        self.detector.process(in_buffer, |novelty, _| {
             let _ = to_main_thread.push(DetectorMessage::NoveltyValue(novelty.novelty()));
         });
 
@@ -40,37 +41,13 @@ impl AudioProcessor<DetectorMessage> for NoveltyDetectorProcessor {
     }
 }
 
-struct PeakDetector {
-    threshold: f32,
-    is_armed: bool,
-}
-
-impl PeakDetector {
-    fn new(threshold: f32) -> Self {
-        PeakDetector {
-            threshold,
-            is_armed: true,
-        }
-    }
-
-    fn process(&mut self, input: f32) -> bool {
-        if input > self.threshold && self.is_armed {
-            self.is_armed = false;
-            return true;
-        } else if input < self.threshold {
-            self.is_armed = true;
-        };
-        false
-    }
-}
-
 fn main() {
     let sample_rate = 44100.0;
     let mut audio_host = AudioHost::new(sample_rate, NoveltyDetectorProcessor::new());
     println!("Listening for sounds...");
 
     let poll_interval_ms = 30;
-    let mut novelty_peak_detector = PeakDetector::new(0.4);
+    let mut novelty_peak_detector = TriggerDetector::new(0.4, 0.3);
 
     loop {
         thread::sleep(Duration::from_millis(poll_interval_ms));
@@ -0,0 +1,39 @@
+use dev_helpers::note_number_to_string;
+use dev_helpers::wav;
+use microdsp::common::freq_to_midi_note;
+use microdsp::mpm::MpmPitchDetector;
+use microdsp::sfnov::SpectralFluxNoveltyDetector;
+
+fn main() {
+    // Analyzes a mono WAV file offline and prints detected pitches and onsets.
+    // Pass a path as the first argument to analyze another file.
+    let path = std::env::args()
+        .nth(1)
+        .unwrap_or_else(|| "example_data/voice_1.wav".into());
+
+    let (spec, signal) = wav::read_wav_with_spec(path.clone()).unwrap();
+    if spec.channels != 1 {
+        panic!("Expected a mono WAV file, got {} channels", spec.channels)
+    }
+    let sample_rate = spec.sample_rate as f32;
+    println!("Analyzing {} ({} Hz)", path, spec.sample_rate);
+
+    let mut pitch_detector = MpmPitchDetector::new(sample_rate, 1024, 3 * 256);
+    println!("Pitches:");
+    for (timestamp, frequency) in pitch_detector.analyze_pitches(&signal) {
+        let note_number = freq_to_midi_note(frequency);
+        println!(
+            "  {:.3} s: {:.1} Hz ({})",
+            timestamp,
+            frequency,
+            note_number_to_string(note_number)
+        );
+    }
+
+    let mut novelty_detector = SpectralFluxNoveltyDetector::new(512);
+    novelty_detector.set_sample_rate(sample_rate);
+    println!("Onsets:");
+    for (timestamp, novelty) in novelty_detector.analyze_onsets(&signal) {
+        println!("  {:.3} s: novelty {:.3}", timestamp, novelty);
+    }
+}
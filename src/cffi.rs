@@ -0,0 +1,302 @@
+//! A C API for the pitch and novelty detectors, enabled by the `cffi` feature.
+//! Detectors are created and destroyed through the API and passed around as
+//! opaque pointers, so any number of instances can be used simultaneously.
+//!
+//! The create functions return a null pointer if the detector options are invalid,
+//! instead of panicking across the FFI boundary. All functions taking pointers are
+//! unsafe. Detector pointers must be null or have been returned by the corresponding
+//! create function and not yet destroyed. Functions given a null detector pointer do
+//! nothing and return 0 or false. Sample pointers must point to at least `sample_count`
+//! valid samples.
+
+use alloc::{boxed::Box, vec};
+use core::slice;
+
+use crate::mpm::MpmPitchDetector;
+use crate::sfnov::{HardKneeCompression, SpectralFluxNoveltyDetector};
+
+/// The novelty detector type exposed through the C API.
+pub type NoveltyDetector = SpectralFluxNoveltyDetector<HardKneeCompression>;
+
+unsafe fn samples<'a>(samples: *const f32, sample_count: usize) -> &'a [f32] {
+    if sample_count == 0 {
+        &[]
+    } else {
+        slice::from_raw_parts(samples, sample_count)
+    }
+}
+
+/// Allocates a zero initialized array of `len` `f32` values, e.g for passing
+/// samples from a WASM host. Must be freed using [`microdsp_free_f32_array`].
+#[no_mangle]
+pub extern "C" fn microdsp_allocate_f32_array(len: usize) -> *mut f32 {
+    Box::into_raw(vec![0.; len].into_boxed_slice()) as *mut f32
+}
+
+/// Frees an array allocated using [`microdsp_allocate_f32_array`].
+/// # Safety
+/// `array` must have been returned by [`microdsp_allocate_f32_array`] with the same `len`.
+#[no_mangle]
+pub unsafe extern "C" fn microdsp_free_f32_array(array: *mut f32, len: usize) {
+    drop(Box::from_raw(core::ptr::slice_from_raw_parts_mut(
+        array, len,
+    )));
+}
+
+/// Creates a pitch detector. See [`MpmPitchDetector::new`]. Returns a null
+/// pointer if the window size or hop size is invalid.
+#[no_mangle]
+pub extern "C" fn mpm_create(
+    sample_rate: f32,
+    window_size: usize,
+    hop_size: usize,
+) -> *mut MpmPitchDetector {
+    match MpmPitchDetector::try_new(sample_rate, window_size, hop_size) {
+        Ok(detector) => Box::into_raw(Box::new(detector)),
+        Err(_) => core::ptr::null_mut(),
+    }
+}
+
+/// Destroys a pitch detector. Does nothing if `detector` is null.
+/// # Safety
+/// `detector` must be null or a pointer returned by [`mpm_create`] that has not
+/// been destroyed.
+#[no_mangle]
+pub unsafe extern "C" fn mpm_destroy(detector: *mut MpmPitchDetector) {
+    if detector.is_null() {
+        return;
+    }
+    drop(Box::from_raw(detector));
+}
+
+/// Processes samples. Returns the number of analyzed windows.
+/// The getters return values for the most recent window.
+/// # Safety
+/// `detector` must be null or a pointer returned by [`mpm_create`] that has not
+/// been destroyed. Returns 0 if `detector` is null. See also the [module documentation](self).
+#[no_mangle]
+pub unsafe extern "C" fn mpm_process(
+    detector: *mut MpmPitchDetector,
+    samples_ptr: *const f32,
+    sample_count: usize,
+) -> usize {
+    let detector = match detector.as_mut() {
+        Some(detector) => detector,
+        None => return 0,
+    };
+    let mut window_count = 0;
+    detector.process(samples(samples_ptr, sample_count), |_, _, _| {
+        window_count += 1
+    });
+    window_count
+}
+
+/// # Safety
+/// `detector` must be null or a pointer returned by [`mpm_create`] that has not
+/// been destroyed. Returns 0 if `detector` is null.
+#[no_mangle]
+pub unsafe extern "C" fn mpm_get_frequency(detector: *const MpmPitchDetector) -> f32 {
+    detector
+        .as_ref()
+        .map_or(0.0, |detector| detector.result().frequency)
+}
+
+/// # Safety
+/// `detector` must be null or a pointer returned by [`mpm_create`] that has not
+/// been destroyed. Returns 0 if `detector` is null.
+#[no_mangle]
+pub unsafe extern "C" fn mpm_get_clarity(detector: *const MpmPitchDetector) -> f32 {
+    detector
+        .as_ref()
+        .map_or(0.0, |detector| detector.result().clarity)
+}
+
+/// # Safety
+/// `detector` must be null or a pointer returned by [`mpm_create`] that has not
+/// been destroyed. Returns 0 if `detector` is null.
+#[no_mangle]
+pub unsafe extern "C" fn mpm_get_midi_note_number(detector: *const MpmPitchDetector) -> f32 {
+    detector
+        .as_ref()
+        .map_or(0.0, |detector| detector.result().midi_note_number)
+}
+
+/// # Safety
+/// `detector` must be null or a pointer returned by [`mpm_create`] that has not
+/// been destroyed. Returns 0 if `detector` is null.
+#[no_mangle]
+pub unsafe extern "C" fn mpm_get_window_rms_db(detector: *const MpmPitchDetector) -> f32 {
+    detector
+        .as_ref()
+        .map_or(0.0, |detector| detector.result().window_rms_db())
+}
+
+/// # Safety
+/// `detector` must be null or a pointer returned by [`mpm_create`] that has not
+/// been destroyed. Returns false if `detector` is null.
+#[no_mangle]
+pub unsafe extern "C" fn mpm_get_is_tone(detector: *const MpmPitchDetector) -> bool {
+    match detector.as_ref() {
+        Some(detector) => detector.result().is_tone(),
+        None => false,
+    }
+}
+
+/// Creates a novelty detector. See [`SpectralFluxNoveltyDetector::new`]. Returns
+/// a null pointer if the window size is invalid.
+#[no_mangle]
+pub extern "C" fn sfnov_create(window_size: usize) -> *mut NoveltyDetector {
+    match SpectralFluxNoveltyDetector::try_new(window_size) {
+        Ok(detector) => Box::into_raw(Box::new(detector)),
+        Err(_) => core::ptr::null_mut(),
+    }
+}
+
+/// Destroys a novelty detector. Does nothing if `detector` is null.
+/// # Safety
+/// `detector` must be null or a pointer returned by [`sfnov_create`] that has not
+/// been destroyed.
+#[no_mangle]
+pub unsafe extern "C" fn sfnov_destroy(detector: *mut NoveltyDetector) {
+    if detector.is_null() {
+        return;
+    }
+    drop(Box::from_raw(detector));
+}
+
+/// Processes samples. Returns the number of computed novelty values.
+/// [`sfnov_get_novelty`] returns the most recent value.
+/// # Safety
+/// `detector` must be null or a pointer returned by [`sfnov_create`] that has not
+/// been destroyed. Returns 0 if `detector` is null. See also the [module documentation](self).
+#[no_mangle]
+pub unsafe extern "C" fn sfnov_process(
+    detector: *mut NoveltyDetector,
+    samples_ptr: *const f32,
+    sample_count: usize,
+) -> usize {
+    let detector = match detector.as_mut() {
+        Some(detector) => detector,
+        None => return 0,
+    };
+    let mut novelty_count = 0;
+    detector.process(samples(samples_ptr, sample_count), |_, _| {
+        novelty_count += 1
+    });
+    novelty_count
+}
+
+/// # Safety
+/// `detector` must be null or a pointer returned by [`sfnov_create`] that has not
+/// been destroyed. Returns 0 if `detector` is null.
+#[no_mangle]
+pub unsafe extern "C" fn sfnov_get_novelty(detector: *const NoveltyDetector) -> f32 {
+    detector
+        .as_ref()
+        .map_or(0.0, |detector| detector.novelty().novelty())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use alloc::vec::Vec;
+
+    #[test]
+    fn test_mpm() {
+        let sample_rate = 44100.0;
+        let window_size = 1024;
+        let sample_count = 4 * window_size;
+        let samples = microdsp_allocate_f32_array(sample_count);
+        unsafe {
+            let buffer = slice::from_raw_parts_mut(samples, sample_count);
+            for (i, sample) in buffer.iter_mut().enumerate() {
+                *sample = (2.0 * core::f32::consts::PI * 440.0 * (i as f32) / sample_rate).sin();
+            }
+
+            let detector_1 = mpm_create(sample_rate, window_size, window_size);
+            let detector_2 = mpm_create(sample_rate, window_size, window_size);
+            assert_eq!(mpm_process(detector_1, samples, sample_count), 4);
+            assert_eq!(mpm_process(detector_2, samples, window_size / 2), 0);
+            assert!((mpm_get_frequency(detector_1) - 440.0).abs() < 1.0);
+            assert!((mpm_get_midi_note_number(detector_1) - 69.0).abs() < 0.1);
+            assert!(mpm_get_clarity(detector_1) > 0.9);
+            assert!(mpm_get_is_tone(detector_1));
+            assert_eq!(mpm_get_frequency(detector_2), 0.0);
+            mpm_destroy(detector_1);
+            mpm_destroy(detector_2);
+            microdsp_free_f32_array(samples, sample_count);
+        }
+    }
+
+    #[test]
+    fn test_sfnov() {
+        let window_size = 256;
+        unsafe {
+            let detector = sfnov_create(window_size);
+            assert_eq!(sfnov_process(detector, core::ptr::null(), 0), 0);
+            let samples = [0.5; 1024];
+            // The first window only primes the previous power spectrum
+            assert_eq!(sfnov_process(detector, samples.as_ptr(), samples.len()), 6);
+            assert_eq!(sfnov_get_novelty(detector), 0.0);
+            sfnov_destroy(detector);
+        }
+    }
+
+    #[test]
+    fn test_invalid_options() {
+        assert!(mpm_create(44100.0, 0, 512).is_null());
+        assert!(mpm_create(44100.0, 1024, 2048).is_null());
+        assert!(sfnov_create(1000).is_null());
+        let samples = [0.5; 1024];
+        unsafe {
+            let detector = core::ptr::null_mut();
+            assert_eq!(mpm_process(detector, samples.as_ptr(), samples.len()), 0);
+            assert_eq!(mpm_get_frequency(detector), 0.0);
+            assert_eq!(mpm_get_clarity(detector), 0.0);
+            assert_eq!(mpm_get_midi_note_number(detector), 0.0);
+            assert_eq!(mpm_get_window_rms_db(detector), 0.0);
+            assert!(!mpm_get_is_tone(detector));
+            mpm_destroy(detector);
+
+            let detector = core::ptr::null_mut();
+            assert_eq!(sfnov_process(detector, samples.as_ptr(), samples.len()), 0);
+            assert_eq!(sfnov_get_novelty(detector), 0.0);
+            sfnov_destroy(detector);
+        }
+    }
+
+    #[test]
+    fn test_independent_mpm_handles() {
+        let sample_rate = 44100.0;
+        let window_size = 1024;
+        let sine = |frequency: f32| -> Vec<f32> {
+            (0..4 * window_size)
+                .map(|i| (2.0 * core::f32::consts::PI * frequency * (i as f32) / sample_rate).sin())
+                .collect()
+        };
+        let low = sine(220.0);
+        let high = sine(440.0);
+        unsafe {
+            let low_detector = mpm_create(sample_rate, window_size, window_size);
+            let high_detector = mpm_create(sample_rate, window_size, window_size / 2);
+            // Feed the detectors alternately, in chunks not aligned with the windows
+            let mut low_window_count = 0;
+            let mut high_window_count = 0;
+            for (low_chunk, high_chunk) in low.chunks(300).zip(high.chunks(300)) {
+                low_window_count += mpm_process(low_detector, low_chunk.as_ptr(), low_chunk.len());
+                high_window_count +=
+                    mpm_process(high_detector, high_chunk.as_ptr(), high_chunk.len());
+            }
+            assert_eq!(low_window_count, 4);
+            assert_eq!(high_window_count, 7);
+            assert!((mpm_get_frequency(low_detector) - 220.0).abs() < 1.0);
+            assert!((mpm_get_frequency(high_detector) - 440.0).abs() < 1.0);
+
+            // Destroying one detector leaves the other usable
+            mpm_destroy(low_detector);
+            assert_eq!(mpm_process(high_detector, low.as_ptr(), low.len()), 8);
+            assert!((mpm_get_frequency(high_detector) - 220.0).abs() < 1.0);
+            mpm_destroy(high_detector);
+        }
+    }
+}
@@ -1,4 +1,5 @@
-use super::fft::real_fft;
+use super::fft::RealFft;
+use super::simd::norm_sqr;
 
 /// Computes the length of the FFT needed to compute the autocorrelation
 /// for a given window size and lag count to avoid circular convolution effects.
@@ -31,9 +32,28 @@ pub fn autocorr_fft(
     result: &mut [f32],
     scratch_buffer: &mut [f32],
     lag_count: usize,
+) {
+    let fft = RealFft::new(autocorr_fft_size(buffer.len(), lag_count));
+    autocorr_fft_with(&fft, buffer, result, scratch_buffer, lag_count, None);
+}
+
+/// Like [`autocorr_fft`], but uses a given transform of size
+/// `autocorr_fft_size(buffer.len(), lag_count)`, avoiding selecting one on every call.
+/// If `whitening_half_width` is not `None`, the power spectrum is whitened before
+/// the inverse transform. See `whiten`.
+pub(crate) fn autocorr_fft_with(
+    fft: &RealFft,
+    buffer: &[f32],
+    result: &mut [f32],
+    scratch_buffer: &mut [f32],
+    lag_count: usize,
+    whitening_half_width: Option<usize>,
 ) {
     // Sanity checks
     let fft_size = autocorr_fft_size(buffer.len(), lag_count);
+    if fft.size() != fft_size {
+        panic!("Got fft of size {}, expected {}.", fft.size(), fft_size)
+    }
     if result.len() != fft_size {
         panic!(
             "Got autocorr fft buffer of length {}, expected {}.",
@@ -52,20 +72,32 @@ pub fn autocorr_fft(
     }
 
     // Perform the FFT in place
-    let fft = real_fft(&mut result[..]);
+    let spectrum = fft.process(&mut result[..]);
 
     // Compute the power spectral density by point-wise multiplication by the complex conjugate.
-    scratch_buffer[0] = fft[0].re * fft[0].re;
+    scratch_buffer[0] = spectrum[0].re * spectrum[0].re;
     let scratch_buffer_length = scratch_buffer.len();
-    for (index, fft_value) in fft.iter_mut().skip(1).enumerate() {
-        let norm_sq = fft_value.norm_sqr();
-        scratch_buffer[index + 1] = norm_sq;
-        scratch_buffer[scratch_buffer_length - index - 1] = norm_sq;
+    norm_sqr(&spectrum[1..], &mut scratch_buffer[1..spectrum.len()]);
+    let nyquist_bin = spectrum.len();
+    scratch_buffer[nyquist_bin] = spectrum[0].im * spectrum[0].im;
+    if let Some(half_width) = whitening_half_width {
+        // The spectrum is no longer needed, so the result buffer can hold the smoothed power
+        whiten(
+            &mut scratch_buffer[..=nyquist_bin],
+            &mut result[..=nyquist_bin],
+            half_width,
+        );
+    }
+    for index in 1..nyquist_bin {
+        scratch_buffer[scratch_buffer_length - index] = scratch_buffer[index];
     }
-    scratch_buffer[fft.len()] = fft[0].im * fft[0].im;
 
     // 2. Compute the inverse FFT in place to get the autocorrelation (up to a scaling factor)
-    let ifft = real_fft(&mut scratch_buffer[..]);
+    let ifft = if scratch_buffer_length == fft_size {
+        fft.process(&mut scratch_buffer[..])
+    } else {
+        RealFft::new(scratch_buffer_length).process(&mut scratch_buffer[..])
+    };
 
     // Apply scaling factor
     let scale = 1.0 / (fft_size as f32);
@@ -74,9 +106,74 @@ pub fn autocorr_fft(
     }
 }
 
+/// Divides each bin of a one sided power spectrum by the mean power of the bins at most
+/// `half_width` bins away, which is equivalent to dividing each magnitude by the RMS of
+/// the neighbouring magnitudes before squaring. This flattens the spectral envelope while
+/// preserving narrow peaks such as harmonics. `smoothed` is used for temporary storage
+/// and must have the same length as `power`.
+fn whiten(power: &mut [f32], smoothed: &mut [f32], half_width: usize) {
+    let bin_count = power.len();
+    // Compute a moving average using a running sum
+    let mut sum: f32 = power.iter().take(half_width + 1).sum();
+    for (index, smoothed) in smoothed.iter_mut().enumerate() {
+        let first = index.saturating_sub(half_width);
+        let last = (index + half_width).min(bin_count - 1);
+        *smoothed = sum / ((last - first + 1) as f32);
+        if index + half_width + 1 < bin_count {
+            sum += power[index + half_width + 1];
+        }
+        if index >= half_width {
+            sum -= power[index - half_width];
+        }
+    }
+    for (power, smoothed) in power.iter_mut().zip(smoothed.iter()) {
+        *power = if *smoothed > 0.0 {
+            *power / *smoothed
+        } else {
+            0.0
+        };
+    }
+}
+
+/// Like [`autocorr_fft`], but normalizes the first `lag_count` values of the
+/// result by the lag 0 value, which becomes 1. If the input buffer is all zeros,
+/// the result is all zeros.
+pub fn autocorr_fft_normalized(
+    buffer: &[f32],
+    result: &mut [f32],
+    scratch_buffer: &mut [f32],
+    lag_count: usize,
+) {
+    autocorr_fft(buffer, result, scratch_buffer, lag_count);
+    let lag_0 = result[0];
+    if lag_0 > 0.0 {
+        let scale = 1.0 / lag_0;
+        for value in result.iter_mut().take(lag_count) {
+            *value *= scale;
+        }
+    } else {
+        for value in result.iter_mut().take(lag_count) {
+            *value = 0.0;
+        }
+    }
+}
+
+/// Autocorrelation scaling used by [`autocorr_conv`].
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum AutocorrScaling {
+    /// The raw sum of products for each lag. Longer lags are biased towards zero,
+    /// since fewer products contribute to them.
+    Biased,
+    /// Each lag tau is divided by the number of products, `window_size - tau`.
+    Unbiased,
+    /// Each lag is divided by the lag 0 value, which becomes 1. All zero
+    /// input gives an all zero result.
+    Coefficient,
+}
+
 /// Computes the [autocorrelation](https://en.wikipedia.org/wiki/Autocorrelation)
 /// of a given buffer using time domain convolution.
-pub fn autocorr_conv(window: &[f32], result: &mut [f32]) {
+pub fn autocorr_conv(window: &[f32], result: &mut [f32], scaling: AutocorrScaling) {
     let window_size = window.len();
     if window_size < result.len() {
         panic!("Result vector must not be longer than the window.");
@@ -93,7 +190,17 @@ pub fn autocorr_conv(window: &[f32], result: &mut [f32]) {
             let xj_plus_tau = window[j + tau];
             sum += xj * xj_plus_tau;
         }
-        result[tau] = sum;
+        result[tau] = match scaling {
+            AutocorrScaling::Unbiased => sum / ((window_size - tau) as f32),
+            _ => sum,
+        };
+    }
+
+    if scaling == AutocorrScaling::Coefficient && lag_count > 0 {
+        let lag_0 = result[0];
+        for value in result.iter_mut() {
+            *value = if lag_0 > 0.0 { *value / lag_0 } else { 0.0 };
+        }
     }
 }
 
@@ -102,7 +209,30 @@ mod tests {
     use alloc::vec;
     use alloc::vec::Vec;
 
-    use super::{autocorr_conv, autocorr_fft, autocorr_fft_size};
+    use super::{
+        autocorr_conv, autocorr_fft, autocorr_fft_normalized, autocorr_fft_size, whiten,
+        AutocorrScaling,
+    };
+
+    #[test]
+    fn test_whiten() {
+        // A power spectrum with a sloping envelope and a peak in bin 10
+        let mut power: Vec<f32> = (0..33).map(|i| 100.0 * 0.9_f32.powi(i)).collect();
+        power[10] *= 10.0;
+        power[0] = 0.0;
+        let mut smoothed = vec![0.0; power.len()];
+        whiten(&mut power, &mut smoothed, 2);
+
+        // The envelope is flattened, but the peak is preserved
+        assert_eq!(power[0], 0.0);
+        for (index, value) in power.iter().enumerate().take(31).skip(3) {
+            if (8..=12).contains(&index) {
+                continue;
+            }
+            assert!((value - 1.0).abs() < 0.1, "bin {}: {}", index, value);
+        }
+        assert!(power[10] > 3.0);
+    }
 
     #[test]
     fn test_autocorr_fft() {
@@ -114,7 +244,11 @@ mod tests {
         let window: Vec<f32> = vec![1.0, 2.0, 3.0, 4.0, 5.0, 6.0, 7.0, 8.0];
         let lag_count = 4;
         let mut autocorr_reference: Vec<f32> = vec![0.0; lag_count];
-        autocorr_conv(&window[..], &mut autocorr_reference[..]);
+        autocorr_conv(
+            &window[..],
+            &mut autocorr_reference[..],
+            AutocorrScaling::Biased,
+        );
 
         let fft_size = autocorr_fft_size(window.len(), lag_count);
         let mut fft_buffer: Vec<f32> = vec![0.0; fft_size];
@@ -131,4 +265,60 @@ mod tests {
             assert!((*reference - fft_value).abs() <= epsilon);
         }
     }
+
+    #[test]
+    fn test_autocorr_fft_normalized() {
+        let window: Vec<f32> = vec![1.0, 2.0, 3.0, 4.0, 5.0, 6.0, 7.0, 8.0];
+        let lag_count = 4;
+        let fft_size = autocorr_fft_size(window.len(), lag_count);
+        let mut fft_buffer: Vec<f32> = vec![0.0; fft_size];
+        let mut scratch_buffer: Vec<f32> = vec![0.0; fft_size];
+        autocorr_fft_normalized(
+            &window[..],
+            &mut fft_buffer[..],
+            &mut scratch_buffer[..],
+            lag_count,
+        );
+
+        let expected = [1.0, 168.0 / 204.0, 133.0 / 204.0, 100.0 / 204.0];
+        let epsilon = 1e-5;
+        for (expected, value) in expected.iter().zip(fft_buffer.iter()) {
+            assert!((*expected - value).abs() <= epsilon);
+        }
+
+        // All zero input
+        let window: Vec<f32> = vec![0.0; 8];
+        autocorr_fft_normalized(
+            &window[..],
+            &mut fft_buffer[..],
+            &mut scratch_buffer[..],
+            lag_count,
+        );
+        assert!(fft_buffer.iter().take(lag_count).all(|value| *value == 0.0));
+    }
+
+    #[test]
+    fn test_autocorr_conv_scaling() {
+        let window: Vec<f32> = vec![1.0, 2.0, 3.0, 4.0];
+        let mut result: Vec<f32> = vec![0.0; 4];
+        let epsilon = 1e-6;
+        let cases = [
+            (AutocorrScaling::Biased, [30.0, 20.0, 11.0, 4.0]),
+            (AutocorrScaling::Unbiased, [7.5, 20.0 / 3.0, 5.5, 4.0]),
+            (
+                AutocorrScaling::Coefficient,
+                [1.0, 20.0 / 30.0, 11.0 / 30.0, 4.0 / 30.0],
+            ),
+        ];
+        for (scaling, expected) in cases {
+            autocorr_conv(&window[..], &mut result[..], scaling);
+            for (expected, value) in expected.iter().zip(result.iter()) {
+                assert!((*expected - value).abs() <= epsilon);
+            }
+        }
+
+        // All zero input
+        autocorr_conv(&[0.0; 4], &mut result[..], AutocorrScaling::Coefficient);
+        assert!(result.iter().all(|value| *value == 0.0));
+    }
 }
@@ -0,0 +1,175 @@
+use core::f32::consts::PI;
+use micromath::F32Ext;
+
+/// A second order [IIR filter](https://en.wikipedia.org/wiki/Digital_biquad_filter)
+/// implemented using the transposed direct form II. The coefficient constructors
+/// follow the [Audio EQ Cookbook](https://www.w3.org/TR/audio-eq-cookbook/).
+pub struct Biquad {
+    b0: f32,
+    b1: f32,
+    b2: f32,
+    a1: f32,
+    a2: f32,
+    z1: f32,
+    z2: f32,
+}
+
+impl Biquad {
+    /// Creates a new `Biquad` instance from the coefficients of the transfer function
+    /// H(z) = (b0 + b1 z^-1 + b2 z^-2) / (a0 + a1 z^-1 + a2 z^-2).
+    /// `a0` must not be zero.
+    pub fn new(b0: f32, b1: f32, b2: f32, a0: f32, a1: f32, a2: f32) -> Self {
+        if a0 == 0.0 {
+            panic!("Biquad coefficient a0 must not be zero")
+        }
+        Biquad {
+            b0: b0 / a0,
+            b1: b1 / a0,
+            b2: b2 / a0,
+            a1: a1 / a0,
+            a2: a2 / a0,
+            z1: 0.0,
+            z2: 0.0,
+        }
+    }
+
+    /// Creates a low pass filter with a given cutoff frequency in Hz and Q.
+    pub fn lowpass(sample_rate: f32, cutoff: f32, q: f32) -> Self {
+        let (cos_w0, alpha) = Biquad::intermediates(sample_rate, cutoff, q);
+        Biquad::new(
+            0.5 * (1.0 - cos_w0),
+            1.0 - cos_w0,
+            0.5 * (1.0 - cos_w0),
+            1.0 + alpha,
+            -2.0 * cos_w0,
+            1.0 - alpha,
+        )
+    }
+
+    /// Creates a high pass filter with a given cutoff frequency in Hz and Q.
+    pub fn highpass(sample_rate: f32, cutoff: f32, q: f32) -> Self {
+        let (cos_w0, alpha) = Biquad::intermediates(sample_rate, cutoff, q);
+        Biquad::new(
+            0.5 * (1.0 + cos_w0),
+            -(1.0 + cos_w0),
+            0.5 * (1.0 + cos_w0),
+            1.0 + alpha,
+            -2.0 * cos_w0,
+            1.0 - alpha,
+        )
+    }
+
+    /// Creates a band pass filter with a given center frequency in Hz and Q.
+    /// The gain at the center frequency is 0 dB.
+    pub fn bandpass(sample_rate: f32, center: f32, q: f32) -> Self {
+        let (cos_w0, alpha) = Biquad::intermediates(sample_rate, center, q);
+        Biquad::new(alpha, 0.0, -alpha, 1.0 + alpha, -2.0 * cos_w0, 1.0 - alpha)
+    }
+
+    /// Creates a notch filter with a given center frequency in Hz and Q.
+    pub fn notch(sample_rate: f32, center: f32, q: f32) -> Self {
+        let (cos_w0, alpha) = Biquad::intermediates(sample_rate, center, q);
+        Biquad::new(
+            1.0,
+            -2.0 * cos_w0,
+            1.0,
+            1.0 + alpha,
+            -2.0 * cos_w0,
+            1.0 - alpha,
+        )
+    }
+
+    /// Returns cos(w0) and alpha as defined in the Audio EQ Cookbook.
+    fn intermediates(sample_rate: f32, frequency: f32, q: f32) -> (f32, f32) {
+        if q <= 0.0 {
+            panic!("Biquad Q must be greater than 0")
+        }
+        if frequency <= 0.0 || frequency >= 0.5 * sample_rate {
+            panic!("Biquad frequency must be in the range (0, sample_rate / 2)")
+        }
+        let w0 = 2.0 * PI * frequency / sample_rate;
+        (F32Ext::cos(w0), F32Ext::sin(w0) / (2.0 * q))
+    }
+
+    /// Clears the filter state.
+    pub fn reset(&mut self) {
+        self.z1 = 0.0;
+        self.z2 = 0.0;
+    }
+
+    /// Filters a single sample.
+    pub fn process_sample(&mut self, input: f32) -> f32 {
+        let output = self.b0 * input + self.z1;
+        self.z1 = self.b1 * input - self.a1 * output + self.z2;
+        self.z2 = self.b2 * input - self.a2 * output;
+        output
+    }
+
+    /// Filters a buffer of samples in place.
+    pub fn process_buffer(&mut self, buffer: &mut [f32]) {
+        for sample in buffer.iter_mut() {
+            *sample = self.process_sample(*sample);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use alloc::vec::Vec;
+
+    /// Returns the steady state peak output level for a unit amplitude sine input.
+    fn sine_gain(filter: &mut Biquad, frequency: f32, sample_rate: f32) -> f32 {
+        filter.reset();
+        let len = sample_rate as usize;
+        let mut buffer: Vec<f32> = (0..len)
+            .map(|i| (2.0 * PI * frequency * (i as f32) / sample_rate).sin())
+            .collect();
+        filter.process_buffer(&mut buffer);
+        buffer[len / 2..]
+            .iter()
+            .fold(0.0, |peak: f32, sample| peak.max(sample.abs()))
+    }
+
+    #[test]
+    fn test_dc_gain() {
+        let sample_rate = 44100.0;
+        let dc_gain = |filter: &mut Biquad| {
+            let mut output = 0.0;
+            for _ in 0..10000 {
+                output = filter.process_sample(1.0);
+            }
+            output
+        };
+        assert!((dc_gain(&mut Biquad::lowpass(sample_rate, 1000.0, 0.707)) - 1.0).abs() < 1e-4);
+        assert!(dc_gain(&mut Biquad::highpass(sample_rate, 1000.0, 0.707)).abs() < 1e-4);
+        assert!(dc_gain(&mut Biquad::bandpass(sample_rate, 1000.0, 1.0)).abs() < 1e-4);
+        assert!((dc_gain(&mut Biquad::notch(sample_rate, 1000.0, 1.0)) - 1.0).abs() < 1e-4);
+    }
+
+    #[test]
+    fn test_notch() {
+        let sample_rate = 44100.0;
+        let mut filter = Biquad::notch(sample_rate, 1000.0, 2.0);
+        // The depth of the null is limited by the accuracy of the approximate cosine
+        assert!(sine_gain(&mut filter, 1000.0, sample_rate) < 0.02);
+        assert!(sine_gain(&mut filter, 900.0, sample_rate) > 0.1);
+        assert!(sine_gain(&mut filter, 1100.0, sample_rate) > 0.1);
+        assert!((sine_gain(&mut filter, 100.0, sample_rate) - 1.0).abs() < 0.01);
+        assert!((sine_gain(&mut filter, 10000.0, sample_rate) - 1.0).abs() < 0.01);
+    }
+
+    #[test]
+    fn test_bandpass() {
+        let sample_rate = 44100.0;
+        let mut filter = Biquad::bandpass(sample_rate, 1000.0, 2.0);
+        assert!((sine_gain(&mut filter, 1000.0, sample_rate) - 1.0).abs() < 0.01);
+        assert!(sine_gain(&mut filter, 100.0, sample_rate) < 0.1);
+    }
+
+    #[test]
+    #[should_panic]
+    fn test_invalid_q() {
+        Biquad::lowpass(44100.0, 1000.0, 0.0);
+    }
+}
@@ -0,0 +1,55 @@
+use micromath::F32Ext;
+
+use super::midi::exp2;
+
+/// The lowest level in dB returned by [`to_db`].
+pub const DB_FLOOR: f32 = -120.0;
+
+/// Converts a linear level to dB relative to 1, i.e 0 dB corresponds to a level of 1.
+/// Levels below the one corresponding to [`DB_FLOOR`], including zero, give `DB_FLOOR`.
+pub fn to_db(level: f32) -> f32 {
+    let level = F32Ext::abs(level);
+    if level <= 1e-6 {
+        return DB_FLOOR;
+    }
+    // The approximate log10 function is less accurate below 1,
+    // so use log10(x) = -log10(1 / x) in that case.
+    let db = if level < 1.0 {
+        -20.0 * F32Ext::log10(1.0 / level)
+    } else {
+        20.0 * F32Ext::log10(level)
+    };
+    db.max(DB_FLOOR)
+}
+
+/// Converts a level in dB relative to 1 to a linear level. The inverse of [`to_db`].
+pub fn from_db(db: f32) -> f32 {
+    // 10^(db / 20) = 2^(log2(10) * db / 20)
+    exp2(core::f32::consts::LOG2_10 * db / 20.0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_to_db() {
+        assert_eq!(to_db(0.0), DB_FLOOR);
+        assert_eq!(to_db(1e-9), DB_FLOOR);
+        assert!(to_db(1.0).abs() < 1e-3);
+        for level in [1e-5_f32, 0.001, 0.1, 0.5, 0.9, 2.0, 10.0] {
+            let error = (to_db(level) - 20.0 * level.log10()).abs();
+            assert!(error < 0.01);
+            assert!((to_db(-level) - to_db(level)).abs() < f32::EPSILON);
+        }
+    }
+
+    #[test]
+    fn test_from_db() {
+        for db in [-100.0_f32, -60.0, -6.0, 0.0, 6.0, 20.0] {
+            let expected = 10.0_f32.powf(db / 20.0);
+            assert!((from_db(db) - expected).abs() / expected < 1e-5);
+            assert!((to_db(from_db(db)) - db).abs() < 0.01);
+        }
+    }
+}
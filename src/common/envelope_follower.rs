@@ -0,0 +1,158 @@
+use micromath::F32Ext;
+
+/// The level measure tracked by an [`EnvelopeFollower`].
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum EnvelopeMode {
+    /// Tracks the absolute value of the input.
+    Peak,
+    /// Tracks the [root mean square](https://en.wikipedia.org/wiki/Root_mean_square)
+    /// of the input, i.e the square root of the smoothed squared input.
+    Rms,
+}
+
+/// A streaming envelope follower with separate attack and release time constants,
+/// e.g for level metering or gating. The envelope moves towards rising input levels
+/// using the attack time constant and towards falling levels using the release time constant.
+pub struct EnvelopeFollower {
+    mode: EnvelopeMode,
+    attack_coefficient: f32,
+    release_coefficient: f32,
+    /// The smoothed absolute or squared input, depending on the mode.
+    state: f32,
+}
+
+impl EnvelopeFollower {
+    /// Creates a new `EnvelopeFollower` instance.
+    /// # Arguments
+    ///
+    /// * `mode` - The level measure to track.
+    /// * `attack` - The attack time constant in samples. 0 means that the envelope follows rising levels instantly.
+    /// * `release` - The release time constant in samples. 0 means that the envelope follows falling levels instantly.
+    pub fn new(mode: EnvelopeMode, attack: f32, release: f32) -> Self {
+        EnvelopeFollower {
+            mode,
+            attack_coefficient: EnvelopeFollower::coefficient(attack),
+            release_coefficient: EnvelopeFollower::coefficient(release),
+            state: 0.0,
+        }
+    }
+
+    /// Creates a new `EnvelopeFollower` instance with attack and release time constants
+    /// in seconds at a given sample rate.
+    pub fn from_seconds(mode: EnvelopeMode, attack: f32, release: f32, sample_rate: f32) -> Self {
+        EnvelopeFollower::new(mode, attack * sample_rate, release * sample_rate)
+    }
+
+    /// Returns the one pole smoothing coefficient for a time constant in samples.
+    fn coefficient(time_constant: f32) -> f32 {
+        if time_constant < 0.0 {
+            panic!("Envelope time constants must not be negative")
+        }
+        if time_constant == 0.0 {
+            0.0
+        } else {
+            F32Ext::exp(-1.0 / time_constant)
+        }
+    }
+
+    /// Returns the tracked level measure.
+    pub fn mode(&self) -> EnvelopeMode {
+        self.mode
+    }
+
+    /// Returns the current envelope value.
+    pub fn value(&self) -> f32 {
+        match self.mode {
+            EnvelopeMode::Peak => self.state,
+            EnvelopeMode::Rms => F32Ext::sqrt(self.state),
+        }
+    }
+
+    /// Resets the envelope to 0.
+    pub fn reset(&mut self) {
+        self.state = 0.0;
+    }
+
+    /// Processes a single sample and returns the updated envelope value.
+    pub fn process_sample(&mut self, x: f32) -> f32 {
+        let input = match self.mode {
+            EnvelopeMode::Peak => F32Ext::abs(x),
+            EnvelopeMode::Rms => x * x,
+        };
+        let coefficient = if input > self.state {
+            self.attack_coefficient
+        } else {
+            self.release_coefficient
+        };
+        self.state = input + coefficient * (self.state - input);
+        self.value()
+    }
+
+    /// Processes a buffer of samples, replacing each sample with the envelope value.
+    pub fn process_buffer(&mut self, buffer: &mut [f32]) {
+        for sample in buffer.iter_mut() {
+            *sample = self.process_sample(*sample);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use alloc::vec;
+
+    #[test]
+    fn test_instant() {
+        let mut follower = EnvelopeFollower::new(EnvelopeMode::Peak, 0.0, 0.0);
+        assert_eq!(follower.process_sample(-0.5), 0.5);
+        assert_eq!(follower.process_sample(0.25), 0.25);
+    }
+
+    #[test]
+    fn test_attack_and_release() {
+        let sample_rate = 1000.0;
+        let mut follower =
+            EnvelopeFollower::from_seconds(EnvelopeMode::Peak, 0.01, 0.1, sample_rate);
+
+        // After one attack time constant, the envelope has risen by about 63%
+        let mut buffer = vec![1.0; 10];
+        follower.process_buffer(&mut buffer);
+        assert!((buffer[9] - 0.632).abs() < 0.02);
+        for _ in 0..100 {
+            follower.process_sample(1.0);
+        }
+        assert!((follower.value() - 1.0).abs() < 1e-3);
+
+        // Release is ten times slower
+        for _ in 0..10 {
+            follower.process_sample(0.0);
+        }
+        assert!((follower.value() - 0.905).abs() < 0.02);
+
+        follower.reset();
+        assert_eq!(follower.value(), 0.0);
+    }
+
+    #[test]
+    fn test_rms() {
+        let sample_rate = 44100.0;
+        let mut follower =
+            EnvelopeFollower::from_seconds(EnvelopeMode::Rms, 0.05, 0.05, sample_rate);
+        let mut peak_follower =
+            EnvelopeFollower::from_seconds(EnvelopeMode::Peak, 0.0, 0.05, sample_rate);
+        for i in 0..44100 {
+            let x = (2.0 * core::f32::consts::PI * 440.0 * (i as f32) / sample_rate).sin();
+            follower.process_sample(x);
+            peak_follower.process_sample(x);
+        }
+        // micromath's sqrt is approximate
+        assert!((follower.value() - core::f32::consts::FRAC_1_SQRT_2).abs() < 0.05);
+        assert!(peak_follower.value() > 0.95);
+    }
+
+    #[test]
+    #[should_panic]
+    fn test_negative_time_constant() {
+        EnvelopeFollower::new(EnvelopeMode::Peak, -1.0, 0.0);
+    }
+}
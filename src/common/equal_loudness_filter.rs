@@ -0,0 +1,224 @@
+use crate::Error;
+
+/// An [IIR filter](https://en.wikipedia.org/wiki/Infinite_impulse_response) with
+/// `L` feedforward and `L` feedback coefficients, implemented using the
+/// direct form I difference equation.
+struct IirFilter<const L: usize> {
+    /// Feedforward coefficients.
+    b_coeffs: [f32; L],
+    /// Feedback coefficients. The first coefficient is assumed to be 1.
+    a_coeffs: [f32; L],
+    /// Ring buffer of the most recent inputs.
+    inputs: [f32; L],
+    /// Ring buffer of the most recent outputs.
+    outputs: [f32; L],
+    /// The ring buffer index of the most recent input and output.
+    position: usize,
+}
+
+impl<const L: usize> IirFilter<L> {
+    fn new(b_coeffs: [f32; L], a_coeffs: [f32; L]) -> Self {
+        IirFilter {
+            b_coeffs,
+            a_coeffs,
+            inputs: [0.; L],
+            outputs: [0.; L],
+            position: 0,
+        }
+    }
+
+    fn reset(&mut self) {
+        self.inputs = [0.; L];
+        self.outputs = [0.; L];
+        self.position = 0;
+    }
+
+    fn process_sample(&mut self, input: f32) -> f32 {
+        self.position = (self.position + 1) % L;
+        self.inputs[self.position] = input;
+
+        // y[n] = b0 x[n] + ... + bk x[n - k] - a1 y[n - 1] - ... - ak y[n - k]
+        let mut output = self.b_coeffs[0] * input;
+        for k in 1..L {
+            let index = (self.position + L - k) % L;
+            output +=
+                self.b_coeffs[k] * self.inputs[index] - self.a_coeffs[k] * self.outputs[index];
+        }
+        self.outputs[self.position] = output;
+        output
+    }
+}
+
+/// An approximate inverse [equal-loudness contour](https://en.wikipedia.org/wiki/Equal-loudness_contour)
+/// filter, emphasizing frequencies according to how loud they are perceived.
+/// Consists of a 10th order Yule-Walk filter followed by a 2nd order Butterworth
+/// high pass filter with a cutoff of 150 Hz, using the coefficients from the
+/// [ReplayGain](https://en.wikipedia.org/wiki/ReplayGain) reference implementation.
+pub struct EqualLoudnessFilter {
+    yule_walk: IirFilter<11>,
+    butterworth: IirFilter<3>,
+}
+
+impl EqualLoudnessFilter {
+    /// Creates a new `EqualLoudnessFilter` instance.
+    /// # Arguments
+    ///
+    /// * `sample_rate` - The sample rate in Hz. Must be 44100 or 48000.
+    pub fn new(sample_rate: f32) -> Self {
+        EqualLoudnessFilter::try_new(sample_rate).unwrap_or_else(|error| panic!("{}", error))
+    }
+
+    /// Like `new`, but returns an error instead of panicking if the sample rate is not supported.
+    #[allow(clippy::excessive_precision)]
+    pub fn try_new(sample_rate: f32) -> Result<Self, Error> {
+        let (yule_walk, butterworth) = if sample_rate == 44100. {
+            (
+                IirFilter::new(
+                    [
+                        0.05418656406430,
+                        -0.02911007808948,
+                        -0.00848709379851,
+                        -0.00851165645469,
+                        -0.00834990904936,
+                        0.02245293253339,
+                        -0.02596338512915,
+                        0.01624864962975,
+                        -0.00240879051584,
+                        0.00674613682247,
+                        -0.00187763777362,
+                    ],
+                    [
+                        1.00000000000000,
+                        -3.47845948550071,
+                        6.36317777566148,
+                        -8.54751527471874,
+                        9.47693607801280,
+                        -8.81498681370155,
+                        6.85401540936998,
+                        -4.39470996079559,
+                        2.19611684890774,
+                        -0.75104302451432,
+                        0.13149317958808,
+                    ],
+                ),
+                IirFilter::new(
+                    [0.98500175787242, -1.97000351574484, 0.98500175787242],
+                    [1.00000000000000, -1.96977855582618, 0.97022847566350],
+                ),
+            )
+        } else if sample_rate == 48000. {
+            (
+                IirFilter::new(
+                    [
+                        0.03857599435200,
+                        -0.02160367184185,
+                        -0.00123395316851,
+                        -0.00009291677959,
+                        -0.01655260341619,
+                        0.02161526843274,
+                        -0.02074045215285,
+                        0.00594298065125,
+                        0.00306428023191,
+                        0.00012025322027,
+                        0.00288463683916,
+                    ],
+                    [
+                        1.00000000000000,
+                        -3.84664617118067,
+                        7.81501653005538,
+                        -11.34170355132042,
+                        13.05504219327545,
+                        -12.28759895145294,
+                        9.48293806319790,
+                        -5.87257861775999,
+                        2.75465861874613,
+                        -0.86984376593551,
+                        0.13919314567432,
+                    ],
+                ),
+                IirFilter::new(
+                    [0.98621192462708, -1.97242384925416, 0.98621192462708],
+                    [1.00000000000000, -1.97223372919527, 0.97261396931306],
+                ),
+            )
+        } else {
+            return Err(Error::UnsupportedSampleRate(sample_rate));
+        };
+
+        Ok(EqualLoudnessFilter {
+            yule_walk,
+            butterworth,
+        })
+    }
+
+    /// Clears the filter state.
+    pub fn reset(&mut self) {
+        self.yule_walk.reset();
+        self.butterworth.reset();
+    }
+
+    /// Filters a single sample.
+    pub fn process_sample(&mut self, input: f32) -> f32 {
+        let yule_walk_output = self.yule_walk.process_sample(input);
+        self.butterworth.process_sample(yule_walk_output)
+    }
+
+    /// Filters a buffer of samples. Input and output buffers must have the same length.
+    pub fn process(&mut self, input_samples: &[f32], output_samples: &mut [f32]) {
+        if input_samples.len() != output_samples.len() {
+            panic!("Equal loudness filter input and output buffers must have the same length")
+        }
+        for (input, output) in input_samples.iter().zip(output_samples.iter_mut()) {
+            *output = self.process_sample(*input);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use alloc::{vec, vec::Vec};
+    use core::f32::consts::PI;
+
+    fn gain_db(filter: &mut EqualLoudnessFilter, frequency: f32, sample_rate: f32) -> f32 {
+        filter.reset();
+        let len = sample_rate as usize;
+        let input: Vec<f32> = (0..len)
+            .map(|i| (2.0 * PI * frequency * (i as f32) / sample_rate).sin())
+            .collect();
+        let mut output = vec![0.0; len];
+        filter.process(&input, &mut output);
+        // Skip the transient
+        let rms = |buffer: &[f32]| {
+            let tail = &buffer[len / 2..];
+            (tail.iter().map(|x| x * x).sum::<f32>() / (tail.len() as f32)).sqrt()
+        };
+        20.0 * (rms(&output) / rms(&input)).log10()
+    }
+
+    #[test]
+    fn test_frequency_response() {
+        // Expected gains in dB computed from the filter coefficients
+        let expected_gains = [
+            (50.0, -26.6),
+            (100.0, -15.3),
+            (1000.0, -8.3),
+            (3500.0, -0.7),
+            (8000.0, -15.6),
+            (12000.0, -23.5),
+        ];
+        for sample_rate in [44100.0, 48000.0] {
+            let mut filter = EqualLoudnessFilter::new(sample_rate);
+            for (frequency, expected_gain) in expected_gains {
+                let gain = gain_db(&mut filter, frequency, sample_rate);
+                assert!((gain - expected_gain).abs() < 0.5);
+            }
+        }
+    }
+
+    #[test]
+    #[should_panic]
+    fn test_unsupported_sample_rate() {
+        EqualLoudnessFilter::new(22050.0);
+    }
+}
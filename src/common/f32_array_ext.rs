@@ -13,6 +13,15 @@ pub trait F32ArrayExt {
     /// Returns the [root mean square](https://en.wikipedia.org/wiki/Root_mean_square)
     /// level in dB relative to 1, i.e 0 dB corresponds to a level of 1.
     fn rms_level_db(&self) -> f32;
+    /// Returns the arithmetic mean.
+    fn mean(&self) -> f32;
+    /// Scales all values so that the peak level equals `target`.
+    /// Does nothing if all values are zero.
+    fn normalize_peak(&mut self, target: f32);
+    /// Returns the [zero-crossing rate](https://en.wikipedia.org/wiki/Zero-crossing_rate),
+    /// i.e the fraction of adjacent value pairs that change sign, between 0 and 1.
+    /// Zero is counted as positive. Returns 0 if there are fewer than two values.
+    fn zero_crossing_rate(&self) -> f32;
 }
 
 impl F32ArrayExt for [f32] {
@@ -49,6 +58,36 @@ impl F32ArrayExt for [f32] {
     fn rms_level_db(&self) -> f32 {
         20. * F32Ext::log10(self.rms_level())
     }
+
+    fn mean(&self) -> f32 {
+        if self.is_empty() {
+            return 0.0;
+        };
+        let sum: f32 = self.iter().sum();
+        sum / (self.len() as f32)
+    }
+
+    fn normalize_peak(&mut self, target: f32) {
+        let peak_level = self.peak_level();
+        if peak_level == 0.0 {
+            return;
+        }
+        let scale = target / peak_level;
+        for sample in self.iter_mut() {
+            *sample *= scale;
+        }
+    }
+
+    fn zero_crossing_rate(&self) -> f32 {
+        if self.len() < 2 {
+            return 0.0;
+        }
+        let crossing_count = self
+            .windows(2)
+            .filter(|pair| (pair[0] >= 0.0) != (pair[1] >= 0.0))
+            .count();
+        (crossing_count as f32) / ((self.len() - 1) as f32)
+    }
 }
 
 #[cfg(test)]
@@ -60,5 +99,54 @@ mod tests {
         let window: [f32; 0] = [];
         assert!(window.rms_level() == 0.0);
         assert!(window.peak_level() == 0.0);
+        assert!(window.mean() == 0.0);
+        assert!(window.zero_crossing_rate() == 0.0);
+        assert!([1.0].zero_crossing_rate() == 0.0);
+    }
+
+    #[test]
+    fn test_constant_window() {
+        let mut window = [-0.5; 16];
+        assert_eq!(window.rms_level(), 0.5);
+        assert_eq!(window.mean(), -0.5);
+        assert_eq!(window.zero_crossing_rate(), 0.0);
+        window.normalize_peak(2.0);
+        assert!(window.iter().all(|value| *value == -2.0));
+    }
+
+    #[test]
+    fn test_ramp_window() {
+        // 0, 1, 2, ..., 10
+        let mut window = [0.0; 11];
+        for (i, value) in window.iter_mut().enumerate() {
+            *value = i as f32;
+        }
+        assert_eq!(window.mean(), 5.0);
+        // sqrt((0^2 + 1^2 + ... + 10^2) / 11) = sqrt(35). Allow for the
+        // error of the approximate sqrt function.
+        let rms_exact = 35.0_f32.sqrt();
+        assert!((window.rms_level() - rms_exact).abs() / rms_exact < 0.07);
+        window.normalize_peak(1.0);
+        assert_eq!(window.peak_level(), 1.0);
+        assert!((window[5] - 0.5).abs() < 1e-6);
+    }
+
+    #[test]
+    fn test_normalize_silence() {
+        let mut window = [0.0; 8];
+        window.normalize_peak(1.0);
+        assert!(window.iter().all(|value| *value == 0.0));
+    }
+
+    #[test]
+    fn test_zero_crossing_rate() {
+        assert_eq!([1.0, -1.0, 1.0, -1.0, 1.0].zero_crossing_rate(), 1.0);
+        assert_eq!([1.0, 2.0, -1.0, -2.0, 0.0].zero_crossing_rate(), 0.5);
+        // 10 periods of a sine sampled at 8 points per period cross zero twice per period
+        let mut window = [0.0; 80];
+        for (i, value) in window.iter_mut().enumerate() {
+            *value = (2.0 * core::f32::consts::PI * (i as f32 + 0.5) / 8.0).sin();
+        }
+        assert!((window.zero_crossing_rate() - 19.0 / 79.0).abs() < 1e-6);
     }
 }
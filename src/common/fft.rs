@@ -1,20 +1,349 @@
 use core::convert::TryInto;
+use microfft::Complex32;
+
+use crate::Error;
+
+/// The largest supported FFT size. 16384 with the `fft-16384` feature, 4096 otherwise.
+#[cfg(feature = "fft-16384")]
+pub const MAX_FFT_SIZE: usize = 16384;
+/// The largest supported FFT size. 16384 with the `fft-16384` feature, 4096 otherwise.
+#[cfg(not(feature = "fft-16384"))]
+pub const MAX_FFT_SIZE: usize = 4096;
 
 /// Perform an in-place fast Fourier transform on real valued data.
-/// Input buffer length must be a power of 2.
+/// Input buffer length must be a power of 2 in the range [8, [`MAX_FFT_SIZE`]].
+/// See [`RealFft`] for repeatedly transforming buffers of the same size.
 pub fn real_fft(buffer: &mut [f32]) -> &mut [microfft::Complex32] {
+    RealFft::new(buffer.len()).process(buffer)
+}
+
+type RealFftFunction = fn(&mut [f32]) -> &mut [Complex32];
+
+macro_rules! real_fft_function {
+    ($name:ident) => {{
+        fn transform(buffer: &mut [f32]) -> &mut [Complex32] {
+            microfft::real::$name(buffer.try_into().unwrap())
+        }
+        transform as RealFftFunction
+    }};
+}
+
+/// A real valued fast Fourier transform of a fixed size. Unlike [`real_fft`],
+/// which selects the transform for the buffer length on every call, the transform
+/// is selected once when the instance is created.
+#[derive(Clone, Copy)]
+pub struct RealFft {
+    size: usize,
+    transform: RealFftFunction,
+}
+
+impl RealFft {
+    /// Creates a new `RealFft` instance. `size` must be a power of 2 in the range [8, [`MAX_FFT_SIZE`]].
+    pub fn new(size: usize) -> Self {
+        RealFft::try_new(size).unwrap_or_else(|error| panic!("{}", error))
+    }
+
+    /// Like `new`, but returns an error instead of panicking if the size is not supported.
+    pub fn try_new(size: usize) -> Result<Self, Error> {
+        let transform = match size {
+            8 => real_fft_function!(rfft_8),
+            16 => real_fft_function!(rfft_16),
+            32 => real_fft_function!(rfft_32),
+            64 => real_fft_function!(rfft_64),
+            128 => real_fft_function!(rfft_128),
+            256 => real_fft_function!(rfft_256),
+            512 => real_fft_function!(rfft_512),
+            1024 => real_fft_function!(rfft_1024),
+            2048 => real_fft_function!(rfft_2048),
+            4096 => real_fft_function!(rfft_4096),
+            #[cfg(feature = "fft-16384")]
+            8192 => real_fft_function!(rfft_8192),
+            #[cfg(feature = "fft-16384")]
+            16384 => real_fft_function!(rfft_16384),
+            _ => return Err(Error::UnsupportedFftSize(size)),
+        };
+        Ok(RealFft { size, transform })
+    }
+
+    /// Returns the transform size.
+    pub fn size(&self) -> usize {
+        self.size
+    }
+
+    /// Perform an in-place fast Fourier transform on real valued data.
+    /// The buffer length must equal the transform size.
+    pub fn process<'a>(&self, buffer: &'a mut [f32]) -> &'a mut [Complex32] {
+        if buffer.len() != self.size {
+            panic!(
+                "Got fft buffer of length {}, expected {}.",
+                buffer.len(),
+                self.size
+            )
+        }
+        (self.transform)(buffer)
+    }
+
+    /// Like [`power_spectrum`], using this transform. The buffer length must equal
+    /// the transform size.
+    pub fn power_spectrum<'a>(&self, buffer: &'a mut [f32]) -> &'a mut [f32] {
+        let bin_count = buffer.len() / 2;
+        let fft = self.process(buffer);
+        // Clear real-valued coefficient at the Nyquist frequency, which is packed into the
+        // imaginary part of the DC bin.
+        fft[0].im = 0.;
+
+        // The FFT is performed in-place, with the real and imaginary parts of bin k
+        // stored at indices 2k and 2k + 1. Bin k is read before index k is written,
+        // so the power spectrum can be written to the first half of the buffer.
+        for k in 0..bin_count {
+            let re = buffer[2 * k];
+            let im = buffer[2 * k + 1];
+            buffer[k] = re * re + im * im;
+        }
+        &mut buffer[..bin_count]
+    }
+}
+
+/// Perform an in-place inverse fast Fourier transform of the spectrum of real valued data,
+/// packed as returned by [`real_fft`], i.e with the real-valued coefficient at the Nyquist
+/// frequency stored in the imaginary part of the DC bin. The result is scaled so that
+/// `real_ifft(real_fft(x))` equals `x`. Spectrum length must be a power of 2 in the range
+/// [4, [`MAX_FFT_SIZE`] / 2].
+pub fn real_ifft(spectrum: &mut [Complex32]) -> &mut [f32] {
+    // The spectrum X of the real valued signal x is turned into the spectrum Z of the
+    // half length complex signal z[n] = x[2n] + i * x[2n + 1], which is then
+    // inverse transformed. This reverses the steps performed by the forward real FFT.
+    let m = spectrum.len();
+    let n = 2 * m;
+
+    // DC and Nyquist
+    let dc = spectrum[0].re;
+    let nyquist = spectrum[0].im;
+    spectrum[0] = Complex32::new(0.5 * (dc + nyquist), 0.5 * (dc - nyquist));
+
+    // Twiddle factors e^(2 pi i k / n), computed in double precision by repeated
+    // multiplication to avoid accumulating errors.
+    let (step_re, step_im) = unit_phasor(2.0 * core::f64::consts::PI / (n as f64));
+    let (mut twiddle_re, mut twiddle_im) = (step_re, step_im);
+    for k in 1..=(m / 2) {
+        let j = m - k;
+        let (x_k, x_j) = (spectrum[k], spectrum[j]);
+        let w_k = Complex32::new(twiddle_re as f32, twiddle_im as f32);
+        // e^(2 pi i (m - k) / n) = -conj(e^(2 pi i k / n))
+        let w_j = Complex32::new(-w_k.re, w_k.im);
+        let i = Complex32::new(0.0, 0.5);
+        spectrum[k] = 0.5 * (x_k + x_j.conj()) + i * (x_k - x_j.conj()) * w_k;
+        spectrum[j] = 0.5 * (x_j + x_k.conj()) + i * (x_j - x_k.conj()) * w_j;
+
+        let next_re = twiddle_re * step_re - twiddle_im * step_im;
+        twiddle_im = twiddle_re * step_im + twiddle_im * step_re;
+        twiddle_re = next_re;
+    }
+
+    // Inverse complex FFT using ifft(z) = conj(fft(conj(z))) / m
+    for value in spectrum.iter_mut() {
+        *value = value.conj();
+    }
+    let z = complex_fft(spectrum);
+    let scale = 1.0 / (m as f32);
+    for value in z.iter_mut() {
+        *value = scale * value.conj();
+    }
+
+    // x[2n] and x[2n + 1] are the real and imaginary parts of z[n]. Complex32 is
+    // a repr(C) pair of f32 values, so z can be reinterpreted as x in place.
+    let data = z.as_mut_ptr().cast::<f32>();
+    unsafe { core::slice::from_raw_parts_mut(data, n) }
+}
+
+/// Perform an in-place complex fast Fourier transform.
+fn complex_fft(buffer: &mut [Complex32]) -> &mut [Complex32] {
     let fft_size = buffer.len();
     match fft_size {
-        8 => microfft::real::rfft_8(buffer.try_into().unwrap()),
-        16 => microfft::real::rfft_16(buffer.try_into().unwrap()),
-        32 => microfft::real::rfft_16(buffer.try_into().unwrap()),
-        64 => microfft::real::rfft_64(buffer.try_into().unwrap()),
-        128 => microfft::real::rfft_128(buffer.try_into().unwrap()),
-        256 => microfft::real::rfft_256(buffer.try_into().unwrap()),
-        512 => microfft::real::rfft_512(buffer.try_into().unwrap()),
-        1024 => microfft::real::rfft_1024(buffer.try_into().unwrap()),
-        2048 => microfft::real::rfft_2048(buffer.try_into().unwrap()),
-        4096 => microfft::real::rfft_4096(buffer.try_into().unwrap()),
-        _ => panic!("Unsupported fft size {}", fft_size),
+        4 => microfft::complex::cfft_4(buffer.try_into().unwrap()),
+        8 => microfft::complex::cfft_8(buffer.try_into().unwrap()),
+        16 => microfft::complex::cfft_16(buffer.try_into().unwrap()),
+        32 => microfft::complex::cfft_32(buffer.try_into().unwrap()),
+        64 => microfft::complex::cfft_64(buffer.try_into().unwrap()),
+        128 => microfft::complex::cfft_128(buffer.try_into().unwrap()),
+        256 => microfft::complex::cfft_256(buffer.try_into().unwrap()),
+        512 => microfft::complex::cfft_512(buffer.try_into().unwrap()),
+        1024 => microfft::complex::cfft_1024(buffer.try_into().unwrap()),
+        2048 => microfft::complex::cfft_2048(buffer.try_into().unwrap()),
+        4096 => microfft::complex::cfft_4096(buffer.try_into().unwrap()),
+        #[cfg(feature = "fft-16384")]
+        8192 => microfft::complex::cfft_8192(buffer.try_into().unwrap()),
+        _ => panic!("Unsupported inverse fft size {}", 2 * fft_size),
+    }
+}
+
+/// Returns (cos(angle), sin(angle)) for an angle in [0, pi / 4].
+fn unit_phasor(angle: f64) -> (f64, f64) {
+    // Taylor series, accurate to double precision in the given range.
+    let mut cos = 0.0;
+    let mut sin = 0.0;
+    let mut term = 1.0;
+    for i in 0..20 {
+        if i % 2 == 1 {
+            sin += if (i / 2) % 2 == 1 { -term } else { term };
+        } else {
+            cos += if (i / 2) % 2 == 1 { -term } else { term };
+        }
+        term *= angle / ((i + 1) as f64);
+    }
+    (cos, sin)
+}
+
+/// Computes the power spectrum, i.e the squared magnitudes of the FFT coefficients,
+/// of real valued data in-place. Input buffer length must be a power of 2.
+/// Returns the first half of the buffer, containing the power of the bins from DC up to,
+/// but not including, the Nyquist frequency.
+pub fn power_spectrum(buffer: &mut [f32]) -> &mut [f32] {
+    RealFft::new(buffer.len()).power_spectrum(buffer)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use alloc::vec::Vec;
+    use core::f32::consts::PI;
+
+    #[test]
+    fn test_real_ifft_round_trip() {
+        for fft_size in [8, 16, 32, 64, 256, 1024, 2048, 4096, 8192, 16384] {
+            if fft_size > MAX_FFT_SIZE {
+                continue;
+            }
+            let signal: Vec<f32> = (0..fft_size)
+                .map(|i| (0.1 * i as f32).sin() + 0.5 * (0.37 * i as f32).cos() + 0.25)
+                .collect();
+            let mut buffer = signal.clone();
+            let spectrum = real_fft(&mut buffer);
+            let result = real_ifft(spectrum);
+            assert_eq!(result.len(), fft_size);
+            for (x, y) in signal.iter().zip(result.iter()) {
+                assert!((x - y).abs() < 1e-4);
+            }
+        }
+    }
+
+    #[test]
+    fn test_real_fft_struct() {
+        let signal: Vec<f32> = (0..256).map(|i| (0.3 * i as f32).sin()).collect();
+        let fft = RealFft::new(256);
+        assert_eq!(fft.size(), 256);
+        let mut expected = signal.clone();
+        let mut buffer = signal.clone();
+        assert_eq!(fft.process(&mut buffer), real_fft(&mut expected));
+        let mut expected = signal.clone();
+        let mut buffer = signal.clone();
+        assert_eq!(
+            fft.power_spectrum(&mut buffer),
+            power_spectrum(&mut expected)
+        );
+    }
+
+    #[test]
+    #[should_panic]
+    fn test_real_fft_struct_wrong_size() {
+        RealFft::new(256).process(&mut [0.0; 128]);
+    }
+
+    #[test]
+    #[should_panic(expected = "Unsupported fft size 100")]
+    fn test_real_fft_struct_unsupported_size() {
+        RealFft::new(100);
+    }
+
+    #[test]
+    fn test_real_fft_try_new() {
+        assert_eq!(RealFft::try_new(256).map(|fft| fft.size()), Ok(256));
+        assert_eq!(
+            RealFft::try_new(100).map(|fft| fft.size()),
+            Err(Error::UnsupportedFftSize(100))
+        );
+        assert!(RealFft::try_new(MAX_FFT_SIZE).is_ok());
+        assert_eq!(
+            RealFft::try_new(2 * MAX_FFT_SIZE).map(|fft| fft.size()),
+            Err(Error::UnsupportedFftSize(2 * MAX_FFT_SIZE))
+        );
+    }
+
+    #[test]
+    fn test_real_fft_32() {
+        // DC offset of 0.5 and a unit amplitude cosine in bin 3
+        let fft_size = 32;
+        let mut buffer: Vec<f32> = (0..fft_size)
+            .map(|i| 0.5 + (2.0 * PI * (3 * i) as f32 / fft_size as f32).cos())
+            .collect();
+        let fft = real_fft(&mut buffer);
+        assert_eq!(fft.len(), 16);
+        assert!((fft[0].re - 16.0).abs() < 1e-4);
+        assert!((fft[3].re - 16.0).abs() < 1e-4);
+        assert!(fft[3].im.abs() < 1e-4);
+        for (k, z) in fft.iter().enumerate().skip(1) {
+            if k != 3 {
+                assert!(z.norm_sqr() < 1e-6);
+            }
+        }
+    }
+
+    #[cfg(feature = "fft-16384")]
+    #[test]
+    fn test_real_fft_8192() {
+        let fft_size = 8192;
+        let bin = 1000;
+        let mut buffer: Vec<f32> = (0..fft_size)
+            .map(|i| (2.0 * PI * ((bin * i) % fft_size) as f32 / fft_size as f32).sin())
+            .collect();
+        let fft = real_fft(&mut buffer);
+        assert_eq!(fft.len(), fft_size / 2);
+        // A unit amplitude sinusoid gives a bin magnitude of fft_size / 2
+        assert!((fft[bin].norm_sqr().sqrt() - (fft_size / 2) as f32).abs() < 0.1);
+        for (k, z) in fft.iter().enumerate() {
+            if k != bin {
+                assert!(z.norm_sqr().sqrt() < 0.1);
+            }
+        }
+    }
+
+    #[test]
+    fn test_real_ifft_impulse() {
+        // A flat spectrum is the transform of a unit impulse
+        let mut spectrum = [Complex32::new(1.0, 1.0); 8];
+        for value in spectrum.iter_mut().skip(1) {
+            *value = Complex32::new(1.0, 0.0);
+        }
+        let result = real_ifft(&mut spectrum);
+        assert!((result[0] - 1.0).abs() < 1e-6);
+        for value in result.iter().skip(1) {
+            assert!(value.abs() < 1e-6);
+        }
+    }
+
+    #[test]
+    fn test_power_spectrum() {
+        let fft_size = 64;
+        let bin = 5;
+        let mut buffer: Vec<f32> = (0..fft_size)
+            .map(|i| 1.0 + (2.0 * PI * (bin * i) as f32 / fft_size as f32).cos())
+            .collect();
+        let expected: Vec<f32> = {
+            let mut copy = buffer.clone();
+            let fft = real_fft(&mut copy);
+            fft[0].im = 0.;
+            fft.iter().map(|z| z.norm_sqr()).collect()
+        };
+        let power = power_spectrum(&mut buffer);
+        assert_eq!(power.len(), fft_size / 2);
+        assert_eq!(power, &expected[..]);
+        // DC and the sinusoid bin
+        assert!((power[0] - (fft_size * fft_size) as f32).abs() < 1e-2);
+        assert!((power[bin] - (fft_size * fft_size / 4) as f32).abs() < 1e-2);
+        for (k, p) in power.iter().enumerate() {
+            if k != 0 && k != bin {
+                assert!(*p < 1e-6);
+            }
+        }
     }
 }
@@ -0,0 +1,128 @@
+use core::f32::consts::PI;
+use micromath::F32Ext;
+
+/// Measures the magnitude of a single frequency component using the
+/// [Goertzel algorithm](https://en.wikipedia.org/wiki/Goertzel_algorithm),
+/// e.g for [DTMF](https://en.wikipedia.org/wiki/Dual-tone_multi-frequency_signaling)
+/// or fixed tone detection. Input samples are processed in blocks of a fixed size
+/// and the magnitude is updated when a block is complete. Cheaper than
+/// an FFT when only a few frequencies are of interest. Does not allocate.
+pub struct Goertzel {
+    coefficient: f32,
+    block_size: usize,
+    sample_count: usize,
+    s1: f32,
+    s2: f32,
+    magnitude_squared: f32,
+}
+
+impl Goertzel {
+    /// Creates a new `Goertzel` instance.
+    /// # Arguments
+    ///
+    /// * `target_hz` - The frequency to measure, in the range (0, sample_rate / 2).
+    /// * `sample_rate` - The sample rate in Hz.
+    /// * `block_size` - The number of samples per block. Larger blocks give a narrower
+    ///   bandwidth, approximately `sample_rate / block_size` Hz.
+    pub fn new(target_hz: f32, sample_rate: f32, block_size: usize) -> Self {
+        if block_size == 0 {
+            panic!("Goertzel block size must be greater than 0")
+        }
+        if target_hz <= 0.0 || target_hz >= 0.5 * sample_rate {
+            panic!("Goertzel target frequency must be in the range (0, sample_rate / 2)")
+        }
+        Goertzel {
+            coefficient: 2.0 * F32Ext::cos(2.0 * PI * target_hz / sample_rate),
+            block_size,
+            sample_count: 0,
+            s1: 0.0,
+            s2: 0.0,
+            magnitude_squared: 0.0,
+        }
+    }
+
+    /// Returns the number of samples per block.
+    pub fn block_size(&self) -> usize {
+        self.block_size
+    }
+
+    /// Discards any partially processed block and clears the magnitude.
+    pub fn reset(&mut self) {
+        self.sample_count = 0;
+        self.s1 = 0.0;
+        self.s2 = 0.0;
+        self.magnitude_squared = 0.0;
+    }
+
+    /// Processes a single sample. Returns true if the sample completed a block,
+    /// in which case `magnitude` and `magnitude_squared` have been updated.
+    pub fn process_sample(&mut self, x: f32) -> bool {
+        let s0 = x + self.coefficient * self.s1 - self.s2;
+        self.s2 = self.s1;
+        self.s1 = s0;
+        self.sample_count += 1;
+        if self.sample_count < self.block_size {
+            return false;
+        }
+
+        // Scale so that a sine with amplitude 1 at the target frequency gives a magnitude of 1
+        let power = self.s1 * self.s1 + self.s2 * self.s2 - self.coefficient * self.s1 * self.s2;
+        let scale = 2.0 / (self.block_size as f32);
+        self.magnitude_squared = scale * scale * power.max(0.0);
+        self.sample_count = 0;
+        self.s1 = 0.0;
+        self.s2 = 0.0;
+        true
+    }
+
+    /// Returns the squared magnitude of the target frequency in the most recently
+    /// completed block. See `magnitude`.
+    pub fn magnitude_squared(&self) -> f32 {
+        self.magnitude_squared
+    }
+
+    /// Returns the magnitude of the target frequency in the most recently completed
+    /// block, or 0 if no block has been completed. A sine with amplitude 1 at the
+    /// target frequency gives a magnitude of approximately 1.
+    pub fn magnitude(&self) -> f32 {
+        if self.magnitude_squared == 0.0 {
+            return 0.0;
+        }
+        F32Ext::sqrt(self.magnitude_squared)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn block_magnitude(goertzel: &mut Goertzel, frequency: f32, sample_rate: f32) -> f32 {
+        goertzel.reset();
+        for i in 0..goertzel.block_size() {
+            let x = (2.0 * PI * frequency * (i as f32) / sample_rate).sin();
+            let is_block_complete = goertzel.process_sample(x);
+            assert_eq!(is_block_complete, i == goertzel.block_size() - 1);
+        }
+        goertzel.magnitude()
+    }
+
+    #[test]
+    fn test_tone_detection() {
+        let sample_rate = 8000.0;
+        let mut goertzel = Goertzel::new(697.0, sample_rate, 205);
+        assert_eq!(goertzel.magnitude(), 0.0);
+        assert!((block_magnitude(&mut goertzel, 697.0, sample_rate) - 1.0).abs() < 0.1);
+        assert!(block_magnitude(&mut goertzel, 770.0, sample_rate) < 0.1);
+        assert!(block_magnitude(&mut goertzel, 1209.0, sample_rate) < 0.1);
+        assert!(
+            (goertzel.magnitude_squared() - goertzel.magnitude() * goertzel.magnitude()).abs()
+                < 1e-3
+        );
+    }
+
+    #[test]
+    #[should_panic]
+    fn test_invalid_target_frequency() {
+        Goertzel::new(5000.0, 8000.0, 205);
+    }
+}
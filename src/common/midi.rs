@@ -1,10 +1,102 @@
+use core::f32::consts::{LN_2, SQRT_2};
+
 use micromath::F32Ext;
 
-/// Converts a frequency in Hz to a [MIDI](https://en.wikipedia.org/wiki/MIDI) note number (with a fractional part).
+/// Converts a frequency in Hz to a [MIDI](https://en.wikipedia.org/wiki/MIDI) note number (with a fractional part),
+/// assuming that A4 (MIDI note 69) has a frequency of 440 Hz.
 pub fn freq_to_midi_note(freq: f32) -> f32 {
     12.0 * F32Ext::log2(freq) - 36.376316562295926
 }
 
+/// Converts a frequency in Hz to a [MIDI](https://en.wikipedia.org/wiki/MIDI) note number (with a fractional part),
+/// given the frequency in Hz of A4 (MIDI note 69), for example 440, 432 or 415.
+pub fn freq_to_midi_note_with_reference(freq: f32, a4_freq: f32) -> f32 {
+    // Take the difference of the logarithms, as opposed to the logarithm of
+    // the quotient, since the approximate log2 function is less accurate below 1.
+    12.0 * (F32Ext::log2(freq) - F32Ext::log2(a4_freq)) + 69.0
+}
+
+/// Returns the interval in [cents](https://en.wikipedia.org/wiki/Cent_(music)) from
+/// `reference_freq` to `freq`, i.e 100 times the number of semitones. Positive if `freq`
+/// is higher than `reference_freq`. Both frequencies must be positive.
+pub fn freq_to_cents(freq: f32, reference_freq: f32) -> f32 {
+    // Take the difference of the logarithms for the same reason
+    // as in freq_to_midi_note_with_reference.
+    1200.0 * (F32Ext::log2(freq) - F32Ext::log2(reference_freq))
+}
+
+/// Converts a [MIDI](https://en.wikipedia.org/wiki/MIDI) note number (with a fractional part) to a frequency in Hz.
+/// The inverse of [`freq_to_midi_note`].
+pub fn midi_to_freq(note: f32) -> f32 {
+    27.5 * exp2((note - 21.0) / 12.0)
+}
+
+/// The maximum length in bytes of a note name, for example `C#/Db-1`.
+pub const NOTE_NAME_MAX_LEN: usize = 7;
+
+/// The name of a note, including sharp and flat spellings and the octave number,
+/// for example `A4` or `C#/Db3`. Stored in a fixed size buffer, so no allocation is needed.
+#[derive(Clone, Copy)]
+pub struct NoteName {
+    bytes: [u8; NOTE_NAME_MAX_LEN],
+    len: usize,
+}
+
+impl NoteName {
+    /// Returns the note name as a string slice.
+    pub fn as_str(&self) -> &str {
+        // Only ASCII characters are written to the buffer
+        core::str::from_utf8(&self.bytes[..self.len]).unwrap()
+    }
+
+    fn push(&mut self, bytes: &[u8]) {
+        self.bytes[self.len..self.len + bytes.len()].copy_from_slice(bytes);
+        self.len += bytes.len();
+    }
+}
+
+impl core::fmt::Display for NoteName {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+/// Returns the name of the MIDI note closest to a given note number, using
+/// [scientific pitch notation](https://en.wikipedia.org/wiki/Scientific_pitch_notation),
+/// where MIDI note 60 is `C4`. Accidentals are spelled as both sharp and flat, for example `C#/Db4`.
+/// Note numbers are clamped to the MIDI range 0 to 127.
+pub fn note_name(note_number: f32) -> NoteName {
+    const NAMES: [&str; 12] = [
+        "C", "C#/Db", "D", "D#/Eb", "E", "F", "F#/Gb", "G", "G#/Ab", "A", "A#/Bb", "B",
+    ];
+    let nearest_note_number = (F32Ext::round(note_number) as i32).clamp(0, 127);
+    let octave = nearest_note_number / 12 - 1;
+
+    let mut name = NoteName {
+        bytes: [0; NOTE_NAME_MAX_LEN],
+        len: 0,
+    };
+    name.push(NAMES[(nearest_note_number % 12) as usize].as_bytes());
+    if octave < 0 {
+        name.push(b"-");
+    }
+    name.push(&[b'0' + (octave.unsigned_abs() as u8)]);
+    name
+}
+
+/// Computes 2 raised to the power of `x`. More accurate than `F32Ext::powf`,
+/// whose error may exceed a few cents when used for note number conversion.
+pub(crate) fn exp2(x: f32) -> f32 {
+    // Split x into an integer part n and a fractional part f in [0, 1), so that
+    // 2^x = 2^n * sqrt(2) * e^y, where y = (f - 0.5) * ln(2) is in [-0.35, 0.35).
+    // In this range, a fifth order Taylor expansion of e^y has a relative error of a few
+    // parts per million, corresponding to less than 0.01 cents.
+    let n = F32Ext::floor(x);
+    let y = (x - n - 0.5) * LN_2;
+    let exp_y = 1.0 + y * (1.0 + y / 2.0 * (1.0 + y / 3.0 * (1.0 + y / 4.0 * (1.0 + y / 5.0))));
+    SQRT_2 * exp_y * F32Ext::powi(2.0, n as i32)
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -27,4 +119,61 @@ mod tests {
             }
         }
     }
+
+    #[test]
+    fn test_reference_frequency() {
+        // Allow for the error of the approximate log2 function.
+        let eps = 0.005;
+        for f in [27.5, 100.0, 440.0, 1234.5] {
+            let note_number = freq_to_midi_note_with_reference(f, 440.0);
+            assert!((note_number - freq_to_midi_note(f)).abs() < eps);
+        }
+        assert!((freq_to_midi_note_with_reference(432.0, 432.0) - 69.0).abs() < eps);
+        assert!((freq_to_midi_note_with_reference(864.0, 432.0) - 81.0).abs() < eps);
+        assert!((freq_to_midi_note_with_reference(415.0, 415.0) - 69.0).abs() < eps);
+        // 440 Hz is about 31.8 cents above A4 with a 432 Hz reference
+        let note_number = freq_to_midi_note_with_reference(440.0, 432.0);
+        assert!((100.0 * (note_number - 69.0) - 31.77).abs() < 100.0 * eps);
+    }
+
+    #[test]
+    fn test_freq_to_cents() {
+        assert!(freq_to_cents(440.0, 440.0).abs() < 0.1);
+        assert!((freq_to_cents(880.0, 440.0) - 1200.0).abs() < 0.2);
+        assert!((freq_to_cents(440.0, 466.164) + 100.0).abs() < 0.2);
+    }
+
+    #[test]
+    fn test_note_name() {
+        assert_eq!(note_name(60.0).as_str(), "C4");
+        assert_eq!(note_name(69.3).as_str(), "A4");
+        assert_eq!(note_name(60.6).as_str(), "C#/Db4");
+        assert_eq!(note_name(21.0).as_str(), "A0");
+        assert_eq!(note_name(0.0).as_str(), "C-1");
+        assert_eq!(note_name(1.0).as_str(), "C#/Db-1");
+        assert_eq!(note_name(127.0).as_str(), "G9");
+        assert_eq!(note_name(-10.0).as_str(), "C-1");
+        assert_eq!(note_name(500.0).as_str(), "G9");
+    }
+
+    #[test]
+    fn test_exp2() {
+        for i in -1000..1000 {
+            let x = 0.01 * (i as f32);
+            let rel_error = (exp2(x) - x.exp2()).abs() / x.exp2();
+            assert!(rel_error < 5e-6);
+        }
+    }
+
+    #[test]
+    fn test_midi_to_freq_round_trip() {
+        let max_cent_error = 0.15_f32;
+        for i in 0..=1270 {
+            let note_number = 0.1 * (i as f32);
+            let round_trip_note_number = freq_to_midi_note(midi_to_freq(note_number));
+            let delta_cents = 100. * (note_number - round_trip_note_number);
+            assert!(delta_cents.abs() <= max_cent_error);
+        }
+        assert!((midi_to_freq(69.0) - 440.0).abs() < 0.1);
+    }
 }
@@ -1,15 +1,44 @@
 //! Common algorithms and utilities.
 
 mod autocorr;
+mod biquad;
+mod decibel;
+mod envelope_follower;
+mod equal_loudness_filter;
 mod f32_array_ext;
 mod fft;
+mod goertzel;
 mod midi;
+mod parabolic_interpolation;
+mod ring_buffer;
+mod sample;
+mod simd;
+mod trigger_detector;
+mod welch_psd;
 mod window_function;
 mod window_processor;
 
-pub use autocorr::{autocorr_conv, autocorr_fft, autocorr_fft_size};
+pub(crate) use autocorr::autocorr_fft_with;
+pub use autocorr::{
+    autocorr_conv, autocorr_fft, autocorr_fft_normalized, autocorr_fft_size, AutocorrScaling,
+};
+pub use biquad::Biquad;
+pub use decibel::{from_db, to_db, DB_FLOOR};
+pub use envelope_follower::{EnvelopeFollower, EnvelopeMode};
+pub use equal_loudness_filter::EqualLoudnessFilter;
 pub use f32_array_ext::F32ArrayExt;
-pub use fft::real_fft;
-pub use midi::freq_to_midi_note;
-pub use window_function::{apply_window_function, WindowFunctionType};
+pub use fft::{power_spectrum, real_fft, real_ifft, RealFft, MAX_FFT_SIZE};
+pub use goertzel::Goertzel;
+pub use midi::{
+    freq_to_cents, freq_to_midi_note, freq_to_midi_note_with_reference, midi_to_freq, note_name,
+    NoteName, NOTE_NAME_MAX_LEN,
+};
+pub(crate) use parabolic_interpolation::{parabolic_curvature, parabolic_peak, Float};
+pub use ring_buffer::RingBuffer;
+pub use sample::Sample;
+pub(crate) use sample::{downmix_interleaved, i16_to_f32};
+pub(crate) use simd::guarded_ratio;
+pub use trigger_detector::TriggerDetector;
+pub use welch_psd::WelchPsd;
+pub use window_function::{apply_window_function, fill_window_function, WindowFunctionType};
 pub use window_processor::WindowProcessor;
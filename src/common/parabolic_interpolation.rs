@@ -0,0 +1,74 @@
+use core::ops::{Add, Div, Mul, Neg, Sub};
+
+/// A floating point type, i.e `f32` or `f64`, for helpers shared by the single
+/// and double precision pitch detection code.
+pub(crate) trait Float:
+    Copy
+    + PartialOrd
+    + Add<Output = Self>
+    + Sub<Output = Self>
+    + Mul<Output = Self>
+    + Div<Output = Self>
+    + Neg<Output = Self>
+{
+    const ZERO: Self;
+    const HALF: Self;
+    const TWO: Self;
+}
+
+impl Float for f32 {
+    const ZERO: f32 = 0.0;
+    const HALF: f32 = 0.5;
+    const TWO: f32 = 2.0;
+}
+
+impl Float for f64 {
+    const ZERO: f64 = 0.0;
+    const HALF: f64 = 0.5;
+    const TWO: f64 = 2.0;
+}
+
+/// Fits a parabola to three equidistant values at x = -1, 0 and 1 and returns
+/// the x position and the value of its extremum. Used to estimate the location
+/// of a peak with sub-sample precision. If the values lie on a line, the
+/// center value at x = 0 is returned.
+pub(crate) fn parabolic_peak<T: Float>(left: T, center: T, right: T) -> (T, T) {
+    // Compute coefficients of a parabola ax^2 + bx + c passing through
+    // (-1, left), (0, center), (1, right)
+    let a = parabolic_curvature(left, center, right);
+    let b = T::HALF * (right - left);
+    let c = center;
+    // Find the x value where the derivative is zero, i.e where the parabola has its extremum
+    let x_max = if a != T::ZERO {
+        -b / (T::TWO * a)
+    } else {
+        T::ZERO
+    };
+    (x_max, a * x_max * x_max + b * x_max + c)
+}
+
+/// Returns the second order coefficient `a` of the parabola `ax^2 + bx + c` passing
+/// through three equidistant values at x = -1, 0 and 1. Negative for a maximum,
+/// with larger magnitudes meaning a sharper peak.
+pub(crate) fn parabolic_curvature<T: Float>(left: T, center: T, right: T) -> T {
+    T::HALF * (right - T::TWO * center + left)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parabolic_peak() {
+        assert_eq!(parabolic_peak(0.0_f32, 3.0, 0.0), (0.0, 3.0));
+        assert_eq!(parabolic_peak(1.0_f32, 1.0, 1.0), (0.0, 1.0));
+        // y = -(x - 0.25)^2 + 1
+        let (x, y) = parabolic_peak(-0.5625_f32, 0.9375, 0.4375);
+        assert!((x - 0.25).abs() <= f32::EPSILON);
+        assert!((y - 1.0).abs() <= f32::EPSILON);
+        assert_eq!(parabolic_curvature(-0.5625_f32, 0.9375, 0.4375), -1.0);
+        let (x, y) = parabolic_peak(-0.5625_f64, 0.9375, 0.4375);
+        assert!((x - 0.25).abs() <= f64::EPSILON);
+        assert!((y - 1.0).abs() <= f64::EPSILON);
+    }
+}
@@ -0,0 +1,141 @@
+use alloc::{boxed::Box, vec};
+
+/// A fixed capacity [circular buffer](https://en.wikipedia.org/wiki/Circular_buffer)
+/// keeping the most recently pushed values. Allocates once on creation. Every value
+/// is stored twice, so that the contents are always available as a contiguous slice
+/// without copying, at the cost of twice the memory and two writes per push.
+pub struct RingBuffer<T: Copy + Default = f32> {
+    /// Two consecutive copies of the ring, each `capacity` values long.
+    buffer: Box<[T]>,
+    capacity: usize,
+    /// The position in the first copy where the next value is written.
+    write_index: usize,
+    len: usize,
+}
+
+impl<T: Copy + Default> RingBuffer<T> {
+    /// Creates a new, empty, `RingBuffer` holding at most `capacity` values.
+    pub fn new(capacity: usize) -> Self {
+        if capacity == 0 {
+            panic!("Ring buffer capacity must be greater than 0")
+        }
+        RingBuffer {
+            buffer: vec![T::default(); 2 * capacity].into_boxed_slice(),
+            capacity,
+            write_index: 0,
+            len: 0,
+        }
+    }
+
+    /// Returns the maximum number of values the buffer holds.
+    pub fn capacity(&self) -> usize {
+        self.capacity
+    }
+
+    /// Returns the number of values in the buffer.
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// Returns true if the buffer holds `capacity` values, in which case
+    /// pushing a value discards the oldest one.
+    pub fn is_full(&self) -> bool {
+        self.len == self.capacity
+    }
+
+    /// Removes all values.
+    pub fn clear(&mut self) {
+        self.write_index = 0;
+        self.len = 0;
+    }
+
+    /// Adds a value, discarding the oldest value if the buffer is full.
+    pub fn push(&mut self, value: T) {
+        self.buffer[self.write_index] = value;
+        self.buffer[self.write_index + self.capacity] = value;
+        self.write_index = (self.write_index + 1) % self.capacity;
+        self.len = (self.len + 1).min(self.capacity);
+    }
+
+    /// Returns the value pushed `index` values before the newest one, i.e
+    /// `newest(0)` is the most recently pushed value. Panics if `index` is not less than `len`.
+    pub fn newest(&self, index: usize) -> T {
+        if index >= self.len {
+            panic!(
+                "Ring buffer index {} out of range for length {}",
+                index, self.len
+            )
+        }
+        self.as_slice()[self.len - 1 - index]
+    }
+
+    /// Returns the value pushed `index` values after the oldest one, i.e
+    /// `oldest(0)` is the oldest value. Panics if `index` is not less than `len`.
+    pub fn oldest(&self, index: usize) -> T {
+        if index >= self.len {
+            panic!(
+                "Ring buffer index {} out of range for length {}",
+                index, self.len
+            )
+        }
+        self.as_slice()[index]
+    }
+
+    /// Returns the values in the buffer, oldest first.
+    pub fn as_slice(&self) -> &[T] {
+        let start = self.write_index + self.capacity - self.len;
+        &self.buffer[start..start + self.len]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_push_and_read() {
+        let mut buffer: RingBuffer = RingBuffer::new(4);
+        assert!(buffer.is_empty());
+        assert!(buffer.as_slice().is_empty());
+
+        buffer.push(1.0);
+        buffer.push(2.0);
+        assert_eq!(buffer.len(), 2);
+        assert!(!buffer.is_full());
+        assert_eq!(buffer.as_slice(), &[1.0, 2.0]);
+        assert_eq!(buffer.newest(0), 2.0);
+        assert_eq!(buffer.oldest(0), 1.0);
+
+        // Wrap around several times
+        for value in 3..=10 {
+            buffer.push(value as f32);
+        }
+        assert!(buffer.is_full());
+        assert_eq!(buffer.as_slice(), &[7.0, 8.0, 9.0, 10.0]);
+        assert_eq!(buffer.newest(1), 9.0);
+        assert_eq!(buffer.oldest(1), 8.0);
+
+        buffer.clear();
+        assert!(buffer.is_empty());
+        buffer.push(11.0);
+        assert_eq!(buffer.as_slice(), &[11.0]);
+    }
+
+    #[test]
+    #[should_panic]
+    fn test_index_out_of_range() {
+        let mut buffer: RingBuffer<i32> = RingBuffer::new(4);
+        buffer.push(1);
+        buffer.newest(1);
+    }
+
+    #[test]
+    #[should_panic]
+    fn test_zero_capacity() {
+        RingBuffer::<f32>::new(0);
+    }
+}
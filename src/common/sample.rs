@@ -0,0 +1,109 @@
+/// Converts a 16 bit integer sample to an `f32` sample in the range [-1, 1).
+pub(crate) fn i16_to_f32(sample: i16) -> f32 {
+    const SCALE: f32 = 1.0 / 32768.0;
+    SCALE * (sample as f32)
+}
+
+/// A sample type that can be converted to an `f32` sample.
+pub trait Sample: Copy {
+    /// Converts the sample to `f32`. Integer samples are scaled to the range [-1, 1).
+    fn to_f32(self) -> f32;
+}
+
+impl Sample for f32 {
+    fn to_f32(self) -> f32 {
+        self
+    }
+}
+
+impl Sample for f64 {
+    fn to_f32(self) -> f32 {
+        self as f32
+    }
+}
+
+impl Sample for i16 {
+    fn to_f32(self) -> f32 {
+        i16_to_f32(self)
+    }
+}
+
+const DOWNMIX_CHUNK_SIZE: usize = 256;
+
+/// Downmixes interleaved multi-channel samples to mono, using the average of the
+/// channels of each frame, and passes the mono samples to `handler` in chunks.
+/// Panics if `channels` is 0 or if the buffer length is not a multiple of `channels`.
+pub(crate) fn downmix_interleaved<F>(buffer: &[f32], channels: usize, mut handler: F)
+where
+    F: FnMut(&[f32]),
+{
+    if channels == 0 {
+        panic!("Channel count must be greater than 0")
+    }
+    let incomplete_frame_len = buffer.len() % channels;
+    if incomplete_frame_len > 0 {
+        panic!(
+            "Interleaved buffer length {} is not a multiple of the channel count {}",
+            buffer.len(),
+            channels
+        )
+    }
+    if channels == 1 {
+        return handler(buffer);
+    }
+    let scale = 1.0 / (channels as f32);
+    let mut mono = [0.0; DOWNMIX_CHUNK_SIZE];
+    for chunk in buffer.chunks(DOWNMIX_CHUNK_SIZE * channels) {
+        let mono = &mut mono[..chunk.len() / channels];
+        for (mono, frame) in mono.iter_mut().zip(chunk.chunks_exact(channels)) {
+            *mono = scale * frame.iter().sum::<f32>();
+        }
+        handler(mono);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{downmix_interleaved, i16_to_f32, Sample};
+    use alloc::vec::Vec;
+
+    #[test]
+    fn test_i16_to_f32() {
+        assert_eq!(i16_to_f32(0), 0.0);
+        assert_eq!(i16_to_f32(i16::MIN), -1.0);
+        assert_eq!(i16_to_f32(16384), 0.5);
+        assert!(i16_to_f32(i16::MAX) < 1.0);
+    }
+
+    #[test]
+    fn test_sample_to_f32() {
+        assert_eq!(0.25_f32.to_f32(), 0.25);
+        assert_eq!(0.25_f64.to_f32(), 0.25);
+        assert_eq!(16384_i16.to_f32(), 0.5);
+    }
+
+    #[test]
+    fn test_downmix_interleaved() {
+        // Enough frames for several chunks
+        let stereo: Vec<f32> = (0..1000).map(|i| i as f32).collect();
+        let mut mono = Vec::new();
+        downmix_interleaved(&stereo, 2, |chunk| {
+            assert!(chunk.len() <= super::DOWNMIX_CHUNK_SIZE);
+            mono.extend_from_slice(chunk)
+        });
+        let expected: Vec<f32> = (0..500).map(|i| (4 * i + 1) as f32 / 2.0).collect();
+        assert_eq!(mono, expected);
+
+        mono.clear();
+        downmix_interleaved(&stereo[..4], 1, |chunk| mono.extend_from_slice(chunk));
+        assert_eq!(mono, [0.0, 1.0, 2.0, 3.0]);
+    }
+
+    #[test]
+    #[should_panic(
+        expected = "Interleaved buffer length 5 is not a multiple of the channel count 2"
+    )]
+    fn test_downmix_interleaved_incomplete_frame() {
+        downmix_interleaved(&[0.0; 5], 2, |_| {});
+    }
+}
@@ -0,0 +1,226 @@
+//! Inner loops vectorized using `core::arch` when the `simd` feature is enabled,
+//! with scalar fallbacks. SSE (or AVX2, if enabled at compile time) is used on x86_64
+//! and NEON on aarch64. Both are available on all CPUs of the respective architecture,
+//! so no runtime feature detection is needed.
+
+use microfft::Complex32;
+use micromath::F32Ext;
+
+/// Writes the squared magnitude of each complex value to `result`,
+/// which must have the same length as `values`.
+pub(crate) fn norm_sqr(values: &[Complex32], result: &mut [f32]) {
+    if values.len() != result.len() {
+        panic!(
+            "Got result of length {}, expected {}.",
+            result.len(),
+            values.len()
+        )
+    }
+    // Complex32 is a repr(C) pair of f32 values, so values can be
+    // reinterpreted as interleaved real and imaginary parts.
+    let interleaved =
+        unsafe { core::slice::from_raw_parts(values.as_ptr().cast::<f32>(), 2 * values.len()) };
+    let vectorized_count = norm_sqr_vectorized(interleaved, result);
+    for (value, result) in values.iter().zip(result.iter_mut()).skip(vectorized_count) {
+        *result = value.norm_sqr();
+    }
+}
+
+/// Replaces each denominator `d` with `scale * n / d`, where `n` is the corresponding
+/// numerator, or with 0 if the magnitude of `d` is not greater than `f32::EPSILON`.
+pub(crate) fn guarded_ratio(numerators: &[f32], denominators: &mut [f32], scale: f32) {
+    if numerators.len() < denominators.len() {
+        panic!("Numerators must not be fewer than denominators")
+    }
+    let vectorized_count = guarded_ratio_vectorized(numerators, denominators, scale);
+    for (n, d) in numerators
+        .iter()
+        .zip(denominators.iter_mut())
+        .skip(vectorized_count)
+    {
+        *d = if F32Ext::abs(*d) <= f32::EPSILON {
+            0.0
+        } else {
+            scale * n / *d
+        };
+    }
+}
+
+// The functions below process as many values as possible using vector
+// instructions and return the number of processed values.
+
+#[cfg(not(all(feature = "simd", any(target_arch = "x86_64", target_arch = "aarch64"))))]
+fn norm_sqr_vectorized(_: &[f32], _: &mut [f32]) -> usize {
+    0
+}
+
+#[cfg(not(all(feature = "simd", any(target_arch = "x86_64", target_arch = "aarch64"))))]
+fn guarded_ratio_vectorized(_: &[f32], _: &mut [f32], _: f32) -> usize {
+    0
+}
+
+#[cfg(all(feature = "simd", target_arch = "x86_64", not(target_feature = "avx2")))]
+fn norm_sqr_vectorized(interleaved: &[f32], result: &mut [f32]) -> usize {
+    use core::arch::x86_64::*;
+    let count = result.len() / 4 * 4;
+    unsafe {
+        for i in (0..count).step_by(4) {
+            let a = _mm_loadu_ps(interleaved.as_ptr().add(2 * i));
+            let b = _mm_loadu_ps(interleaved.as_ptr().add(2 * i + 4));
+            let a = _mm_mul_ps(a, a);
+            let b = _mm_mul_ps(b, b);
+            // Add adjacent squared real and imaginary parts
+            let re = _mm_shuffle_ps(a, b, 0b10_00_10_00);
+            let im = _mm_shuffle_ps(a, b, 0b11_01_11_01);
+            _mm_storeu_ps(result.as_mut_ptr().add(i), _mm_add_ps(re, im));
+        }
+    }
+    count
+}
+
+#[cfg(all(feature = "simd", target_arch = "x86_64", not(target_feature = "avx2")))]
+fn guarded_ratio_vectorized(numerators: &[f32], denominators: &mut [f32], scale: f32) -> usize {
+    use core::arch::x86_64::*;
+    let count = denominators.len() / 4 * 4;
+    unsafe {
+        let scale = _mm_set1_ps(scale);
+        let epsilon = _mm_set1_ps(f32::EPSILON);
+        let sign_mask = _mm_set1_ps(-0.0);
+        for i in (0..count).step_by(4) {
+            let n = _mm_loadu_ps(numerators.as_ptr().add(i));
+            let d = _mm_loadu_ps(denominators.as_ptr().add(i));
+            let ratio = _mm_div_ps(_mm_mul_ps(scale, n), d);
+            let is_valid = _mm_cmpgt_ps(_mm_andnot_ps(sign_mask, d), epsilon);
+            _mm_storeu_ps(
+                denominators.as_mut_ptr().add(i),
+                _mm_and_ps(is_valid, ratio),
+            );
+        }
+    }
+    count
+}
+
+#[cfg(all(feature = "simd", target_arch = "x86_64", target_feature = "avx2"))]
+fn norm_sqr_vectorized(interleaved: &[f32], result: &mut [f32]) -> usize {
+    use core::arch::x86_64::*;
+    let count = result.len() / 8 * 8;
+    unsafe {
+        for i in (0..count).step_by(8) {
+            let a = _mm256_loadu_ps(interleaved.as_ptr().add(2 * i));
+            let b = _mm256_loadu_ps(interleaved.as_ptr().add(2 * i + 8));
+            // Horizontal adds of adjacent squared real and imaginary parts
+            // are interleaved per 128 bit lane, which the permute reorders.
+            let sums = _mm256_hadd_ps(_mm256_mul_ps(a, a), _mm256_mul_ps(b, b));
+            let sums =
+                _mm256_castpd_ps(_mm256_permute4x64_pd(_mm256_castps_pd(sums), 0b11_01_10_00));
+            _mm256_storeu_ps(result.as_mut_ptr().add(i), sums);
+        }
+    }
+    count
+}
+
+#[cfg(all(feature = "simd", target_arch = "x86_64", target_feature = "avx2"))]
+fn guarded_ratio_vectorized(numerators: &[f32], denominators: &mut [f32], scale: f32) -> usize {
+    use core::arch::x86_64::*;
+    let count = denominators.len() / 8 * 8;
+    unsafe {
+        let scale = _mm256_set1_ps(scale);
+        let epsilon = _mm256_set1_ps(f32::EPSILON);
+        let sign_mask = _mm256_set1_ps(-0.0);
+        for i in (0..count).step_by(8) {
+            let n = _mm256_loadu_ps(numerators.as_ptr().add(i));
+            let d = _mm256_loadu_ps(denominators.as_ptr().add(i));
+            let ratio = _mm256_div_ps(_mm256_mul_ps(scale, n), d);
+            let is_valid = _mm256_cmp_ps::<_CMP_GT_OQ>(_mm256_andnot_ps(sign_mask, d), epsilon);
+            _mm256_storeu_ps(
+                denominators.as_mut_ptr().add(i),
+                _mm256_and_ps(is_valid, ratio),
+            );
+        }
+    }
+    count
+}
+
+#[cfg(all(feature = "simd", target_arch = "aarch64"))]
+fn norm_sqr_vectorized(interleaved: &[f32], result: &mut [f32]) -> usize {
+    use core::arch::aarch64::*;
+    let count = result.len() / 4 * 4;
+    unsafe {
+        for i in (0..count).step_by(4) {
+            let a = vld1q_f32(interleaved.as_ptr().add(2 * i));
+            let b = vld1q_f32(interleaved.as_ptr().add(2 * i + 4));
+            // Add adjacent squared real and imaginary parts
+            let sums = vpaddq_f32(vmulq_f32(a, a), vmulq_f32(b, b));
+            vst1q_f32(result.as_mut_ptr().add(i), sums);
+        }
+    }
+    count
+}
+
+#[cfg(all(feature = "simd", target_arch = "aarch64"))]
+fn guarded_ratio_vectorized(numerators: &[f32], denominators: &mut [f32], scale: f32) -> usize {
+    use core::arch::aarch64::*;
+    let count = denominators.len() / 4 * 4;
+    unsafe {
+        let scale = vdupq_n_f32(scale);
+        let epsilon = vdupq_n_f32(f32::EPSILON);
+        let zero = vdupq_n_f32(0.0);
+        for i in (0..count).step_by(4) {
+            let n = vld1q_f32(numerators.as_ptr().add(i));
+            let d = vld1q_f32(denominators.as_ptr().add(i));
+            let ratio = vdivq_f32(vmulq_f32(scale, n), d);
+            let is_valid = vcgtq_f32(vabsq_f32(d), epsilon);
+            vst1q_f32(
+                denominators.as_mut_ptr().add(i),
+                vbslq_f32(is_valid, ratio, zero),
+            );
+        }
+    }
+    count
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use alloc::vec::Vec;
+
+    #[test]
+    fn test_norm_sqr() {
+        // Use a length that is not a multiple of the vector width
+        let values: Vec<Complex32> = (0..37)
+            .map(|i| Complex32::new((0.3 * i as f32).sin(), (0.7 * i as f32).cos()))
+            .collect();
+        let mut result = [0.0; 37];
+        norm_sqr(&values, &mut result);
+        for (value, result) in values.iter().zip(result.iter()) {
+            assert!((value.norm_sqr() - result).abs() <= f32::EPSILON);
+        }
+    }
+
+    #[test]
+    fn test_guarded_ratio() {
+        let numerators: Vec<f32> = (0..37).map(|i| (0.3 * i as f32).sin()).collect();
+        let mut denominators: Vec<f32> = (0..37)
+            .map(|i| {
+                if i % 5 == 0 {
+                    0.0
+                } else {
+                    (0.7 * i as f32).cos()
+                }
+            })
+            .collect();
+        let expected: Vec<f32> = numerators
+            .iter()
+            .zip(denominators.iter())
+            .map(|(n, d)| {
+                if d.abs() <= f32::EPSILON {
+                    0.0
+                } else {
+                    2.0 * n / d
+                }
+            })
+            .collect();
+        guarded_ratio(&numerators, &mut denominators, 2.0);
+        assert_eq!(denominators, expected);
+    }
+}
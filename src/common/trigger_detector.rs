@@ -0,0 +1,99 @@
+/// Turns a stream of values, e.g novelty or level values, into discrete triggers
+/// using a [Schmitt trigger](https://en.wikipedia.org/wiki/Schmitt_trigger) with
+/// hysteresis. A trigger fires when a value exceeds the high threshold, after which
+/// the detector is disarmed until a value falls below the low threshold. This prevents
+/// values fluctuating around a single threshold from firing repeated triggers.
+pub struct TriggerDetector {
+    high_threshold: f32,
+    low_threshold: f32,
+    is_armed: bool,
+}
+
+impl TriggerDetector {
+    /// Creates a new, armed, `TriggerDetector` instance.
+    /// # Arguments
+    ///
+    /// * `high_threshold` - A trigger fires when an armed detector gets a value greater than this threshold.
+    /// * `low_threshold` - The detector is armed again when it gets a value less than this threshold. Must not be greater than `high_threshold`.
+    pub fn new(high_threshold: f32, low_threshold: f32) -> Self {
+        if low_threshold > high_threshold {
+            panic!("Trigger low threshold must not be greater than the high threshold")
+        }
+        TriggerDetector {
+            high_threshold,
+            low_threshold,
+            is_armed: true,
+        }
+    }
+
+    /// Creates a new `TriggerDetector` without hysteresis, i.e with the same high and low threshold.
+    pub fn with_threshold(threshold: f32) -> Self {
+        TriggerDetector::new(threshold, threshold)
+    }
+
+    pub fn high_threshold(&self) -> f32 {
+        self.high_threshold
+    }
+
+    pub fn low_threshold(&self) -> f32 {
+        self.low_threshold
+    }
+
+    /// Returns true if the next value greater than the high threshold fires a trigger.
+    pub fn is_armed(&self) -> bool {
+        self.is_armed
+    }
+
+    /// Arms the detector.
+    pub fn reset(&mut self) {
+        self.is_armed = true;
+    }
+
+    /// Processes a single value. Returns true if the value fired a trigger.
+    pub fn process(&mut self, x: f32) -> bool {
+        if self.is_armed && x > self.high_threshold {
+            self.is_armed = false;
+            return true;
+        }
+        if x < self.low_threshold {
+            self.is_armed = true;
+        }
+        false
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use alloc::vec::Vec;
+
+    fn triggers(detector: &mut TriggerDetector, values: &[f32]) -> Vec<bool> {
+        values.iter().map(|x| detector.process(*x)).collect()
+    }
+
+    #[test]
+    fn test_hysteresis() {
+        let mut detector = TriggerDetector::new(0.5, 0.2);
+        // Values between the thresholds neither fire nor rearm
+        let values = [0.1, 0.6, 0.7, 0.4, 0.6, 0.3, 0.8, 0.1, 0.6];
+        let expected = [false, true, false, false, false, false, false, false, true];
+        assert_eq!(triggers(&mut detector, &values), expected);
+        assert!(!detector.is_armed());
+        detector.reset();
+        assert!(detector.is_armed());
+    }
+
+    #[test]
+    fn test_single_threshold() {
+        let mut detector = TriggerDetector::with_threshold(0.4);
+        let values = [0.5, 0.5, 0.3, 0.5, 0.4, 0.5];
+        let expected = [true, false, false, true, false, false];
+        assert_eq!(triggers(&mut detector, &values), expected);
+    }
+
+    #[test]
+    #[should_panic]
+    fn test_invalid_thresholds() {
+        TriggerDetector::new(0.2, 0.5);
+    }
+}
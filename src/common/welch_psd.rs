@@ -0,0 +1,151 @@
+use alloc::{boxed::Box, vec};
+
+use super::{fill_window_function, RealFft, WindowFunctionType, WindowProcessor};
+
+/// Estimates the [power spectral density](https://en.wikipedia.org/wiki/Spectral_density)
+/// of a stream of input samples using [Welch's method](https://en.wikipedia.org/wiki/Welch%27s_method),
+/// i.e by averaging the power spectra of overlapping windowed segments. The average has
+/// a lower variance than the power spectrum of a single window, which is useful for
+/// analyzing steady state signals. With a hop size equal to the window size and a
+/// rectangular window, i.e `WindowFunctionType::Tukey(0.0)`, this is Bartlett's method.
+pub struct WelchPsd {
+    window_processor: WindowProcessor,
+    window_coefficients: Box<[f32]>,
+    /// The sum of the squared window coefficients, used to normalize the power spectra.
+    window_power: f32,
+    fft: RealFft,
+    scratch_buffer: Box<[f32]>,
+    psd: Box<[f32]>,
+    segment_count: usize,
+}
+
+impl WelchPsd {
+    /// Creates a new `WelchPsd` instance.
+    /// # Arguments
+    ///
+    /// * `window_size` - The segment size. Must be a power of 2 in the range [8, [`MAX_FFT_SIZE`](crate::common::MAX_FFT_SIZE)].
+    /// * `hop_size` - The distance between the start of segments, e.g half the window size.
+    /// * `window_func` - The window function applied to each segment.
+    pub fn new(window_size: usize, hop_size: usize, window_func: WindowFunctionType) -> Self {
+        let mut window_coefficients = vec![0.; window_size].into_boxed_slice();
+        fill_window_function(window_func, &mut window_coefficients);
+        let window_power = window_coefficients.iter().map(|w| w * w).sum();
+        WelchPsd {
+            window_processor: WindowProcessor::new(window_size, hop_size, 1),
+            window_coefficients,
+            window_power,
+            fft: RealFft::new(window_size),
+            scratch_buffer: vec![0.; window_size].into_boxed_slice(),
+            psd: vec![0.; window_size / 2].into_boxed_slice(),
+            segment_count: 0,
+        }
+    }
+
+    /// Processes an arbitrarily sized buffer of input samples, adding the power
+    /// spectrum of each newly completed segment to the average.
+    pub fn push(&mut self, buffer: &[f32]) {
+        let window_coefficients = &self.window_coefficients;
+        let scale = if self.window_power > 0. {
+            1. / self.window_power
+        } else {
+            0.
+        };
+        let fft = &self.fft;
+        let scratch_buffer = &mut self.scratch_buffer;
+        let psd = &mut self.psd;
+        let segment_count = &mut self.segment_count;
+        self.window_processor.process(buffer, |_, window| {
+            for ((scratch, x), w) in scratch_buffer
+                .iter_mut()
+                .zip(window.iter())
+                .zip(window_coefficients.iter())
+            {
+                *scratch = x * w;
+            }
+            let power = fft.power_spectrum(scratch_buffer);
+            // Update the running mean
+            *segment_count += 1;
+            let weight = 1. / (*segment_count as f32);
+            for (average, power) in psd.iter_mut().zip(power.iter()) {
+                *average += weight * (scale * power - *average);
+            }
+        });
+    }
+
+    /// Returns the average power spectrum of the segments processed since creation or
+    /// the last reset, for the bins from DC up to, but not including, the Nyquist frequency.
+    /// Each segment's power spectrum is divided by the sum of the squared window coefficients,
+    /// so that white noise with variance σ² gives a value of approximately σ² in every bin.
+    /// Divide by the sample rate to get the density per Hz. All zeros if no segment
+    /// has been completed.
+    pub fn psd(&self) -> &[f32] {
+        &self.psd
+    }
+
+    /// Returns the number of segments averaged by `psd`.
+    pub fn segment_count(&self) -> usize {
+        self.segment_count
+    }
+
+    /// Clears the average and discards any partially filled segment.
+    pub fn reset(&mut self) {
+        self.window_processor.reset();
+        self.psd.fill(0.);
+        self.segment_count = 0;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use alloc::vec::Vec;
+    use rand::{rngs::StdRng, Rng, SeedableRng};
+
+    #[test]
+    fn test_white_noise() {
+        // Uniform noise in [-1, 1] has a variance of 1/3
+        let mut rng = StdRng::seed_from_u64(123);
+        let signal: Vec<f32> = (0..256 * 200).map(|_| rng.gen_range(-1.0..=1.0)).collect();
+        let mut welch = WelchPsd::new(256, 128, WindowFunctionType::Hann);
+        assert!(welch.psd().iter().all(|value| *value == 0.));
+
+        welch.push(&signal);
+        assert_eq!(welch.segment_count(), 399);
+        // The average is close to flat, apart from the DC bin where the
+        // Hann window leaks less noise power
+        for value in welch.psd().iter().skip(2) {
+            assert!((value - 1. / 3.).abs() < 0.1, "{}", value);
+        }
+
+        welch.reset();
+        assert_eq!(welch.segment_count(), 0);
+        assert!(welch.psd().iter().all(|value| *value == 0.));
+    }
+
+    #[test]
+    fn test_sine_peak() {
+        let window_size = 512;
+        let bin = 40;
+        let signal: Vec<f32> = (0..window_size * 8)
+            .map(|i| {
+                (2. * core::f32::consts::PI * (bin as f32) * (i as f32) / (window_size as f32))
+                    .sin()
+            })
+            .collect();
+        let mut welch = WelchPsd::new(window_size, window_size / 2, WindowFunctionType::Hann);
+        // Segments are completed regardless of how the input is split
+        for chunk in signal.chunks(100) {
+            welch.push(chunk);
+        }
+        assert_eq!(welch.segment_count(), 15);
+        let (peak_bin, _) = welch
+            .psd()
+            .iter()
+            .enumerate()
+            .fold(
+                (0, 0.),
+                |max, (k, value)| if *value > max.1 { (k, *value) } else { max },
+            );
+        assert_eq!(peak_bin, bin);
+    }
+}
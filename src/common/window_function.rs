@@ -1,12 +1,20 @@
 use core::f32::consts::PI;
 
-#[derive(Clone, Copy)]
+#[derive(Clone, Copy, Debug, PartialEq)]
 /// [Window function](https://en.wikipedia.org/wiki/Window_function) type.
 pub enum WindowFunctionType {
     /// [Hann window](https://en.wikipedia.org/wiki/Window_function#Hann_and_Hamming_windows)
     Hann,
     /// [Welch window](<https://en.wikipedia.org/wiki/Window_function#Welch_window>)
     Welch,
+    /// [Hamming window](https://en.wikipedia.org/wiki/Window_function#Hann_and_Hamming_windows)
+    Hamming,
+    /// [Blackman window](https://en.wikipedia.org/wiki/Window_function#Blackman_window)
+    Blackman,
+    /// [Tukey window](https://en.wikipedia.org/wiki/Window_function#Tukey_window),
+    /// also known as tapered cosine window. The parameter in [0, 1] is the fraction
+    /// of the window spent tapering. 0 gives a rectangular window and 1 gives a Hann window.
+    Tukey(f32),
 }
 
 /// Performs point-wise multiplication of a buffer and a window function of a given type.
@@ -14,24 +22,89 @@ pub fn apply_window_function(window_function: WindowFunctionType, buffer: &mut [
     match window_function {
         WindowFunctionType::Hann => hann_window(buffer),
         WindowFunctionType::Welch => welch_window(buffer),
+        WindowFunctionType::Hamming => hamming_window(buffer),
+        WindowFunctionType::Blackman => blackman_window(buffer),
+        WindowFunctionType::Tukey(alpha) => tukey_window(buffer, alpha),
     }
 }
 
+/// Writes the values of a window function of a given type to a buffer, i.e the
+/// same result as applying the window function to a buffer of ones. Useful for
+/// precomputing window coefficients that are reused for many windows.
+pub fn fill_window_function(window_function: WindowFunctionType, buffer: &mut [f32]) {
+    buffer.fill(1.0);
+    apply_window_function(window_function, buffer);
+}
+
 /// Performs point-wise multiplication of a buffer and the Hann window function.
 fn hann_window(buffer: &mut [f32]) {
+    // 0.5 - 0.5 * cos(2 * pi * n / (N - 1))
+    raised_cosine_window(buffer, |s| 0.5 + 0.5 * s)
+}
+
+/// Performs point-wise multiplication of a buffer and the Hamming window function.
+fn hamming_window(buffer: &mut [f32]) {
+    // 0.54 - 0.46 * cos(2 * pi * n / (N - 1))
+    raised_cosine_window(buffer, |s| 0.54 + 0.46 * s)
+}
+
+/// Performs point-wise multiplication of a buffer and the Blackman window function.
+fn blackman_window(buffer: &mut [f32]) {
+    // 0.42 - 0.5 * cos(2 * pi * n / (N - 1)) + 0.08 * cos(4 * pi * n / (N - 1)),
+    // which, using cos(2t) = 2 * cos(t)^2 - 1, becomes
+    // 0.34 + 0.5 * s + 0.16 * s^2
+    raised_cosine_window(buffer, |s| 0.34 + 0.5 * s + 0.16 * s * s)
+}
+
+/// Performs point-wise multiplication of a buffer and the Tukey window function.
+fn tukey_window(buffer: &mut [f32], alpha: f32) {
+    if !(0.0..=1.0).contains(&alpha) {
+        panic!("Tukey window alpha must be in [0, 1]")
+    }
+    if alpha == 0.0 {
+        // Rectangular window
+        return;
+    }
+    if alpha == 1.0 {
+        hann_window(buffer);
+        return;
+    }
+
+    let len = buffer.len();
+    // The length of each tapered region in samples
+    let taper_len = 0.5 * alpha * (len.max(1) - 1) as f32;
+    for (i, value) in buffer.iter_mut().enumerate() {
+        // Distance from the nearest endpoint
+        let n = i.min(len - 1 - i) as f32;
+        if n < taper_len {
+            // 0.5 - 0.5 * cos(pi * n / taper_len)
+            let x = 2.0 * n / taper_len - 1.0;
+            *value *= 0.5 + 0.5 * approx_sin_half_pi(x);
+        }
+    }
+}
+
+/// Approximates sin(0.5 * pi * x) for x in [-1, 1].
+fn approx_sin_half_pi(x: f32) -> f32 {
     // sin(0.5 * pi * x) can be approximated with a
-    // max error below 0.0003 and exactly matching endpoints on [-1, 1] as
+    // max error below 0.0006 and exactly matching endpoints on [-1, 1] as
     // ax^5 + bx^3 + cx,
     // where
     // a = pi / 2 - 1.5
     // b = 2.5 - pi
     // c = pi / 2
+    let a = PI / 2. - 1.5;
+    let b = 2.5 - PI;
+    let c = PI / 2.;
+    let x3 = x * x * x;
+    let x5 = x3 * x * x;
+    a * x5 + b * x3 + c * x
+}
 
-    let a = 0.5 * (PI / 2. - 1.5);
-    let b = 0.5 * (2.5 - PI);
-    let c = 0.5 * PI / 2.;
-    let d = 0.5;
-
+/// Performs point-wise multiplication of a buffer and a window function
+/// expressed in terms of s = -cos(2 * pi * n / (N - 1)).
+fn raised_cosine_window<F: Fn(f32) -> f32>(buffer: &mut [f32], window_value: F) {
+    // Over each half of the window, s equals sin(0.5 * pi * x) for x in [-1, 1].
     let len = buffer.len();
     let dx = 4. / ((len - 1) as f32);
     let len_is_even = len % 2 == 0;
@@ -40,10 +113,7 @@ fn hann_window(buffer: &mut [f32]) {
     let left_half_end_len = if len_is_even { len / 2 } else { len / 2 + 1 };
     let mut x = -1.0;
     for value in buffer.iter_mut().take(left_half_end_len) {
-        let x3 = x * x * x;
-        let x5 = x3 * x * x;
-        let window_value = a * x5 + b * x3 + c * x + d;
-        *value *= window_value;
+        *value *= window_value(approx_sin_half_pi(x));
         x += dx;
     }
 
@@ -54,10 +124,7 @@ fn hann_window(buffer: &mut [f32]) {
         1.0 - dx
     };
     for value in buffer.iter_mut().skip(left_half_end_len) {
-        let x3 = x * x * x;
-        let x5 = x3 * x * x;
-        let window_value = a * x5 + b * x3 + c * x + d;
-        *value *= window_value;
+        *value *= window_value(approx_sin_half_pi(x));
         x -= dx;
     }
 }
@@ -81,8 +148,11 @@ fn welch_window(buffer: &mut [f32]) {
 
 #[cfg(test)]
 mod tests {
-    use crate::common::window_function::{hann_window, welch_window};
-    use alloc::vec;
+    use crate::common::window_function::{
+        apply_window_function, blackman_window, fill_window_function, hamming_window, hann_window,
+        tukey_window, welch_window, WindowFunctionType,
+    };
+    use alloc::{vec, vec::Vec};
     use core::f32::consts::PI;
 
     #[test]
@@ -127,4 +197,113 @@ mod tests {
             }
         }
     }
+
+    #[test]
+    fn test_hamming_window() {
+        let hamming_exact = |n: usize, size: usize| -> f32 {
+            if n == 0 {
+                return 0.08;
+            }
+            0.54 - 0.46 * (2.0 * PI * (n as f32) / ((size - 1) as f32)).cos()
+        };
+        let eps = 0.0003;
+        for window_size in [1, 2, 128, 4096, 100000] {
+            let mut window = vec![1.0; window_size];
+            hamming_window(&mut window);
+            for (i, value_approx) in window.iter().enumerate() {
+                let exact_value = hamming_exact(i, window.len());
+                let error = (exact_value - value_approx).abs();
+                assert!(error < eps);
+            }
+        }
+    }
+
+    #[test]
+    fn test_blackman_window() {
+        let blackman_exact = |n: usize, size: usize| -> f32 {
+            if n == 0 {
+                return 0.0;
+            }
+            let t = 2.0 * PI * (n as f32) / ((size - 1) as f32);
+            0.42 - 0.5 * t.cos() + 0.08 * (2.0 * t).cos()
+        };
+        let eps = 0.0005;
+        for window_size in [1, 2, 128, 4096, 100000] {
+            let mut window = vec![1.0; window_size];
+            blackman_window(&mut window);
+            for (i, value_approx) in window.iter().enumerate() {
+                let exact_value = blackman_exact(i, window.len());
+                let error = (exact_value - value_approx).abs();
+                assert!(error < eps);
+            }
+        }
+    }
+
+    #[test]
+    fn test_tukey_window() {
+        let tukey_exact = |n: usize, size: usize, alpha: f32| -> f32 {
+            let taper_len = 0.5 * alpha * ((size - 1) as f32);
+            let n = n.min(size - 1 - n) as f32;
+            if n < taper_len {
+                0.5 - 0.5 * (PI * n / taper_len).cos()
+            } else {
+                1.0
+            }
+        };
+        let eps = 0.0003;
+        for window_size in [1, 2, 101, 128, 4096] {
+            for alpha in [0.0, 0.1, 0.25, 0.5, 0.75, 1.0] {
+                let mut window = vec![1.0; window_size];
+                tukey_window(&mut window, alpha);
+                for (i, value_approx) in window.iter().enumerate() {
+                    let exact_value = if alpha == 1.0 && window_size == 1 {
+                        // Matches the Hann window
+                        0.0
+                    } else {
+                        tukey_exact(i, window.len(), alpha)
+                    };
+                    let error = (exact_value - value_approx).abs();
+                    assert!(error < eps);
+                }
+                if window_size > 2 {
+                    // Endpoints are zero unless the window is rectangular
+                    let endpoint = if alpha == 0.0 { 1.0 } else { 0.0 };
+                    assert_eq!(window[0], endpoint);
+                    assert!((window[window_size - 1] - endpoint).abs() < eps);
+                    // The middle of the window is never tapered
+                    assert!((window[window_size / 2] - 1.0).abs() < eps);
+                }
+            }
+        }
+    }
+
+    #[test]
+    #[should_panic]
+    fn test_tukey_window_invalid_alpha() {
+        let mut window = vec![1.0; 16];
+        tukey_window(&mut window, 1.5);
+    }
+
+    #[test]
+    fn test_fill_window_function() {
+        let window_size = 128;
+        for window_function in [
+            WindowFunctionType::Hann,
+            WindowFunctionType::Welch,
+            WindowFunctionType::Hamming,
+            WindowFunctionType::Blackman,
+            WindowFunctionType::Tukey(0.5),
+        ] {
+            let mut coefficients = vec![123.0; window_size];
+            fill_window_function(window_function, &mut coefficients);
+            let mut window: Vec<f32> = (0..window_size).map(|i| (i as f32).sin()).collect();
+            let expected: Vec<f32> = window
+                .iter()
+                .zip(coefficients.iter())
+                .map(|(x, c)| x * c)
+                .collect();
+            apply_window_function(window_function, &mut window);
+            assert_eq!(window, expected);
+        }
+    }
 }
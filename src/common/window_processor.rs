@@ -1,59 +1,161 @@
-use alloc::{boxed::Box, vec};
+use core::marker::PhantomData;
+
+use super::{Biquad, RingBuffer, Sample};
+use crate::Error;
+
+/// A 4th order Butterworth low pass filter, applied to input samples
+/// before downsampling to reduce aliasing.
+struct AntiAliasingFilter {
+    stages: [Biquad; 2],
+}
+
+impl AntiAliasingFilter {
+    fn new(downsampling: usize) -> Self {
+        // Cut off at half the Nyquist frequency after downsampling, giving
+        // roughly 25 dB attenuation at the downsampled Nyquist frequency.
+        let cutoff = 0.25 / (downsampling as f32);
+        AntiAliasingFilter {
+            stages: [
+                Biquad::lowpass(1.0, cutoff, 0.541_196_1),
+                Biquad::lowpass(1.0, cutoff, 1.306_563),
+            ],
+        }
+    }
+
+    fn process_sample(&mut self, input: f32) -> f32 {
+        let output = self.stages[0].process_sample(input);
+        self.stages[1].process_sample(output)
+    }
+
+    fn reset(&mut self) {
+        for stage in self.stages.iter_mut() {
+            stage.reset();
+        }
+    }
+}
 
 /// Provides fixed size windows extracted from
 /// a stream of arbitrarily sized input buffers. Supports
 /// downsampling and partially overlapping windows. Useful
 /// for implementing algorithms operating on
-/// consecutive windows of the same size.
-pub struct WindowProcessor {
-    downsampled_window: Box<[f32]>,
+/// consecutive windows of the same size. Input samples of
+/// type `T` are converted to `f32` as they are written to the window.
+pub struct WindowProcessor<T: Sample = f32> {
+    // The most recent downsampled samples
+    downsampled_window: RingBuffer,
     downsampling: usize,
     downsampled_hop_size: usize,
-    // Downsampled window write index
+    // The number of samples of the next window written so far
     write_index: usize,
+    // The number of windows passed to the handler since creation or the last reset.
+    window_count: usize,
+    // The number of samples at the start of the window that were
+    // included in the previous window.
+    retained_sample_count: usize,
     wrapped_sample_counter: usize,
+    anti_aliasing_filter: Option<AntiAliasingFilter>,
+    sample_type: PhantomData<T>,
 }
 
-fn validate_sizes(downsampled_size: usize, downsampled_hop_size: usize, downsampling: usize) {
+fn validate_sizes(
+    downsampled_size: usize,
+    downsampled_hop_size: usize,
+    downsampling: usize,
+) -> Result<(), Error> {
     if downsampled_size == 0 {
-        panic!("Downsampled size must be greater than 0")
+        return Err(Error::InvalidWindowSize(downsampled_size));
     }
-    if downsampled_hop_size == 0 {
-        panic!("Downsampled hop size must be greater than 0")
+    if downsampled_hop_size == 0 || downsampled_hop_size > downsampled_size {
+        return Err(Error::InvalidHopSize {
+            hop_size: downsampled_hop_size,
+            window_size: downsampled_size,
+        });
     }
     if downsampling == 0 {
-        panic!("Downsampling must be greater than 0")
-    }
-    if downsampled_hop_size > downsampled_size {
-        panic!("Downsampled hop size must not be greater than downsampled size")
+        return Err(Error::InvalidDownsampling(downsampling));
     }
+    Ok(())
 }
 
-impl WindowProcessor {
+impl<T: Sample> WindowProcessor<T> {
     /// Creates a new `WindowProcessor` instance.
     /// # Arguments
     ///
-    /// * `downsampling` - The downsampling factor (1 corresponds to no downsampling)
     /// * `downsampled_window_size` - The window size _after downsampling_.
     /// * `downsampled_hop_size` - The distance, _after downsampling_, between the start of windows. Must not be zero and not be greater than `downsampled_window_size`.
+    /// * `downsampling` - The downsampling factor (1 corresponds to no downsampling)
     pub fn new(
-        downsampling: usize,
         downsampled_window_size: usize,
         downsampled_hop_size: usize,
+        downsampling: usize,
     ) -> Self {
-        validate_sizes(downsampled_window_size, downsampled_hop_size, downsampling);
-        WindowProcessor {
-            downsampled_window: vec![0.; downsampled_window_size].into_boxed_slice(),
+        WindowProcessor::try_new(downsampled_window_size, downsampled_hop_size, downsampling)
+            .unwrap_or_else(|error| panic!("{}", error))
+    }
+
+    /// Like `new`, but returns an error instead of panicking if the sizes are invalid.
+    pub fn try_new(
+        downsampled_window_size: usize,
+        downsampled_hop_size: usize,
+        downsampling: usize,
+    ) -> Result<Self, Error> {
+        validate_sizes(downsampled_window_size, downsampled_hop_size, downsampling)?;
+        Ok(WindowProcessor {
+            downsampled_window: RingBuffer::new(downsampled_window_size),
             downsampled_hop_size,
             downsampling,
             write_index: 0,
+            window_count: 0,
+            retained_sample_count: 0,
             wrapped_sample_counter: 0,
-        }
+            anti_aliasing_filter: None,
+            sample_type: PhantomData,
+        })
+    }
+
+    /// Like `new`, but with the anti-aliasing filter enabled.
+    /// See [`WindowProcessor::set_anti_aliasing_filter`].
+    pub fn with_anti_aliasing_filter(
+        downsampled_window_size: usize,
+        downsampled_hop_size: usize,
+        downsampling: usize,
+    ) -> Self {
+        let mut processor =
+            WindowProcessor::new(downsampled_window_size, downsampled_hop_size, downsampling);
+        processor.set_anti_aliasing_filter(true);
+        processor
+    }
+
+    /// Enables or disables low pass filtering of input samples before downsampling,
+    /// which reduces aliasing of content above the downsampled Nyquist frequency.
+    /// The filter is a 4th order Butterworth low pass filter with a cutoff at half the
+    /// downsampled Nyquist frequency. It delays low frequency content by roughly
+    /// `1.7 * downsampling` input samples. Has no effect if the downsampling factor is 1.
+    /// Disabled by default.
+    pub fn set_anti_aliasing_filter(&mut self, enabled: bool) {
+        self.anti_aliasing_filter = if enabled && self.downsampling > 1 {
+            Some(AntiAliasingFilter::new(self.downsampling))
+        } else {
+            None
+        };
     }
 
+    /// Returns true if the anti-aliasing filter is enabled.
+    pub fn anti_aliasing_filter(&self) -> bool {
+        self.anti_aliasing_filter.is_some()
+    }
+
+    /// Resets the processor, discarding any partially filled window
+    /// and restarting the window count at zero.
     pub fn reset(&mut self) {
+        self.downsampled_window.clear();
         self.write_index = 0;
+        self.window_count = 0;
+        self.retained_sample_count = 0;
         self.wrapped_sample_counter = 0;
+        if let Some(filter) = self.anti_aliasing_filter.as_mut() {
+            filter.reset();
+        }
     }
 
     /// Returns the downsampling factor.
@@ -68,31 +170,93 @@ impl WindowProcessor {
 
     /// Returns the window size _after downsampling_.
     pub fn downsampled_window_size(&self) -> usize {
-        self.downsampled_window.len()
+        self.downsampled_window.capacity()
+    }
+
+    /// Returns the number of input samples needed to complete the next window.
+    pub fn samples_until_next_window(&self) -> usize {
+        let remaining_downsampled = self.downsampled_window.capacity() - self.write_index;
+        // The offset of the next kept input sample
+        let skip = (self.downsampling - self.wrapped_sample_counter) % self.downsampling;
+        skip + (remaining_downsampled - 1) * self.downsampling + 1
     }
 
     /// Processes an arbitrarily sized buffer of input samples. Invokes
-    /// the provided handler with each newly filled window.
-    pub fn process<F>(&mut self, buffer: &[f32], mut handler: F)
+    /// the provided handler with the index of each newly filled window,
+    /// counting from zero since creation or the last reset, and the window itself.
+    pub fn process<F>(&mut self, buffer: &[T], handler: F)
     where
-        F: FnMut(&[f32]),
+        F: FnMut(usize, &[f32]),
     {
-        let downsampled_window_size = self.downsampled_window.len();
-        let skip = (self.downsampling - self.wrapped_sample_counter) % self.downsampling;
-        for input in buffer.iter().skip(skip).step_by(self.downsampling) {
-            self.downsampled_window[self.write_index] = *input;
-            self.write_index += 1;
-            if self.write_index == downsampled_window_size {
-                handler(&self.downsampled_window);
-                self.downsampled_window
-                    .rotate_left(self.downsampled_hop_size);
-                self.write_index = downsampled_window_size - self.downsampled_hop_size;
+        self.process_with(buffer, T::to_f32, handler)
+    }
+
+    /// Like `process`, but converts each sample to `f32` using `convert`
+    /// as it is written to the window, avoiding an intermediate buffer.
+    pub(crate) fn process_with<S, C, F>(&mut self, buffer: &[S], convert: C, mut handler: F)
+    where
+        S: Copy,
+        C: Fn(S) -> f32,
+        F: FnMut(usize, &[f32]),
+    {
+        match self.anti_aliasing_filter.take() {
+            Some(mut filter) => {
+                // Every sample passes through the filter, but only
+                // every downsampling:th filtered sample is kept.
+                let mut phase = self.wrapped_sample_counter;
+                for input in buffer.iter() {
+                    let sample = filter.process_sample(convert(*input));
+                    if phase == 0 {
+                        self.push_sample(sample, &mut handler);
+                    }
+                    phase = (phase + 1) % self.downsampling;
+                }
+                self.anti_aliasing_filter = Some(filter);
+            }
+            None => {
+                let skip = (self.downsampling - self.wrapped_sample_counter) % self.downsampling;
+                for input in buffer.iter().skip(skip).step_by(self.downsampling) {
+                    self.push_sample(convert(*input), &mut handler);
+                }
             }
         }
 
         self.wrapped_sample_counter =
             (self.wrapped_sample_counter + buffer.len()) % self.downsampling
     }
+
+    fn push_sample<F>(&mut self, sample: f32, handler: &mut F)
+    where
+        F: FnMut(usize, &[f32]),
+    {
+        let downsampled_window_size = self.downsampled_window.capacity();
+        self.downsampled_window.push(sample);
+        self.write_index += 1;
+        if self.write_index == downsampled_window_size {
+            handler(self.window_count, self.downsampled_window.as_slice());
+            self.window_count += 1;
+            // The ring buffer keeps the samples shared with the next window
+            self.write_index = downsampled_window_size - self.downsampled_hop_size;
+            self.retained_sample_count = self.write_index;
+        }
+    }
+
+    /// Handles the end of a stream of input samples. If there are samples that have
+    /// not yet been included in a window, the rest of the window is filled with zeros and
+    /// the handler is invoked with it. Then resets the processor. Ensures every input sample
+    /// is included in at least one window.
+    pub fn flush<F>(&mut self, mut handler: F)
+    where
+        F: FnMut(usize, &[f32]),
+    {
+        if self.write_index > self.retained_sample_count {
+            for _ in self.write_index..self.downsampled_window.capacity() {
+                self.downsampled_window.push(0.0);
+            }
+            handler(self.window_count, self.downsampled_window.as_slice());
+        }
+        self.reset();
+    }
 }
 
 #[cfg(test)]
@@ -101,29 +265,30 @@ mod tests {
     use alloc::vec::Vec;
 
     use super::WindowProcessor;
+    use crate::common::Sample;
 
     #[test]
     #[should_panic]
     fn test_zero_window_size() {
-        WindowProcessor::new(1, 0, 256);
+        WindowProcessor::<f32>::new(0, 256, 1);
     }
 
     #[test]
     #[should_panic]
     fn test_zero_hop_size() {
-        WindowProcessor::new(1, 256, 0);
+        WindowProcessor::<f32>::new(256, 0, 1);
     }
 
     #[test]
     #[should_panic]
     fn test_too_large_hop_size() {
-        WindowProcessor::new(1, 256, 257);
+        WindowProcessor::<f32>::new(256, 257, 1);
     }
 
     #[test]
     #[should_panic]
     fn test_zero_downsampling() {
-        WindowProcessor::new(0, 256, 256);
+        WindowProcessor::<f32>::new(256, 256, 0);
     }
 
     #[test]
@@ -135,12 +300,12 @@ mod tests {
         let window_count = 10;
         let sample_count = chunk_size * window_count;
         let samples = vec![0.0; sample_count];
-        let mut processor = WindowProcessor::new(downsampling, window_size, hop_size);
+        let mut processor = WindowProcessor::new(window_size, hop_size, downsampling);
         let mut first_idx = 0;
         let mut winow_counter = 0;
         while first_idx < sample_count {
             let chunk = &samples[first_idx..(first_idx + chunk_size)];
-            processor.process(chunk, |_| {
+            processor.process(chunk, |_, _| {
                 winow_counter += 1;
             });
             first_idx += chunk_size;
@@ -160,7 +325,7 @@ mod tests {
         for downsampling in 1..10 {
             for hop_size in 1..=window_size {
                 for chunk_size in 1..5 * window_size {
-                    let mut processor = WindowProcessor::new(downsampling, window_size, hop_size);
+                    let mut processor = WindowProcessor::new(window_size, hop_size, downsampling);
                     let mut processed_window_count = 0;
                     let mut input_buffer_pos = 0;
                     // Feed the processor chunks of chunk_size samples
@@ -173,7 +338,8 @@ mod tests {
                         let chunk = &input_buffer[chunk_start_idx..chunk_end_idx];
                         assert_eq!(chunk.len(), current_chunk_size);
 
-                        processor.process(chunk, |window| {
+                        processor.process(chunk, |window_index, window| {
+                            assert_eq!(window_index, processed_window_count);
                             // Verify that the first sample of the extrated window
                             // corresponds to the correct input_buffer value
                             assert_eq!(
@@ -190,4 +356,156 @@ mod tests {
             }
         }
     }
+
+    #[test]
+    fn test_anti_aliasing_filter() {
+        let downsampling = 4;
+        let window_size = 256;
+        // A sine above the downsampled Nyquist frequency, which
+        // aliases when downsampling without filtering.
+        let frequency = 0.45;
+        let input_buffer: Vec<f32> = (0..(4 * downsampling * window_size))
+            .map(|i| (2.0 * core::f32::consts::PI * frequency * (i as f32)).sin())
+            .collect();
+        let peak = |processor: &mut WindowProcessor| {
+            let mut peak: f32 = 0.0;
+            processor.process(&input_buffer[..], |_, window| {
+                peak = window.iter().fold(0.0, |peak: f32, x| peak.max(x.abs()));
+            });
+            peak
+        };
+
+        let mut processor = WindowProcessor::new(window_size, window_size, downsampling);
+        assert!(!processor.anti_aliasing_filter());
+        assert!(peak(&mut processor) > 0.9);
+
+        let mut processor =
+            WindowProcessor::with_anti_aliasing_filter(window_size, window_size, downsampling);
+        assert!(processor.anti_aliasing_filter());
+        assert!(peak(&mut processor) < 0.01);
+
+        // No filtering without downsampling
+        let processor =
+            WindowProcessor::<f32>::with_anti_aliasing_filter(window_size, window_size, 1);
+        assert!(!processor.anti_aliasing_filter());
+    }
+
+    #[test]
+    fn test_anti_aliasing_filter_passband() {
+        let downsampling = 4;
+        let window_size = 256;
+        let frequency = 0.01;
+        let input_buffer: Vec<f32> = (0..(4 * downsampling * window_size))
+            .map(|i| (2.0 * core::f32::consts::PI * frequency * (i as f32)).sin())
+            .collect();
+        let mut processor =
+            WindowProcessor::with_anti_aliasing_filter(window_size, window_size, downsampling);
+        let mut window_count = 0;
+        processor.process(&input_buffer[..], |_, window| {
+            window_count += 1;
+            let peak = window.iter().fold(0.0, |peak: f32, x| peak.max(x.abs()));
+            assert!((peak - 1.0).abs() < 0.01);
+        });
+        assert_eq!(window_count, 4);
+    }
+
+    #[test]
+    fn test_flush() {
+        let input_buffer: Vec<f32> = (1..=5).map(|v| v as f32).collect();
+        let mut processor = WindowProcessor::new(4, 2, 1);
+        let mut windows: Vec<Vec<f32>> = Vec::new();
+        processor.process(&input_buffer[..], |_, window| windows.push(window.to_vec()));
+        processor.flush(|_, window| windows.push(window.to_vec()));
+        assert_eq!(
+            windows,
+            vec![vec![1.0, 2.0, 3.0, 4.0], vec![3.0, 4.0, 5.0, 0.0]]
+        );
+
+        // Nothing pending after flushing
+        processor.flush(|_, _| panic!("Unexpected window"));
+
+        // Nothing pending right after a window
+        processor.process(&input_buffer[..4], |_, _| {});
+        processor.flush(|_, _| panic!("Unexpected window"));
+
+        // A partially filled first window
+        windows.clear();
+        processor.process(&input_buffer[..3], |_, window| {
+            windows.push(window.to_vec())
+        });
+        processor.flush(|_, window| windows.push(window.to_vec()));
+        assert_eq!(windows, vec![vec![1.0, 2.0, 3.0, 0.0]]);
+    }
+
+    #[test]
+    fn test_window_index() {
+        let input_buffer = vec![0.0; 100];
+        let mut processor = WindowProcessor::new(10, 5, 2);
+        let mut window_indices: Vec<usize> = Vec::new();
+        processor.process(&input_buffer[..50], |window_index, _| {
+            window_indices.push(window_index)
+        });
+        processor.process(&input_buffer[50..], |window_index, _| {
+            window_indices.push(window_index)
+        });
+        assert_eq!(window_indices, (0..9).collect::<Vec<usize>>());
+
+        // Resetting restarts the count
+        window_indices.clear();
+        processor.reset();
+        processor.process(&input_buffer[..], |window_index, _| {
+            window_indices.push(window_index)
+        });
+        assert_eq!(window_indices, (0..9).collect::<Vec<usize>>());
+    }
+
+    #[test]
+    fn test_samples_until_next_window() {
+        let input_buffer: Vec<f32> = vec![0.0; 1000];
+        for downsampling in 1..5 {
+            for chunk_size in 1..20 {
+                let mut processor = WindowProcessor::new(16, 5, downsampling);
+                assert_eq!(processor.samples_until_next_window(), downsampling * 15 + 1);
+                for chunk in input_buffer.chunks(chunk_size) {
+                    let remaining = processor.samples_until_next_window();
+                    let mut window_sample_index = None;
+                    for (i, sample) in chunk.iter().enumerate() {
+                        processor.process(&[*sample], |_, _| window_sample_index = Some(i));
+                        if window_sample_index.is_some() {
+                            break;
+                        }
+                    }
+                    match window_sample_index {
+                        Some(i) => assert_eq!(i + 1, remaining),
+                        None => assert!(remaining > chunk.len()),
+                    }
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_sample_types() {
+        let input_buffer: Vec<i16> = (0..100).map(|v| 100 * v).collect();
+        let mut windows: Vec<Vec<f32>> = Vec::new();
+        let mut windows_i16: Vec<Vec<f32>> = Vec::new();
+        let mut windows_f64: Vec<Vec<f32>> = Vec::new();
+
+        let mut processor: WindowProcessor = WindowProcessor::new(8, 4, 2);
+        let buffer: Vec<f32> = input_buffer.iter().map(|v| v.to_f32()).collect();
+        processor.process(&buffer[..], |_, window| windows.push(window.to_vec()));
+
+        let mut processor: WindowProcessor<i16> = WindowProcessor::new(8, 4, 2);
+        processor.process(&input_buffer[..], |_, window| {
+            windows_i16.push(window.to_vec())
+        });
+
+        let mut processor: WindowProcessor<f64> = WindowProcessor::new(8, 4, 2);
+        let buffer: Vec<f64> = buffer.iter().map(|v| *v as f64).collect();
+        processor.process(&buffer[..], |_, window| windows_f64.push(window.to_vec()));
+
+        assert!(!windows.is_empty());
+        assert_eq!(windows, windows_i16);
+        assert_eq!(windows, windows_f64);
+    }
 }
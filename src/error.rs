@@ -0,0 +1,82 @@
+use core::fmt;
+
+/// Errors returned by fallible constructors, e.g
+/// [`MpmPitchDetector::try_from_options`](crate::mpm::MpmPitchDetector::try_from_options).
+/// The corresponding panicking constructors panic with the `Display` text of these errors.
+#[derive(Clone, Copy, Debug, PartialEq)]
+#[non_exhaustive]
+pub enum Error {
+    /// The window size is not supported, e.g because it is 0.
+    InvalidWindowSize(usize),
+    /// The hop size is 0 or greater than the window size.
+    InvalidHopSize { hop_size: usize, window_size: usize },
+    /// The downsampling factor is 0.
+    InvalidDownsampling(usize),
+    /// The lag count is 0 or greater than the window size.
+    LagCountOutOfRange {
+        lag_count: usize,
+        window_size: usize,
+    },
+    /// There is no FFT implementation for the given size.
+    UnsupportedFftSize(usize),
+    /// The sample rate in Hz is not supported.
+    UnsupportedSampleRate(f32),
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Error::InvalidWindowSize(window_size) => {
+                write!(f, "Invalid window size {}", window_size)
+            }
+            Error::InvalidHopSize {
+                hop_size,
+                window_size,
+            } => write!(
+                f,
+                "Hop size {} must be in the range [1, {}]",
+                hop_size, window_size
+            ),
+            Error::InvalidDownsampling(downsampling) => {
+                write!(f, "Invalid downsampling factor {}", downsampling)
+            }
+            Error::LagCountOutOfRange {
+                lag_count,
+                window_size,
+            } => write!(
+                f,
+                "Lag count {} must be in the range [1, {}]",
+                lag_count, window_size
+            ),
+            Error::UnsupportedFftSize(size) => write!(f, "Unsupported fft size {}", size),
+            Error::UnsupportedSampleRate(sample_rate) => {
+                write!(f, "Unsupported sample rate {}", sample_rate)
+            }
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for Error {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use alloc::string::ToString;
+
+    #[test]
+    fn test_display() {
+        assert_eq!(
+            Error::LagCountOutOfRange {
+                lag_count: 129,
+                window_size: 128
+            }
+            .to_string(),
+            "Lag count 129 must be in the range [1, 128]"
+        );
+        assert_eq!(
+            Error::UnsupportedFftSize(100).to_string(),
+            "Unsupported fft size 100"
+        );
+    }
+}
@@ -0,0 +1,122 @@
+use crate::common::WindowProcessor;
+use crate::hps::result::{HpsPitchResult, DEFAULT_HARMONIC_COUNT};
+
+/// A streaming harmonic product spectrum pitch detector. Mirrors
+/// [`MpmPitchDetector`](crate::mpm::MpmPitchDetector).
+pub struct HpsPitchDetector {
+    sample_rate: f32,
+    window_processor: WindowProcessor,
+    result: HpsPitchResult,
+}
+
+impl HpsPitchDetector {
+    /// Creates a new `HpsPitchDetector` instance multiplying 5 harmonics.
+    /// `window_size` must be a power of 2 in the range [8, [`MAX_FFT_SIZE`](crate::common::MAX_FFT_SIZE)].
+    pub fn new(sample_rate: f32, window_size: usize, hop_size: usize) -> Self {
+        HpsPitchDetector::from_options(
+            sample_rate,
+            window_size,
+            hop_size,
+            DEFAULT_HARMONIC_COUNT,
+            1,
+        )
+    }
+
+    /// Creates a new `HpsPitchDetector` instance.
+    /// # Arguments
+    ///
+    /// * `sample_rate` - The input sample rate in Hz.
+    /// * `downsampled_window_size` - The window size _after downsampling_. Must be a power of 2 in the range [8, [`MAX_FFT_SIZE`](crate::common::MAX_FFT_SIZE)].
+    /// * `downsampled_hop_size` - The distance, _after downsampling_, between the start of windows.
+    /// * `harmonic_count` - The number of harmonics to multiply. See [`HpsPitchResult::new`].
+    /// * `downsampling` - The downsampling factor (1 corresponds to no downsampling).
+    pub fn from_options(
+        sample_rate: f32,
+        downsampled_window_size: usize,
+        downsampled_hop_size: usize,
+        harmonic_count: usize,
+        downsampling: usize,
+    ) -> Self {
+        HpsPitchDetector {
+            sample_rate,
+            window_processor: WindowProcessor::new(
+                downsampled_window_size,
+                downsampled_hop_size,
+                downsampling,
+            ),
+            result: HpsPitchResult::new(downsampled_window_size, harmonic_count),
+        }
+    }
+
+    /// Processes an arbitrarily sized buffer of input samples. Invokes the provided
+    /// handler with the result of each newly analyzed window and the index of the last
+    /// input sample in that window. The sample index is given in the original,
+    /// non-downsampled, sample domain and counts all samples passed to `process`
+    /// since the detector was created.
+    pub fn process<F>(&mut self, buffer: &[f32], mut result_handler: F)
+    where
+        F: FnMut(&HpsPitchResult, usize),
+    {
+        let result = &mut self.result;
+        let downsampling = self.window_processor.downsampling();
+        let downsampled_window_size = self.window_processor.downsampled_window_size();
+        let downsampled_hop_size = self.window_processor.downsampled_hop_size();
+        let sample_rate = self.sample_rate / (downsampling as f32);
+        self.window_processor
+            .process(buffer, |window_index, window| {
+                result.compute_for(window, sample_rate);
+                let last_downsampled_index =
+                    downsampled_window_size - 1 + window_index * downsampled_hop_size;
+                result_handler(result, downsampling * last_downsampled_index);
+            });
+    }
+
+    /// Returns the most recently computed pitch detection result.
+    pub fn result(&self) -> &HpsPitchResult {
+        &self.result
+    }
+
+    /// Returns the sample rate in Hz.
+    pub fn sample_rate(&self) -> f32 {
+        self.sample_rate
+    }
+
+    /// Returns the number of input samples needed before the next window is analyzed.
+    /// See [`WindowProcessor::samples_until_next_window`].
+    pub fn samples_until_next_window(&self) -> usize {
+        self.window_processor.samples_until_next_window()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use alloc::vec::Vec;
+
+    #[test]
+    fn test_process() {
+        let sample_rate = 44100.0;
+        let frequency = 330.0;
+        let signal: Vec<f32> = (0..12000)
+            .map(|i| {
+                let t = (i as f32) / sample_rate;
+                (1..4)
+                    .map(|h| (2.0 * core::f32::consts::PI * frequency * (h as f32) * t).sin())
+                    .sum()
+            })
+            .collect();
+
+        let mut detector = HpsPitchDetector::from_options(sample_rate, 1024, 512, 3, 2);
+        let mut sample_indices: Vec<usize> = Vec::new();
+        for chunk in signal.chunks(1000) {
+            detector.process(chunk, |result, sample_index| {
+                assert!((result.frequency - frequency).abs() < 2.0);
+                sample_indices.push(sample_index);
+            });
+        }
+        assert_eq!(sample_indices.len(), 10);
+        for (i, sample_index) in sample_indices.iter().enumerate() {
+            assert_eq!(*sample_index, 2 * (1023 + i * 512));
+        }
+    }
+}
@@ -0,0 +1,44 @@
+//! Monophonic pitch detection using the [harmonic product spectrum](https://www.chciken.com/digital/signal/processing/2020/05/13/guitar-tuner.html#hps)
+//! (HPS), an alternative to [`mpm`](crate::mpm).
+//!
+//! The power spectrum of each window is downsampled by the integer factors 1, 2, ..., `harmonic_count`
+//! and the downsampled spectra are multiplied. Since the harmonics of a tone line up at the
+//! fundamental frequency after downsampling, the product has its largest value there.
+//!
+//! # Examples
+//! ```
+//! use microdsp::hps::HpsPitchDetector;
+//!
+//! // Create an input buffer containing a tone at 220 Hz with a weak fundamental
+//! let sample_rate = 44100.0;
+//! let mut chunk: Vec<f32> = vec![0.0; 10000];
+//! for (i, sample) in chunk.iter_mut().enumerate() {
+//!     let t = (i as f32) / sample_rate;
+//!     *sample = 0.05 * (2.0 * core::f32::consts::PI * 220.0 * t).sin();
+//!     for harmonic in 2..6 {
+//!         *sample += (2.0 * core::f32::consts::PI * 220.0 * (harmonic as f32) * t).sin();
+//!     }
+//! }
+//!
+//! let mut detector = HpsPitchDetector::new(sample_rate, 4096, 1024);
+//! detector.process(&chunk[..], |result, _| {
+//!     assert!((result.frequency - 220.0).abs() < 1.0);
+//! });
+//! ```
+//! # HPS vs MPM
+//! HPS finds the fundamental frequency of tones with a weak fundamental, e.g speech limited
+//! to the telephone band, where MPM tends to pick the period of a strong harmonic. HPS still
+//! relies on some energy at the fundamental frequency, since that spectrum bin is part of the
+//! product. If the fundamental is missing entirely, or if the even harmonics are much stronger
+//! than the odd ones, HPS tends to report a frequency one octave or more too high. MPM, on the
+//! other hand, tends to err an octave too low (see [`MpmPitchResult::set_octave_correction`](crate::mpm::MpmPitchResult::set_octave_correction)).
+//!
+//! The frequency resolution of HPS is limited by the spectrum bin width `sample_rate / window_size`,
+//! which is refined using parabolic interpolation of the strongest harmonic. Low fundamentals
+//! therefore require larger windows than with MPM.
+
+mod hps_pitch_detector;
+mod result;
+
+pub use hps_pitch_detector::HpsPitchDetector;
+pub use result::HpsPitchResult;
@@ -0,0 +1,226 @@
+use alloc::{boxed::Box, vec};
+use micromath::F32Ext;
+
+use crate::common::{
+    fill_window_function, parabolic_peak, RealFft, WindowFunctionType, MAX_FFT_SIZE,
+};
+
+/// The default number of harmonics included in the harmonic product spectrum.
+pub const DEFAULT_HARMONIC_COUNT: usize = 5;
+
+/// A pitch detection result computed using the harmonic product spectrum.
+pub struct HpsPitchResult {
+    /// The estimated pitch frequency in Hz, or 0 if the result is not valid.
+    pub frequency: f32,
+    /// The harmonic product spectrum of the analyzed window, with bin `k` corresponding
+    /// to a fundamental frequency of `k * sample_rate / window_size` Hz. Computed from the
+    /// power spectrum normalized to a maximum of 1.
+    pub spectrum: Box<[f32]>,
+    /// The index of the selected `spectrum` bin.
+    pub peak_bin: usize,
+    /// The power spectrum of the analyzed window, normalized to a maximum of 1.
+    fft_buffer: Box<[f32]>,
+    fft: RealFft,
+    /// Precomputed window function coefficients.
+    window_coefficients: Box<[f32]>,
+    harmonic_count: usize,
+}
+
+impl HpsPitchResult {
+    /// Creates a new `HpsPitchResult` instance.
+    /// # Arguments
+    ///
+    /// * `window_size` - The size of the analyzed windows. Must be a power of 2 in the range [8, [`MAX_FFT_SIZE`]].
+    /// * `harmonic_count` - The number of harmonics, including the fundamental, to multiply. 1 means
+    ///   that the strongest spectrum peak is selected.
+    pub fn new(window_size: usize, harmonic_count: usize) -> Self {
+        if !window_size.is_power_of_two() || !(8..=MAX_FFT_SIZE).contains(&window_size) {
+            panic!(
+                "Window size must be a power of 2 in the range [8, {}]",
+                MAX_FFT_SIZE
+            )
+        }
+        if harmonic_count == 0 || harmonic_count >= window_size / 2 {
+            panic!("Harmonic count must be in the range [1, window_size / 2)")
+        }
+        let mut window_coefficients = vec![0.; window_size].into_boxed_slice();
+        fill_window_function(WindowFunctionType::Hann, &mut window_coefficients);
+        let bin_count = window_size / 2;
+        HpsPitchResult {
+            frequency: 0.0,
+            spectrum: vec![0.; (bin_count - 1) / harmonic_count + 1].into_boxed_slice(),
+            peak_bin: 0,
+            fft_buffer: vec![0.; window_size].into_boxed_slice(),
+            fft: RealFft::new(window_size),
+            window_coefficients,
+            harmonic_count,
+        }
+    }
+
+    /// Returns the number of harmonics multiplied to form the harmonic product spectrum.
+    pub fn harmonic_count(&self) -> usize {
+        self.harmonic_count
+    }
+
+    /// Returns the size of the analyzed windows.
+    pub fn window_size(&self) -> usize {
+        self.fft_buffer.len()
+    }
+
+    /// Indicates if the result has a valid pitch estimate. Silent windows give invalid results.
+    pub fn is_valid(&self) -> bool {
+        self.frequency > 0.0
+    }
+
+    /// Performs pitch detection on a given window, which must have the length `window_size()`.
+    pub fn compute_for(&mut self, window: &[f32], sample_rate: f32) {
+        if window.len() != self.fft_buffer.len() {
+            panic!(
+                "Got window of length {}, expected {}.",
+                window.len(),
+                self.fft_buffer.len()
+            )
+        }
+        self.frequency = 0.0;
+        self.peak_bin = 0;
+
+        for ((value, x), w) in self
+            .fft_buffer
+            .iter_mut()
+            .zip(window.iter())
+            .zip(self.window_coefficients.iter())
+        {
+            *value = x * w;
+        }
+        let power = self.fft.power_spectrum(&mut self.fft_buffer);
+        // Skip DC
+        power[0] = 0.0;
+        let max_power = power.iter().fold(0.0, |max: f32, value| max.max(*value));
+        if max_power <= 0.0 {
+            for value in self.spectrum.iter_mut() {
+                *value = 0.0;
+            }
+            return;
+        }
+        for value in power.iter_mut() {
+            *value /= max_power;
+        }
+
+        // Multiply the power spectrum downsampled by 1, 2, ..., harmonic_count
+        let mut peak_value = 0.0;
+        for (k, value) in self.spectrum.iter_mut().enumerate() {
+            *value = (1..=self.harmonic_count).map(|h| power[h * k]).product();
+            if *value > peak_value {
+                peak_value = *value;
+                self.peak_bin = k;
+            }
+        }
+        if self.peak_bin == 0 {
+            return;
+        }
+
+        // Refine the estimate using the strongest harmonic, since the error
+        // of its interpolated frequency is divided by its harmonic number.
+        let bin_count = power.len();
+        let mut best_harmonic = 1;
+        let mut best_bin = self.peak_bin;
+        for h in 1..=self.harmonic_count {
+            let center = h * self.peak_bin;
+            let start = (center - h / 2).max(1);
+            let end = (center + h / 2).min(bin_count - 2);
+            for bin in start..=end {
+                if power[bin] > power[best_bin] {
+                    best_harmonic = h;
+                    best_bin = bin;
+                }
+            }
+        }
+        let offset = if best_bin + 1 < bin_count {
+            // Interpolating magnitudes is less biased than interpolating power
+            let magnitude = |bin: usize| sqrt(power[bin]);
+            parabolic_peak(
+                magnitude(best_bin - 1),
+                magnitude(best_bin),
+                magnitude(best_bin + 1),
+            )
+            .0
+        } else {
+            0.0
+        };
+        let bin_width = sample_rate / (self.fft_buffer.len() as f32);
+        self.frequency = bin_width * ((best_bin as f32) + offset) / (best_harmonic as f32);
+    }
+}
+
+/// Square root with one Newton iteration to improve on the approximate `F32Ext::sqrt`.
+fn sqrt(x: f32) -> f32 {
+    let y = F32Ext::sqrt(x);
+    if y > 0. {
+        0.5 * (y + x / y)
+    } else {
+        y
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use core::f32::consts::PI;
+
+    /// Fills a window with a tone with a given fundamental amplitude
+    /// and unit amplitude harmonics 2 to 5.
+    fn fill_tone(window: &mut [f32], frequency: f32, fundamental_amplitude: f32, sample_rate: f32) {
+        for (i, sample) in window.iter_mut().enumerate() {
+            let t = (i as f32) / sample_rate;
+            *sample = fundamental_amplitude * (2.0 * PI * frequency * t).sin();
+            for harmonic in 2..6 {
+                *sample += (2.0 * PI * frequency * (harmonic as f32) * t).sin();
+            }
+        }
+    }
+
+    #[test]
+    fn test_sine() {
+        let sample_rate = 44100.0;
+        let mut window = [0.0; 2048];
+        for (i, sample) in window.iter_mut().enumerate() {
+            *sample = (2.0 * PI * 467.0 * (i as f32) / sample_rate).sin();
+        }
+        let mut result = HpsPitchResult::new(window.len(), 1);
+        result.compute_for(&window, sample_rate);
+        assert!(result.is_valid());
+        // Parabolic interpolation is accurate to about a tenth of a bin
+        let bin_width = sample_rate / (window.len() as f32);
+        assert!((result.frequency - 467.0).abs() < 0.1 * bin_width);
+    }
+
+    #[test]
+    fn test_weak_fundamental() {
+        let sample_rate = 44100.0;
+        let mut window = [0.0; 4096];
+        fill_tone(&mut window, 220.0, 0.05, sample_rate);
+
+        let mut result = HpsPitchResult::new(window.len(), DEFAULT_HARMONIC_COUNT);
+        result.compute_for(&window, sample_rate);
+        assert!((result.frequency - 220.0).abs() < 1.0);
+
+        // The strongest spectrum peak is a harmonic
+        let mut result = HpsPitchResult::new(window.len(), 1);
+        result.compute_for(&window, sample_rate);
+        assert!(result.frequency > 400.0);
+    }
+
+    #[test]
+    fn test_silence() {
+        let mut result = HpsPitchResult::new(1024, DEFAULT_HARMONIC_COUNT);
+        result.compute_for(&[0.0; 1024], 44100.0);
+        assert!(!result.is_valid());
+        assert!(result.spectrum.iter().all(|value| *value == 0.0));
+    }
+
+    #[test]
+    #[should_panic]
+    fn test_invalid_window_size() {
+        HpsPitchResult::new(1000, DEFAULT_HARMONIC_COUNT);
+    }
+}
@@ -8,8 +8,16 @@
 
 #![no_std]
 extern crate alloc;
+#[cfg(feature = "std")]
+extern crate std;
 
+#[cfg(feature = "cffi")]
+pub mod cffi;
 pub mod common;
+mod error;
+pub mod hps;
 pub mod mpm;
 pub mod nlms;
 pub mod sfnov;
+
+pub use error::Error;
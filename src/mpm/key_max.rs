@@ -1,4 +1,7 @@
-#[derive(Copy, Clone)]
+use crate::common::{parabolic_curvature, parabolic_peak};
+
+#[derive(Copy, Clone, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
 /// A key maximum, i.e an NSDF maximum that may or may not correspond
 /// to the pitch period.
 pub struct KeyMax {
@@ -11,15 +14,21 @@ pub struct KeyMax {
     /// The lag, in samples, for this maximum, approximated using parabolic interpolation.
     /// Not necessarily an integer.
     pub lag: f32,
+    /// The second order coefficient of the parabola used to interpolate `value` and `lag`.
+    /// Negative for a proper maximum. Larger magnitudes mean a sharper NSDF peak.
+    pub curvature: f32,
 }
 
 impl KeyMax {
-    pub(crate) fn new() -> Self {
+    /// Creates a zeroed `KeyMax`, e.g for filling the key maxima buffer passed to
+    /// [`MpmPitchResult::from_buffers`](crate::mpm::MpmPitchResult::from_buffers).
+    pub fn new() -> Self {
         KeyMax {
             lag_index: 0,
             value_at_lag_index: 0.0,
             value: 0.0,
             lag: 0.0,
+            curvature: 0.0,
         }
     }
 
@@ -35,18 +44,12 @@ impl KeyMax {
         let left = nsdf[left_index];
         let right = nsdf[right_index];
 
-        // Compute coefficients of a parabola ax^2 + bx + c passing through
-        // (-1, left), (0, max), (1, right)
-        let a = 0.5 * (right - 2.0 * value_at_lag_index + left);
-        let b = 0.5 * (right - left);
-        let c = value_at_lag_index;
-        // Find the x value where the derivative is zero, i.e where the parabola has its maximum
-        let x_max = if a != 0.0 { -b / (2.0 * a) } else { 0.0 };
-        let value = a * x_max * x_max + b * x_max + c;
+        let (x_max, value) = parabolic_peak(left, value_at_lag_index, right);
         let lag = (lag_index as f32) + x_max;
 
         self.value = value;
         self.lag = lag;
+        self.curvature = parabolic_curvature(left, value_at_lag_index, right);
     }
 }
 
@@ -69,6 +72,7 @@ mod tests {
             let mut key_max = KeyMax::new();
             key_max.set(&nsdf, 1);
             assert!((key_max.lag - 1.1666666_f32).abs() <= f32::EPSILON);
+            assert!((key_max.curvature - -1.5).abs() <= f32::EPSILON);
         }
     }
 }
@@ -23,10 +23,11 @@
 //! let mut detector = MpmPitchDetector::new(sample_rate, window_size, hop_size);
 //!
 //! // Perform pitch detection. The detector extracts and processes windows and
-//! // invokes the provided callback when a new window has been analyzed.
-//! detector.process(&chunk[..], |result| {
+//! // invokes the provided callback when a new window has been analyzed, passing
+//! // the result, the index of the last input sample in the window and the window.
+//! detector.process(&chunk[..], |result, sample_index, _| {
 //!     if result.is_tone() {
-//!         println!("Frequency {} Hz, clarity {}", result.frequency, result.clarity);
+//!         println!("Sample {}: frequency {} Hz, clarity {}", sample_index, result.frequency, result.clarity);
 //!         assert!((sine_frequency - result.frequency).abs() <= 0.01);
 //!     } else {
 //!         // No discernable pitch detected. Should not end up here, since
@@ -80,9 +81,20 @@
 
 mod key_max;
 mod mpm_pitch_detector;
+mod pitch_smoother;
+mod pitch_snapshot;
 mod result;
+mod result_f64;
+mod tuning_target;
 mod util;
+mod voicing;
+pub mod yin;
 
 pub use key_max::KeyMax;
 pub use mpm_pitch_detector::MpmPitchDetector;
-pub use result::MpmPitchResult;
+pub use pitch_smoother::PitchSmoother;
+pub use pitch_snapshot::PitchSnapshot;
+pub use result::{MpmPitchResult, MAX_KEY_MAXIMA_COUNT};
+pub use result_f64::MpmPitchResultF64;
+pub use tuning_target::TuningTarget;
+pub use voicing::Voicing;
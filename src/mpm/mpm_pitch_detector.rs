@@ -1,17 +1,77 @@
-use crate::common::WindowProcessor;
-use crate::mpm::result::MpmPitchResult;
+use alloc::vec::Vec;
+use micromath::F32Ext;
 
+use crate::common::{
+    autocorr_fft_size, downmix_interleaved, fill_window_function, i16_to_f32, EqualLoudnessFilter,
+    RealFft, WindowFunctionType, WindowProcessor,
+};
+use crate::mpm::result::{MpmPitchResult, MAX_KEY_MAXIMA_COUNT};
+use crate::mpm::util::validate_downsampled_lag_count;
+use crate::mpm::PitchSnapshot;
+use crate::Error;
+
+/// The number of samples filtered at a time when equal loudness filtering is enabled.
+const EQUAL_LOUDNESS_CHUNK_SIZE: usize = 256;
+
+/// Collects input samples into possibly overlapping windows and performs MPM pitch
+/// detection on each newly filled window. As described in the MPM paper, no window
+/// function is applied to the analyzed samples by default, i.e windows are rectangular.
+/// See `set_window_function`.
 pub struct MpmPitchDetector {
     sample_rate: f32,
     window_processor: WindowProcessor,
     result: MpmPitchResult,
+    processed_sample_count: usize,
+    processed_window_count: usize,
+    // The index of the last input sample in the most recently analyzed window.
+    last_window_sample_index: usize,
+    // The processed sample count when the window processor was created.
+    first_sample_index: usize,
+    frequency_range: Option<(f32, f32)>,
+    equal_loudness_filter: Option<EqualLoudnessFilter>,
+    anti_aliasing_filter: bool,
+    // The time constant in seconds of the smoothed frequency.
+    smoothing_time_constant: f32,
+    // Clarity weighted moving average of tonal frequencies. 0 before the first tone.
+    smoothed_frequency: f32,
+    window_function: Option<WindowFunctionType>,
+    // The coefficients of the window function. Empty if there is no window function.
+    window_coefficients: Vec<f32>,
+    // The most recent window with the window function applied. Empty if there is no window function.
+    windowed_samples: Vec<f32>,
 }
 
 impl MpmPitchDetector {
     pub fn new(sample_rate: f32, window_size: usize, hop_size: usize) -> Self {
-        MpmPitchDetector::from_options(sample_rate, window_size, hop_size, window_size / 2, 1)
+        MpmPitchDetector::try_new(sample_rate, window_size, hop_size)
+            .unwrap_or_else(|error| panic!("{}", error))
     }
 
+    /// Like `new`, but returns an error instead of panicking if the sizes are invalid.
+    pub fn try_new(sample_rate: f32, window_size: usize, hop_size: usize) -> Result<Self, Error> {
+        MpmPitchDetector::try_from_options(sample_rate, window_size, hop_size, window_size / 2, 1)
+    }
+
+    /// Creates a new `MpmPitchDetector` with a given overlap between consecutive
+    /// windows, as a fraction of the window size in the range [0, 1). For example,
+    /// an overlap of 0.5 gives a hop size of half the window size. The hop size is
+    /// rounded to the nearest integer, but is at least 1.
+    pub fn with_overlap(sample_rate: f32, window_size: usize, overlap_ratio: f32) -> Self {
+        if !(0.0..1.0).contains(&overlap_ratio) {
+            panic!("Overlap ratio must be in the range [0, 1)")
+        }
+        let hop_size = F32Ext::round((window_size as f32) * (1.0 - overlap_ratio)) as usize;
+        MpmPitchDetector::new(sample_rate, window_size, hop_size.max(1))
+    }
+
+    /// Creates a new `MpmPitchDetector` instance.
+    /// # Arguments
+    ///
+    /// * `sample_rate` - The input sample rate in Hz.
+    /// * `downsampled_window_size` - The window size _after downsampling_.
+    /// * `downsampled_hop_size` - The distance, _after downsampling_, between the start of windows.
+    /// * `downsampled_lag_count` - The number of NSDF lags to compute. Must not be greater than the window size.
+    /// * `downsampling` - The downsampling factor (1 corresponds to no downsampling).
     pub fn from_options(
         sample_rate: f32,
         downsampled_window_size: usize,
@@ -19,29 +79,295 @@ impl MpmPitchDetector {
         downsampled_lag_count: usize,
         downsampling: usize,
     ) -> Self {
-        MpmPitchDetector {
+        MpmPitchDetector::try_from_options(
+            sample_rate,
+            downsampled_window_size,
+            downsampled_hop_size,
+            downsampled_lag_count,
+            downsampling,
+        )
+        .unwrap_or_else(|error| panic!("{}", error))
+    }
+
+    /// Like `from_options`, but returns an error instead of panicking if
+    /// the options are invalid.
+    pub fn try_from_options(
+        sample_rate: f32,
+        downsampled_window_size: usize,
+        downsampled_hop_size: usize,
+        downsampled_lag_count: usize,
+        downsampling: usize,
+    ) -> Result<Self, Error> {
+        let window_processor =
+            WindowProcessor::try_new(downsampled_window_size, downsampled_hop_size, downsampling)?;
+        validate_downsampled_lag_count(downsampled_window_size, downsampled_lag_count)?;
+        RealFft::try_new(autocorr_fft_size(
+            downsampled_window_size,
+            downsampled_lag_count,
+        ))?;
+        Ok(MpmPitchDetector {
             sample_rate,
-            result: MpmPitchResult::new(downsampled_window_size, downsampled_lag_count),
-            window_processor: WindowProcessor::new(
-                downsampling,
+            result: MpmPitchResult::without_window(
                 downsampled_window_size,
-                downsampled_hop_size,
+                downsampled_lag_count,
+                MAX_KEY_MAXIMA_COUNT,
             ),
+            window_processor,
+            processed_sample_count: 0,
+            processed_window_count: 0,
+            last_window_sample_index: 0,
+            first_sample_index: 0,
+            frequency_range: None,
+            equal_loudness_filter: None,
+            anti_aliasing_filter: false,
+            smoothing_time_constant: 0.0,
+            smoothed_frequency: 0.0,
+            window_function: None,
+            window_coefficients: Vec::new(),
+            windowed_samples: Vec::new(),
+        })
+    }
+
+    /// Changes the window size, hop size, lag count and downsampling. Reallocates
+    /// internal buffers and discards any partially filled window, meaning that
+    /// the first window analyzed after this call only contains new input samples.
+    /// Other settings are preserved and sample indices passed to the result handler
+    /// keep counting from where they were.
+    pub fn reconfigure(
+        &mut self,
+        downsampled_window_size: usize,
+        downsampled_hop_size: usize,
+        downsampled_lag_count: usize,
+        downsampling: usize,
+    ) {
+        self.try_reconfigure(
+            downsampled_window_size,
+            downsampled_hop_size,
+            downsampled_lag_count,
+            downsampling,
+        )
+        .unwrap_or_else(|error| panic!("{}", error))
+    }
+
+    /// Like `reconfigure`, but returns an error instead of panicking if the options
+    /// are invalid, in which case the detector is left unchanged.
+    pub fn try_reconfigure(
+        &mut self,
+        downsampled_window_size: usize,
+        downsampled_hop_size: usize,
+        downsampled_lag_count: usize,
+        downsampling: usize,
+    ) -> Result<(), Error> {
+        let mut window_processor =
+            WindowProcessor::try_new(downsampled_window_size, downsampled_hop_size, downsampling)?;
+        validate_downsampled_lag_count(downsampled_window_size, downsampled_lag_count)?;
+        RealFft::try_new(autocorr_fft_size(
+            downsampled_window_size,
+            downsampled_lag_count,
+        ))?;
+        window_processor.set_anti_aliasing_filter(self.anti_aliasing_filter);
+        let result = self.reallocated_result(
+            downsampled_window_size,
+            downsampled_lag_count,
+            self.result.key_max_capacity(),
+        );
+        self.window_processor = window_processor;
+        self.result = result;
+        self.first_sample_index = self.processed_sample_count;
+        self.update_lag_index_range();
+        self.set_window_function(self.window_function);
+        Ok(())
+    }
+
+    /// Returns a newly allocated result with the same settings as the current one.
+    fn reallocated_result(
+        &self,
+        window_size: usize,
+        lag_count: usize,
+        key_max_capacity: usize,
+    ) -> MpmPitchResult {
+        let mut result = MpmPitchResult::without_window(window_size, lag_count, key_max_capacity);
+        result.set_peak_threshold_ratio(self.result.peak_threshold_ratio());
+        result.set_octave_correction(self.result.octave_correction());
+        result.set_reference_frequency(self.result.reference_frequency());
+        result.set_noise_gate(self.result.noise_gate());
+        result.set_spectral_whitening(self.result.spectral_whitening());
+        result
+    }
+
+    /// Processes an arbitrarily sized buffer of input samples. Invokes the provided
+    /// handler with the result of each newly analyzed window, the index of the last
+    /// input sample in that window and the analyzed window itself. The sample index
+    /// is given in the original, non-downsampled, sample domain and counts all samples
+    /// passed to `process` since the detector was created. The analyzed window is in the
+    /// downsampled domain, i.e its sample rate is [`MpmPitchResult::effective_sample_rate`].
+    /// It is passed to the handler instead of being copied to [`MpmPitchResult::window`],
+    /// which is empty for the detector's result.
+    pub fn process<F>(&mut self, buffer: &[f32], result_handler: F)
+    where
+        F: FnMut(&MpmPitchResult, usize, &[f32]),
+    {
+        self.process_with(buffer, |sample| sample, result_handler)
+    }
+
+    /// Returns an iterator over snapshots of the results of the windows completed by
+    /// `buffer`, as an alternative to `process` for offline analysis. Input samples are
+    /// consumed as the iterator advances, so samples after the last yielded window
+    /// are only processed if the iterator is exhausted.
+    pub fn iter_windows<'a>(
+        &'a mut self,
+        buffer: &'a [f32],
+    ) -> impl Iterator<Item = PitchSnapshot> + 'a {
+        let mut position = 0;
+        core::iter::from_fn(move || {
+            while position < buffer.len() {
+                // Process just enough samples to complete the next window
+                let sample_count = self
+                    .samples_until_next_window()
+                    .max(1)
+                    .min(buffer.len() - position);
+                let mut snapshot = None;
+                self.process(
+                    &buffer[position..position + sample_count],
+                    |result, sample_index, _| {
+                        snapshot = Some(PitchSnapshot::new(result, sample_index))
+                    },
+                );
+                position += sample_count;
+                if snapshot.is_some() {
+                    return snapshot;
+                }
+            }
+            None
+        })
+    }
+
+    /// Processes an entire buffer and returns a snapshot of the result of each
+    /// analyzed window. A convenience for offline analysis, e.g of audio files.
+    pub fn analyze(&mut self, buffer: &[f32]) -> Vec<PitchSnapshot> {
+        let mut snapshots = Vec::new();
+        self.process(buffer, |result, sample_index, _| {
+            snapshots.push(PitchSnapshot::new(result, sample_index))
+        });
+        snapshots
+    }
+
+    /// Processes an entire buffer and returns the time in seconds of the last input sample
+    /// and the estimated pitch frequency of each analyzed window that is considered a tone.
+    /// See `last_window_timestamp` and [`MpmPitchResult::is_tone`].
+    pub fn analyze_pitches(&mut self, buffer: &[f32]) -> Vec<(f64, f32)> {
+        let sample_rate = self.sample_rate as f64;
+        let mut pitches = Vec::new();
+        self.process(buffer, |result, sample_index, _| {
+            if result.is_tone() {
+                pitches.push(((sample_index as f64) / sample_rate, result.frequency))
+            }
+        });
+        pitches
+    }
+
+    /// Like `process`, but takes 16 bit integer samples, which are converted
+    /// to `f32` samples in the range [-1, 1) as they are fed to the detector.
+    pub fn process_i16<F>(&mut self, buffer: &[i16], result_handler: F)
+    where
+        F: FnMut(&MpmPitchResult, usize, &[f32]),
+    {
+        self.process_with(buffer, i16_to_f32, result_handler)
+    }
+
+    /// Like `process`, but takes interleaved samples with the given number of channels,
+    /// which are downmixed to mono as they are fed to the detector. The downmix is a simple
+    /// average of the channels of each frame. Sample indices passed to the handler count
+    /// frames rather than interleaved samples. Panics if `channels` is 0 or if the buffer
+    /// length is not a multiple of `channels`.
+    pub fn process_interleaved<F>(&mut self, buffer: &[f32], channels: usize, mut result_handler: F)
+    where
+        F: FnMut(&MpmPitchResult, usize, &[f32]),
+    {
+        downmix_interleaved(buffer, channels, |mono| {
+            self.process(mono, &mut result_handler)
+        })
+    }
+
+    fn process_with<T, C, F>(&mut self, buffer: &[T], convert: C, mut result_handler: F)
+    where
+        T: Copy,
+        C: Fn(T) -> f32,
+        F: FnMut(&MpmPitchResult, usize, &[f32]),
+    {
+        match self.equal_loudness_filter.take() {
+            Some(mut filter) => {
+                let mut filtered = [0.0; EQUAL_LOUDNESS_CHUNK_SIZE];
+                for chunk in buffer.chunks(EQUAL_LOUDNESS_CHUNK_SIZE) {
+                    let filtered = &mut filtered[..chunk.len()];
+                    for (filtered, sample) in filtered.iter_mut().zip(chunk) {
+                        *filtered = filter.process_sample(convert(*sample));
+                    }
+                    self.process_windows(filtered, |sample| sample, &mut result_handler);
+                }
+                self.equal_loudness_filter = Some(filter);
+            }
+            None => self.process_windows(buffer, convert, result_handler),
         }
     }
 
-    pub fn process<F>(&mut self, buffer: &[f32], mut result_handler: F)
+    fn process_windows<T, C, F>(&mut self, buffer: &[T], convert: C, mut result_handler: F)
     where
-        F: FnMut(&MpmPitchResult),
+        T: Copy,
+        C: Fn(T) -> f32,
+        F: FnMut(&MpmPitchResult, usize, &[f32]),
     {
         let result = &mut self.result;
         let downsampling = self.window_processor.downsampling();
+        let downsampled_window_size = self.window_processor.downsampled_window_size();
+        let downsampled_hop_size = self.window_processor.downsampled_hop_size();
+        let first_sample_index = self.first_sample_index;
         let sample_rate = self.sample_rate;
-        self.window_processor.process(buffer, |window| {
-            result.window.copy_from_slice(window);
-            result.compute(sample_rate / (downsampling as f32));
-            result_handler(result);
-        });
+        let processed_window_count = &mut self.processed_window_count;
+        let last_window_sample_index = &mut self.last_window_sample_index;
+        let smoothed_frequency = &mut self.smoothed_frequency;
+        // The moving average coefficient for a window with a clarity of 1
+        let smoothing_coefficient = if self.smoothing_time_constant > 0.0 {
+            let hop_duration = ((downsampling * downsampled_hop_size) as f32) / sample_rate;
+            Some(1.0 - F32Ext::exp(-hop_duration / self.smoothing_time_constant))
+        } else {
+            None
+        };
+        let window_coefficients = &self.window_coefficients;
+        let windowed_samples = &mut self.windowed_samples;
+        self.window_processor
+            .process_with(buffer, convert, |window_index, window| {
+                let window = if window_coefficients.is_empty() {
+                    window
+                } else {
+                    for ((windowed, sample), coefficient) in windowed_samples
+                        .iter_mut()
+                        .zip(window)
+                        .zip(window_coefficients)
+                    {
+                        *windowed = coefficient * sample;
+                    }
+                    &windowed_samples[..]
+                };
+                result.compute_for(window, sample_rate / (downsampling as f32));
+                *processed_window_count += 1;
+                if result.is_tone() {
+                    *smoothed_frequency = match smoothing_coefficient {
+                        Some(coefficient) if *smoothed_frequency > 0.0 => {
+                            let weight = coefficient * result.clarity.clamp(0.0, 1.0);
+                            *smoothed_frequency + weight * (result.frequency - *smoothed_frequency)
+                        }
+                        _ => result.frequency,
+                    };
+                }
+                // Every downsampling:th input sample is kept, starting with the first one.
+                let last_downsampled_index =
+                    downsampled_window_size - 1 + window_index * downsampled_hop_size;
+                *last_window_sample_index =
+                    first_sample_index + downsampling * last_downsampled_index;
+                result_handler(result, *last_window_sample_index, window);
+            });
+        self.processed_sample_count += buffer.len();
     }
 
     /// Returns the most recently computed pitch detection result.
@@ -57,11 +383,252 @@ impl MpmPitchDetector {
     /// Sets the sample rate in Hz.
     pub fn set_sample_rate(&mut self, sample_rate: f32) {
         self.sample_rate = sample_rate;
+        self.update_lag_index_range();
+        if self.equal_loudness_filter.is_some() {
+            self.equal_loudness_filter = Some(EqualLoudnessFilter::new(sample_rate));
+        }
+    }
+
+    /// Enables or disables filtering of input samples using an [`EqualLoudnessFilter`]
+    /// before analysis, which can improve accuracy for voice. Disabled by default.
+    /// Enabling the filter requires a sample rate of 44100 or 48000 Hz.
+    pub fn set_equal_loudness_filter(&mut self, enabled: bool) {
+        self.equal_loudness_filter = if enabled {
+            Some(EqualLoudnessFilter::new(self.sample_rate))
+        } else {
+            None
+        };
+    }
+
+    /// Enables or disables low pass filtering before downsampling. Disabled by default.
+    /// See [`WindowProcessor::set_anti_aliasing_filter`].
+    pub fn set_anti_aliasing_filter(&mut self, enabled: bool) {
+        self.anti_aliasing_filter = enabled;
+        self.window_processor.set_anti_aliasing_filter(enabled);
+    }
+
+    /// Sets a window function to apply to each window before analyzing it, e.g for
+    /// experimenting with windowed autocorrelation. The windowed samples are passed
+    /// to the result handler given to `process`. Defaults to `None`, i.e a rectangular window, which
+    /// is what the MPM algorithm expects since the NSDF already compensates for the
+    /// decreasing overlap at larger lags. Tapering windows reduce the clarity
+    /// of tones and may bias frequency estimates slightly.
+    pub fn set_window_function(&mut self, window_function: Option<WindowFunctionType>) {
+        self.window_function = window_function;
+        self.window_coefficients.clear();
+        self.windowed_samples.clear();
+        if let Some(window_function) = window_function {
+            let downsampled_window_size = self.window_processor.downsampled_window_size();
+            self.window_coefficients
+                .resize(downsampled_window_size, 0.0);
+            self.windowed_samples.resize(downsampled_window_size, 0.0);
+            fill_window_function(window_function, &mut self.window_coefficients);
+        }
+    }
+
+    /// Returns the window function applied to each window, if any.
+    pub fn window_function(&self) -> Option<WindowFunctionType> {
+        self.window_function
+    }
+
+    /// Returns true if equal loudness filtering is enabled.
+    pub fn equal_loudness_filter(&self) -> bool {
+        self.equal_loudness_filter.is_some()
+    }
+
+    /// Restricts pitch detection to frequencies between `min_frequency` and
+    /// `max_frequency` Hz. Windows without NSDF key maxima in the corresponding
+    /// lag range give invalid results.
+    pub fn set_frequency_range(&mut self, min_frequency: f32, max_frequency: f32) {
+        self.frequency_range = Some((min_frequency, max_frequency));
+        self.update_lag_index_range();
+    }
+
+    /// Removes any frequency range restriction set using `set_frequency_range`.
+    pub fn clear_frequency_range(&mut self) {
+        self.frequency_range = None;
+        self.update_lag_index_range();
+    }
+
+    /// Returns the frequency range set using `set_frequency_range`, if any.
+    pub fn frequency_range(&self) -> Option<(f32, f32)> {
+        self.frequency_range
+    }
+
+    /// Returns the lowest and highest detectable frequencies in Hz given the current
+    /// sample rate, downsampling and lag count, narrowed to the frequency range set using
+    /// `set_frequency_range`, if any. Useful for choosing a window size and lag count that
+    /// cover the range of an instrument. See [`MpmPitchResult::detectable_frequency_range`].
+    pub fn detectable_frequency_range(&self) -> (f32, f32) {
+        let downsampled_sample_rate =
+            self.sample_rate / (self.window_processor.downsampling() as f32);
+        let (min_frequency, max_frequency) = self
+            .result
+            .detectable_frequency_range(downsampled_sample_rate);
+        match self.frequency_range {
+            Some((range_min, range_max)) => {
+                (min_frequency.max(range_min), max_frequency.min(range_max))
+            }
+            None => (min_frequency, max_frequency),
+        }
+    }
+
+    /// Returns the lowest detectable frequency in Hz. See `detectable_frequency_range`.
+    pub fn min_detectable_frequency(&self) -> f32 {
+        self.detectable_frequency_range().0
+    }
+
+    /// Returns the highest detectable frequency in Hz. See `detectable_frequency_range`.
+    pub fn max_detectable_frequency(&self) -> f32 {
+        self.detectable_frequency_range().1
+    }
+
+    fn update_lag_index_range(&mut self) {
+        match self.frequency_range {
+            Some((min_frequency, max_frequency)) => {
+                let downsampled_sample_rate =
+                    self.sample_rate / (self.window_processor.downsampling() as f32);
+                let min_lag = downsampled_sample_rate / max_frequency;
+                let max_lag = downsampled_sample_rate / min_frequency;
+                self.result.set_lag_index_range(
+                    F32Ext::floor(min_lag) as usize,
+                    F32Ext::ceil(max_lag) as usize,
+                );
+            }
+            None => self.result.clear_lag_index_range(),
+        }
+    }
+
+    /// Sets the key maximum selection threshold, relative to the largest key maximum.
+    /// See [`MpmPitchResult::set_peak_threshold_ratio`].
+    pub fn set_peak_threshold_ratio(&mut self, peak_threshold_ratio: f32) {
+        self.result.set_peak_threshold_ratio(peak_threshold_ratio);
+    }
+
+    /// Enables or disables octave error correction. Disabled by default.
+    /// See [`MpmPitchResult::set_octave_correction`].
+    pub fn set_octave_correction(&mut self, enabled: bool) {
+        self.result.set_octave_correction(enabled);
+    }
+
+    /// Sets the frequency in Hz of A4 used when computing MIDI note numbers.
+    /// See [`MpmPitchResult::set_reference_frequency`].
+    pub fn set_reference_frequency(&mut self, a4_freq: Option<f32>) {
+        self.result.set_reference_frequency(a4_freq);
+    }
+
+    /// Sets the maximum number of key maxima gathered per window. Reallocates the
+    /// result buffers, keeping all other settings. Defaults to
+    /// [`MAX_KEY_MAXIMA_COUNT`](crate::mpm::MAX_KEY_MAXIMA_COUNT).
+    /// See [`MpmPitchResult::with_key_max_capacity`].
+    pub fn set_key_max_capacity(&mut self, key_max_capacity: usize) {
+        self.result = self.reallocated_result(
+            self.result.window_size(),
+            self.result.nsdf.len(),
+            key_max_capacity,
+        );
+        self.update_lag_index_range();
+    }
+
+    /// Returns the maximum number of key maxima gathered per window.
+    pub fn key_max_capacity(&self) -> usize {
+        self.result.key_max_capacity()
+    }
+
+    /// Skips pitch detection for windows with an RMS level below `threshold_db`,
+    /// giving invalid results for these windows. See [`MpmPitchResult::set_noise_gate`].
+    pub fn set_noise_gate(&mut self, threshold_db: f32) {
+        self.result.set_noise_gate(Some(threshold_db));
+    }
+
+    /// Removes any noise gate set using `set_noise_gate`.
+    pub fn clear_noise_gate(&mut self) {
+        self.result.set_noise_gate(None);
+    }
+
+    /// Returns the noise gate threshold in dB set using `set_noise_gate`, if any.
+    pub fn noise_gate(&self) -> Option<f32> {
+        self.result.noise_gate()
+    }
+
+    /// Enables or disables spectral whitening. Disabled by default.
+    /// See [`MpmPitchResult::set_spectral_whitening`].
+    pub fn set_spectral_whitening(&mut self, enabled: bool) {
+        self.result.set_spectral_whitening(enabled);
+    }
+
+    /// Indicates if spectral whitening is enabled.
+    pub fn spectral_whitening(&self) -> bool {
+        self.result.spectral_whitening()
     }
 
     pub fn downsampled_window_size(&self) -> usize {
         self.window_processor.downsampled_window_size()
     }
+
+    /// Returns the hop size _after downsampling_.
+    pub fn downsampled_hop_size(&self) -> usize {
+        self.window_processor.downsampled_hop_size()
+    }
+
+    /// Returns the downsampling factor.
+    pub fn downsampling(&self) -> usize {
+        self.window_processor.downsampling()
+    }
+
+    /// Returns the number of input samples needed before the next window is analyzed.
+    /// See [`WindowProcessor::samples_until_next_window`].
+    pub fn samples_until_next_window(&self) -> usize {
+        self.window_processor.samples_until_next_window()
+    }
+
+    /// Returns the number of input samples passed to `process` since the detector was
+    /// created, in the original, non-downsampled, sample domain.
+    pub fn processed_sample_count(&self) -> usize {
+        self.processed_sample_count
+    }
+
+    /// Returns the number of windows analyzed since the detector was created,
+    /// including windows analyzed before any call to `reconfigure`.
+    pub fn processed_window_count(&self) -> usize {
+        self.processed_window_count
+    }
+
+    /// Sets the time constant in seconds of `smoothed_frequency`. 0, the default,
+    /// disables smoothing.
+    pub fn set_smoothing_time_constant(&mut self, time_constant: f32) {
+        if time_constant < 0.0 {
+            panic!("Smoothing time constant must not be negative")
+        }
+        self.smoothing_time_constant = time_constant;
+    }
+
+    /// Returns the time constant set using `set_smoothing_time_constant`.
+    pub fn smoothing_time_constant(&self) -> f32 {
+        self.smoothing_time_constant
+    }
+
+    /// Returns an exponential moving average of the frequencies of windows considered
+    /// tones by `is_tone`, e.g for a stable tuner display. Each window's contribution is
+    /// weighted by its clarity, so a window with a clarity of 1 moves the average by the
+    /// amount given by the time constant and less clear windows move it less. Windows that
+    /// are not tones do not affect the average. 0 until the first tone, which sets the
+    /// average directly. With smoothing disabled, this is the frequency of the most recent tone.
+    pub fn smoothed_frequency(&self) -> f32 {
+        self.smoothed_frequency
+    }
+
+    /// Clears `smoothed_frequency`, e.g when switching to another instrument string.
+    pub fn reset_smoothed_frequency(&mut self) {
+        self.smoothed_frequency = 0.0;
+    }
+
+    /// Returns the time in seconds of the last input sample in the most recently analyzed
+    /// window, i.e the sample index passed to the result handler divided by the sample rate.
+    /// Returns 0 if no window has been analyzed.
+    pub fn last_window_timestamp(&self) -> f64 {
+        (self.last_window_sample_index as f64) / (self.sample_rate as f64)
+    }
 }
 
 #[cfg(test)]
@@ -70,6 +637,7 @@ mod tests {
 
     use super::*;
     use crate::alloc::vec::Vec;
+    use crate::common::apply_window_function;
 
     fn generate_sine(sample_rate: f32, frequency: f32, sample_count: usize) -> Vec<f32> {
         let mut window: Vec<f32> = vec![0.0; sample_count];
@@ -82,11 +650,126 @@ mod tests {
     }
 
     #[test]
-    #[should_panic]
+    #[should_panic(expected = "Lag count 129 must be in the range [1, 128]")]
     fn test_invalid_lag_count() {
         MpmPitchDetector::from_options(44100.0, 128, 64, 129, 1);
     }
 
+    #[test]
+    fn test_with_overlap() {
+        let hop_size = |overlap_ratio: f32| {
+            MpmPitchDetector::with_overlap(44100.0, 1024, overlap_ratio)
+                .window_processor
+                .downsampled_hop_size()
+        };
+        assert_eq!(hop_size(0.0), 1024);
+        assert_eq!(hop_size(0.5), 512);
+        assert_eq!(hop_size(0.75), 256);
+        assert_eq!(hop_size(0.9999), 1);
+    }
+
+    #[test]
+    fn test_try_from_options() {
+        assert!(MpmPitchDetector::try_from_options(44100.0, 1024, 512, 512, 1).is_ok());
+        assert_eq!(
+            MpmPitchDetector::try_from_options(44100.0, 1024, 0, 512, 1).err(),
+            Some(Error::InvalidHopSize {
+                hop_size: 0,
+                window_size: 1024
+            })
+        );
+        assert_eq!(
+            MpmPitchDetector::try_from_options(44100.0, 1024, 512, 1025, 1).err(),
+            Some(Error::LagCountOutOfRange {
+                lag_count: 1025,
+                window_size: 1024
+            })
+        );
+        assert_eq!(
+            MpmPitchDetector::try_from_options(44100.0, 16384, 512, 8192, 1).err(),
+            Some(Error::UnsupportedFftSize(32768))
+        );
+    }
+
+    #[test]
+    fn test_try_new() {
+        assert!(MpmPitchDetector::try_new(44100.0, 1024, 512).is_ok());
+        assert_eq!(
+            MpmPitchDetector::try_new(44100.0, 0, 512).err(),
+            Some(Error::InvalidWindowSize(0))
+        );
+        assert_eq!(
+            MpmPitchDetector::try_new(44100.0, 1024, 2048).err(),
+            Some(Error::InvalidHopSize {
+                hop_size: 2048,
+                window_size: 1024
+            })
+        );
+    }
+
+    #[test]
+    #[should_panic(expected = "Lag count 0 must be in the range [1, 128]")]
+    fn test_zero_lag_count() {
+        MpmPitchDetector::from_options(44100.0, 128, 64, 0, 1);
+    }
+
+    #[test]
+    #[should_panic(expected = "Lag count 257 must be in the range [1, 256]")]
+    fn test_reconfigure_invalid_lag_count() {
+        let mut detector = MpmPitchDetector::new(44100.0, 128, 64);
+        detector.reconfigure(256, 64, 257, 1);
+    }
+
+    #[test]
+    fn test_try_reconfigure() {
+        let mut detector = MpmPitchDetector::new(44100.0, 128, 64);
+        assert_eq!(
+            detector.try_reconfigure(256, 512, 128, 1).err(),
+            Some(Error::InvalidHopSize {
+                hop_size: 512,
+                window_size: 256
+            })
+        );
+        assert_eq!(
+            detector.try_reconfigure(256, 64, 257, 1).err(),
+            Some(Error::LagCountOutOfRange {
+                lag_count: 257,
+                window_size: 256
+            })
+        );
+        assert_eq!(
+            detector.try_reconfigure(256, 64, 128, 0).err(),
+            Some(Error::InvalidDownsampling(0))
+        );
+        // Failed attempts leave the detector unchanged
+        assert_eq!(detector.downsampled_window_size(), 128);
+        assert_eq!(detector.downsampled_hop_size(), 64);
+        assert_eq!(detector.result().window_size(), 128);
+        assert_eq!(detector.result().nsdf.len(), 64);
+
+        assert_eq!(detector.try_reconfigure(256, 64, 128, 2), Ok(()));
+        assert_eq!(detector.downsampled_window_size(), 256);
+        assert_eq!(detector.downsampling(), 2);
+        assert_eq!(detector.result().nsdf.len(), 128);
+    }
+
+    #[test]
+    #[should_panic]
+    fn test_with_invalid_overlap() {
+        MpmPitchDetector::with_overlap(44100.0, 1024, 1.0);
+    }
+
+    #[test]
+    fn test_from_options_sizes() {
+        // All sizes differ, so swapped arguments would be caught
+        let detector = MpmPitchDetector::from_options(44100.0, 256, 64, 128, 3);
+        assert_eq!(detector.downsampled_window_size(), 256);
+        assert_eq!(detector.downsampled_hop_size(), 64);
+        assert_eq!(detector.downsampling(), 3);
+        assert_eq!(detector.result().nsdf.len(), 128);
+        assert_eq!(detector.samples_until_next_window(), 3 * 255 + 1);
+    }
+
     #[test]
     fn test_sine_detection() {
         let window_size = 1024;
@@ -97,11 +780,393 @@ mod tests {
 
         let mut detector = MpmPitchDetector::new(sample_rate, window_size, hop_size);
 
-        detector.process(&window[..], |result: &MpmPitchResult| {
+        detector.process(&window[..], |result: &MpmPitchResult, _, _| {
             assert!((frequency - result.frequency).abs() <= 0.001);
         });
     }
 
+    #[test]
+    fn test_process_i16() {
+        let sample_rate: f32 = 44100.0;
+        let frequency: f32 = 467.0;
+        let window = generate_sine(sample_rate, frequency, 3000);
+        let window_i16: Vec<i16> = window
+            .iter()
+            .map(|sample| (0.5 * 32768.0 * sample) as i16)
+            .collect();
+
+        let mut detector = MpmPitchDetector::new(sample_rate, 1024, 512);
+        let mut detector_i16 = MpmPitchDetector::new(sample_rate, 1024, 512);
+        let mut frequencies: Vec<(f32, usize)> = Vec::new();
+        let mut frequencies_i16: Vec<(f32, usize)> = Vec::new();
+        detector.process(&window[..], |result, sample_index, _| {
+            frequencies.push((result.frequency, sample_index));
+        });
+        detector_i16.process_i16(&window_i16[..], |result, sample_index, _| {
+            frequencies_i16.push((result.frequency, sample_index));
+        });
+
+        assert!(frequencies.len() > 0);
+        assert_eq!(frequencies.len(), frequencies_i16.len());
+        for ((f, i), (f_i16, i_i16)) in frequencies.iter().zip(frequencies_i16.iter()) {
+            assert_eq!(i, i_i16);
+            assert!((f - f_i16).abs() < 0.01);
+        }
+    }
+
+    #[test]
+    fn test_process_interleaved() {
+        let sample_rate: f32 = 44100.0;
+        let signal = generate_sine(sample_rate, 467.0, 3000);
+        // Channels with different gains that average to the mono signal
+        let stereo: Vec<f32> = signal
+            .iter()
+            .flat_map(|sample| [1.5 * sample, 0.5 * sample])
+            .collect();
+
+        let mut detector = MpmPitchDetector::new(sample_rate, 1024, 512);
+        let expected = detector.analyze(&signal);
+        let mut detector = MpmPitchDetector::new(sample_rate, 1024, 512);
+        let mut snapshots = Vec::new();
+        for chunk in stereo.chunks(2 * 300) {
+            detector.process_interleaved(chunk, 2, |result, sample_index, _| {
+                snapshots.push(PitchSnapshot::new(result, sample_index))
+            });
+        }
+        assert_eq!(snapshots.len(), expected.len());
+        for (snapshot, expected) in snapshots.iter().zip(expected.iter()) {
+            assert_eq!(snapshot.sample_index, expected.sample_index);
+            assert!((snapshot.frequency - expected.frequency).abs() < 0.01);
+        }
+    }
+
+    #[test]
+    fn test_iter_windows_and_analyze() {
+        let sample_rate: f32 = 44100.0;
+        let frequency: f32 = 467.0;
+        let signal = generate_sine(sample_rate, frequency, 3000);
+
+        let mut expected: Vec<PitchSnapshot> = Vec::new();
+        let mut detector = MpmPitchDetector::new(sample_rate, 1024, 512);
+        for chunk in signal.chunks(700) {
+            detector.process(chunk, |result, sample_index, _| {
+                expected.push(PitchSnapshot::new(result, sample_index));
+            });
+        }
+
+        let mut detector = MpmPitchDetector::new(sample_rate, 1024, 512);
+        assert_eq!(detector.analyze(&signal), expected);
+
+        let mut detector = MpmPitchDetector::new(sample_rate, 1024, 512);
+        let mut snapshots: Vec<PitchSnapshot> = detector.iter_windows(&signal[..1500]).collect();
+        snapshots.extend(detector.iter_windows(&signal[1500..]));
+        assert_eq!(snapshots.len(), 4);
+        assert_eq!(snapshots, expected);
+        assert!(snapshots.iter().all(|snapshot| snapshot.is_tone));
+        assert_eq!(snapshots[3].sample_index, 1023 + 3 * 512);
+
+        let mut detector = MpmPitchDetector::new(sample_rate, 1024, 512);
+        let pitches = detector.analyze_pitches(&signal);
+        assert_eq!(pitches.len(), 4);
+        for ((timestamp, frequency), snapshot) in pitches.iter().zip(expected.iter()) {
+            assert_eq!(
+                *timestamp,
+                (snapshot.sample_index as f64) / (sample_rate as f64)
+            );
+            assert_eq!(*frequency, snapshot.frequency);
+        }
+        assert_eq!(pitches[3].0, detector.last_window_timestamp());
+    }
+
+    #[test]
+    fn test_window_function() {
+        let sample_rate: f32 = 44100.0;
+        let frequency: f32 = 467.0;
+        let signal = generate_sine(sample_rate, frequency, 1024);
+        let mut detector = MpmPitchDetector::new(sample_rate, 1024, 1024);
+        assert_eq!(detector.window_function(), None);
+        let mut rectangular_clarity = 0.0;
+        detector.process(&signal, |result, _, window| {
+            // The handler gets the analyzed samples as is without a window function
+            assert_eq!(window, &signal[..]);
+            rectangular_clarity = result.clarity;
+        });
+
+        detector.set_window_function(Some(WindowFunctionType::Hann));
+        assert_eq!(detector.window_function(), Some(WindowFunctionType::Hann));
+        let mut expected_window = signal.clone();
+        apply_window_function(WindowFunctionType::Hann, &mut expected_window);
+        let mut window_count = 0;
+        detector.process(&signal, |result, _, window| {
+            assert_eq!(window, &expected_window[..]);
+            assert!(result.is_tone());
+            // Windowing biases the estimate slightly
+            assert!((frequency - result.frequency).abs() <= 0.01 * frequency);
+            assert!(result.clarity < rectangular_clarity);
+            window_count += 1;
+        });
+        assert_eq!(window_count, 1);
+
+        // The window function is preserved when reconfiguring
+        detector.reconfigure(512, 512, 256, 1);
+        assert_eq!(detector.window_function(), Some(WindowFunctionType::Hann));
+        detector.process(&signal[..512], |_, _, window| {
+            assert_eq!(window.len(), 512);
+            assert_eq!(window[0], 0.0);
+        });
+
+        detector.set_window_function(None);
+        detector.process(&signal[..512], |result, _, window| {
+            assert_eq!(window, &signal[..512]);
+            assert!((rectangular_clarity - result.clarity).abs() < 0.01);
+            // The detector's result has no window of its own
+            assert!(result.window.is_empty());
+        });
+    }
+
+    #[test]
+    fn test_detectable_frequency_range() {
+        let sample_rate: f32 = 44100.0;
+        let mut detector = MpmPitchDetector::from_options(sample_rate, 1024, 512, 441, 2);
+        assert_eq!(detector.detectable_frequency_range(), (50.0, 7350.0));
+        assert_eq!(detector.min_detectable_frequency(), 50.0);
+        assert_eq!(detector.max_detectable_frequency(), 7350.0);
+        assert_eq!(
+            detector.result().detectable_frequency_range(sample_rate),
+            (100.0, 14700.0)
+        );
+
+        detector.set_frequency_range(80.0, 1000.0);
+        assert_eq!(detector.detectable_frequency_range(), (80.0, 1000.0));
+        detector.set_frequency_range(20.0, 20000.0);
+        assert_eq!(detector.detectable_frequency_range(), (50.0, 7350.0));
+        detector.clear_frequency_range();
+
+        // Tones with pitch periods of a few samples, up to just below
+        // the highest detectable frequency, are detected
+        let detector_sample_rate = 8000.0;
+        let mut detector = MpmPitchDetector::new(detector_sample_rate, 256, 256);
+        let max_frequency = detector.max_detectable_frequency();
+        for frequency in [0.25 * detector_sample_rate, 0.97 * max_frequency] {
+            let signal = generate_sine(detector_sample_rate, frequency, 256);
+            let mut window_count = 0;
+            detector.process(&signal, |result, _, _| {
+                assert!(result.is_tone());
+                assert!((frequency - result.frequency).abs() < 0.02 * frequency);
+                window_count += 1;
+            });
+            assert_eq!(window_count, 1);
+        }
+    }
+
+    #[test]
+    fn test_reconfigure() {
+        let sample_rate: f32 = 44100.0;
+        let frequency: f32 = 467.0;
+        let signal = generate_sine(sample_rate, frequency, 3000);
+        let mut detector = MpmPitchDetector::new(sample_rate, 1024, 1024);
+        detector.set_peak_threshold_ratio(0.8);
+        detector.set_frequency_range(100.0, 1000.0);
+        detector.set_noise_gate(-60.0);
+        detector.set_key_max_capacity(16);
+        assert_eq!(detector.key_max_capacity(), 16);
+
+        // Leave a partially filled window
+        detector.process(&signal[..1500], |_, _, _| {});
+        assert_eq!(detector.processed_sample_count(), 1500);
+        assert_eq!(detector.processed_window_count(), 1);
+        detector.reconfigure(256, 128, 128, 2);
+        assert_eq!(detector.downsampled_window_size(), 256);
+        assert_eq!(detector.samples_until_next_window(), 2 * 255 + 1);
+        assert_eq!(detector.result().peak_threshold_ratio(), 0.8);
+        assert_eq!(detector.noise_gate(), Some(-60.0));
+        assert_eq!(detector.key_max_capacity(), 16);
+
+        let mut sample_indices: Vec<usize> = Vec::new();
+        detector.process(&signal[1500..], |result, sample_index, _| {
+            assert_eq!(result.window_size(), 256);
+            assert!((frequency - result.frequency).abs() <= 0.05);
+            sample_indices.push(sample_index);
+        });
+        // The first window only contains samples passed after reconfiguring
+        assert_eq!(sample_indices[0], 1500 + 2 * 255);
+        assert_eq!(sample_indices[1], 1500 + 2 * (255 + 128));
+        // Counts keep accumulating across reconfiguration
+        assert_eq!(detector.processed_sample_count(), 3000);
+        assert_eq!(detector.processed_window_count(), 1 + sample_indices.len());
+    }
+
+    #[test]
+    fn test_anti_aliasing_filter() {
+        let sample_rate: f32 = 44100.0;
+        let frequency: f32 = 467.0;
+        // Add a tone above the downsampled Nyquist frequency
+        let signal: Vec<f32> = generate_sine(sample_rate, frequency, 8192)
+            .iter()
+            .zip(generate_sine(sample_rate, 15000.0, 8192))
+            .map(|(a, b)| a + b)
+            .collect();
+        let mut detector = MpmPitchDetector::new(sample_rate, 1024, 512);
+        detector.set_anti_aliasing_filter(true);
+        // The setting is kept when reconfiguring
+        detector.reconfigure(512, 512, 256, 4);
+        assert!(detector.window_processor.anti_aliasing_filter());
+
+        let mut window_count = 0;
+        detector.process(&signal[..], |result, _, _| {
+            assert!((frequency - result.frequency).abs() <= 0.5);
+            assert_eq!(result.effective_sample_rate(), sample_rate / 4.0);
+            window_count += 1;
+        });
+        assert_eq!(window_count, 4);
+    }
+
+    #[test]
+    fn test_equal_loudness_filter() {
+        let sample_rate: f32 = 44100.0;
+        let frequency: f32 = 467.0;
+        let signal = generate_sine(sample_rate, frequency, 8192);
+        let mut detector = MpmPitchDetector::new(sample_rate, 1024, 1024);
+        assert!(!detector.equal_loudness_filter());
+        detector.set_equal_loudness_filter(true);
+        assert!(detector.equal_loudness_filter());
+
+        let mut frequencies: Vec<(f32, usize)> = Vec::new();
+        for chunk in signal.chunks(1000) {
+            detector.process(chunk, |result, sample_index, _| {
+                frequencies.push((result.frequency, sample_index));
+            });
+        }
+        assert_eq!(frequencies.len(), 8);
+        for (i, (f, sample_index)) in frequencies.iter().enumerate() {
+            assert_eq!(*sample_index, 1023 + i * 1024);
+            // Skip the filter transient
+            if i > 0 {
+                assert!((frequency - f).abs() <= 0.05);
+            }
+        }
+
+        detector.set_equal_loudness_filter(false);
+        assert!(!detector.equal_loudness_filter());
+    }
+
+    #[test]
+    #[should_panic]
+    fn test_equal_loudness_filter_unsupported_sample_rate() {
+        let mut detector = MpmPitchDetector::new(22050.0, 1024, 512);
+        detector.set_equal_loudness_filter(true);
+    }
+
+    #[test]
+    fn test_smoothed_frequency() {
+        let sample_rate: f32 = 44100.0;
+        let mut detector = MpmPitchDetector::new(sample_rate, 1024, 512);
+        assert_eq!(detector.smoothed_frequency(), 0.0);
+
+        // Without smoothing, the most recent tone is tracked
+        detector.process(&generate_sine(sample_rate, 440.0, 4096), |_, _, _| {});
+        assert!((detector.smoothed_frequency() - 440.0).abs() < 0.5);
+
+        // With smoothing, a step in frequency is approached gradually
+        detector.set_smoothing_time_constant(0.1);
+        assert_eq!(detector.smoothing_time_constant(), 0.1);
+        let mut smoothed = Vec::new();
+        detector.process(&generate_sine(sample_rate, 494.0, 8192), |_, _, _| {});
+        smoothed.push(detector.smoothed_frequency());
+        detector.process(&generate_sine(sample_rate, 494.0, 8192), |_, _, _| {});
+        smoothed.push(detector.smoothed_frequency());
+        assert!(smoothed[0] > 450.0 && smoothed[0] < 490.0, "{:?}", smoothed);
+        assert!(
+            smoothed[1] > smoothed[0] && smoothed[1] < 494.0,
+            "{:?}",
+            smoothed
+        );
+
+        // Silence does not pull the average, once the windows only contain silence
+        detector.process(&vec![0.0; 1024], |_, _, _| {});
+        let before_silence = detector.smoothed_frequency();
+        detector.process(&vec![0.0; 8192], |_, _, _| {});
+        assert_eq!(detector.smoothed_frequency(), before_silence);
+
+        detector.reset_smoothed_frequency();
+        assert_eq!(detector.smoothed_frequency(), 0.0);
+    }
+
+    #[test]
+    fn test_noise_gate() {
+        let sample_rate: f32 = 44100.0;
+        let frequency: f32 = 467.0;
+        let mut signal = generate_sine(sample_rate, frequency, 4096);
+        for sample in signal[2048..].iter_mut() {
+            *sample *= 0.001;
+        }
+        let mut detector = MpmPitchDetector::new(sample_rate, 1024, 1024);
+        detector.set_noise_gate(-40.0);
+
+        let mut valid: Vec<bool> = Vec::new();
+        detector.process(&signal[..], |result, _, _| {
+            valid.push(result.is_valid());
+        });
+        assert_eq!(valid, [true, true, false, false]);
+
+        detector.clear_noise_gate();
+        assert_eq!(detector.noise_gate(), None);
+        detector.process(&signal[2048..], |result, _, _| {
+            assert!(result.is_valid());
+        });
+    }
+
+    #[test]
+    fn test_frequency_range() {
+        let sample_rate: f32 = 44100.0;
+        let frequency: f32 = 220.0;
+        let window = generate_sine(sample_rate, frequency, 2048);
+        let mut detector = MpmPitchDetector::new(sample_rate, 2048, 2048);
+
+        detector.set_frequency_range(80.0, 1000.0);
+        detector.process(&window[..], |result, _, _| {
+            assert!((frequency - result.frequency).abs() <= 0.01);
+        });
+
+        detector.set_frequency_range(300.0, 1000.0);
+        detector.process(&window[..], |result, _, _| {
+            assert!(!result.is_valid());
+        });
+
+        // Doubling the sample rate doubles the detected frequency, moving it into range.
+        detector.set_sample_rate(2.0 * sample_rate);
+        detector.process(&window[..], |result, _, _| {
+            assert!(result.is_valid());
+            assert!((2.0 * frequency - result.frequency).abs() <= 0.01);
+        });
+    }
+
+    #[test]
+    fn test_sample_index() {
+        let window_size = 64;
+        let hop_size = 16;
+        let downsampling = 3;
+        let chunk_size = 50;
+        let sample_count = 2000;
+        let signal = generate_sine(44100.0, 440.0, sample_count);
+        let mut detector =
+            MpmPitchDetector::from_options(44100.0, window_size, hop_size, 32, downsampling);
+
+        let mut sample_indices: Vec<usize> = Vec::new();
+        for chunk in signal.chunks(chunk_size) {
+            detector.process(chunk, |_, sample_index, _| {
+                sample_indices.push(sample_index);
+            });
+        }
+
+        assert!(sample_indices.len() > 1);
+        for (i, sample_index) in sample_indices.iter().enumerate() {
+            let expected = downsampling * (window_size - 1 + i * hop_size);
+            assert_eq!(*sample_index, expected);
+            assert!(*sample_index < sample_count);
+        }
+    }
+
     #[test]
     fn test_downsampled_sine_detection() {
         let window_size = 2048;
@@ -109,8 +1174,12 @@ mod tests {
         let hop_size = window_size;
         let frequency: f32 = 467.0;
         let sample_rate: f32 = 44100.0;
-        let window = generate_sine(sample_rate, frequency, window_size);
         let downsampling_factor = 4;
+        let signal = generate_sine(
+            sample_rate,
+            frequency,
+            2 * downsampling_factor * window_size,
+        );
         let mut detector = MpmPitchDetector::from_options(
             sample_rate,
             window_size,
@@ -120,13 +1189,24 @@ mod tests {
         );
         let downsampled_window_size = detector.downsampled_window_size();
 
-        detector.process(&window[..], |result: &MpmPitchResult| {
-            assert!(result.window.len() == downsampled_window_size);
-            assert!((frequency - result.frequency).abs() <= 0.05);
-        });
-        detector.process(&window[..], |result: &MpmPitchResult| {
-            assert!(result.window.len() == downsampled_window_size);
-            assert!((frequency - result.frequency).abs() <= 0.05);
-        });
+        let mut window_count = 0;
+        detector.process(
+            &signal[..],
+            |result: &MpmPitchResult, sample_index, window| {
+                // The analyzed window holds every downsampling:th input sample
+                // and is sampled at the effective sample rate
+                assert_eq!(window.len(), downsampled_window_size);
+                for (i, value) in window.iter().rev().enumerate() {
+                    assert_eq!(*value, signal[sample_index - downsampling_factor * i]);
+                }
+                assert_eq!(
+                    result.effective_sample_rate(),
+                    sample_rate / (downsampling_factor as f32)
+                );
+                assert!((frequency - result.frequency).abs() <= 0.05);
+                window_count += 1;
+            },
+        );
+        assert_eq!(window_count, 2);
     }
 }
@@ -0,0 +1,125 @@
+use alloc::{boxed::Box, vec};
+
+use crate::mpm::result::MpmPitchResult;
+
+/// A [median filter](https://en.wikipedia.org/wiki/Median_filter) for pitch readings.
+/// Reduces frame to frame jitter and rejects occasional octave jumps by computing
+/// the median frequency of the most recent tonal windows. All memory is allocated
+/// on creation.
+pub struct PitchSmoother {
+    /// Ring buffer of the most recent tonal frequencies.
+    frequencies: Box<[f32]>,
+    /// Storage for sorting frequencies when computing the median.
+    sorted_frequencies: Box<[f32]>,
+    write_index: usize,
+    frequency_count: usize,
+    median: f32,
+}
+
+impl PitchSmoother {
+    /// Creates a new `PitchSmoother` instance.
+    /// # Arguments
+    ///
+    /// * `window_len` - The number of tonal windows to compute the median over. Must be greater than 0.
+    pub fn new(window_len: usize) -> Self {
+        if window_len == 0 {
+            panic!("Pitch smoother window length must be greater than 0")
+        }
+        PitchSmoother {
+            frequencies: vec![0.0; window_len].into_boxed_slice(),
+            sorted_frequencies: vec![0.0; window_len].into_boxed_slice(),
+            write_index: 0,
+            frequency_count: 0,
+            median: 0.0,
+        }
+    }
+
+    /// Adds a pitch detection result and returns the median frequency
+    /// of the most recent tonal windows. Results that are not tones,
+    /// as determined by `is_tone`, are ignored. Returns 0 if no tonal
+    /// windows have been pushed.
+    pub fn push(&mut self, result: &MpmPitchResult) -> f32 {
+        if result.is_tone() {
+            self.push_frequency(result.frequency);
+        }
+        self.median
+    }
+
+    /// Returns the current median frequency.
+    pub fn frequency(&self) -> f32 {
+        self.median
+    }
+
+    /// Clears all previously pushed frequencies.
+    pub fn reset(&mut self) {
+        self.write_index = 0;
+        self.frequency_count = 0;
+        self.median = 0.0;
+    }
+
+    fn push_frequency(&mut self, frequency: f32) {
+        let window_len = self.frequencies.len();
+        self.frequencies[self.write_index] = frequency;
+        self.write_index = (self.write_index + 1) % window_len;
+        self.frequency_count = window_len.min(self.frequency_count + 1);
+
+        let count = self.frequency_count;
+        let sorted = &mut self.sorted_frequencies[..count];
+        sorted.copy_from_slice(&self.frequencies[..count]);
+        sorted.sort_unstable_by(|a, b| a.total_cmp(b));
+        let mid = count / 2;
+        self.median = if count % 2 == 1 {
+            sorted[mid]
+        } else {
+            0.5 * (sorted[mid - 1] + sorted[mid])
+        };
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    #[should_panic]
+    fn test_zero_window_len() {
+        PitchSmoother::new(0);
+    }
+
+    #[test]
+    fn test_median() {
+        let mut smoother = PitchSmoother::new(3);
+        assert_eq!(smoother.frequency(), 0.0);
+        smoother.push_frequency(440.0);
+        assert_eq!(smoother.frequency(), 440.0);
+        smoother.push_frequency(450.0);
+        assert_eq!(smoother.frequency(), 445.0);
+        // An octave jump is rejected
+        smoother.push_frequency(880.0);
+        assert_eq!(smoother.frequency(), 450.0);
+        // The oldest value is replaced
+        smoother.push_frequency(460.0);
+        assert_eq!(smoother.frequency(), 460.0);
+        smoother.reset();
+        assert_eq!(smoother.frequency(), 0.0);
+    }
+
+    #[test]
+    fn test_ignores_non_tones() {
+        let mut smoother = PitchSmoother::new(5);
+        let mut result = MpmPitchResult::new(1024, 512);
+        // Silence is not a tone
+        result.compute(44100.0);
+        result.frequency = 123.0;
+        assert_eq!(smoother.push(&result), 0.0);
+
+        // A pure tone is not ignored
+        let sample_rate = 44100.0;
+        for i in 0..result.window.len() {
+            let phase = 2.0 * core::f32::consts::PI * 441.0 * (i as f32) / sample_rate;
+            result.window[i] = phase.sin();
+        }
+        result.compute(sample_rate);
+        assert!((smoother.push(&result) - 441.0).abs() < 0.01);
+    }
+}
@@ -0,0 +1,36 @@
+use crate::mpm::MpmPitchResult;
+
+/// A small, copyable summary of a pitch detection result, e.g for collecting
+/// results from a number of windows or passing them from a real time audio thread
+/// to another thread. See [`MpmPitchResult::snapshot`] and
+/// [`MpmPitchDetector::iter_windows`](crate::mpm::MpmPitchDetector::iter_windows).
+#[derive(Clone, Copy, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct PitchSnapshot {
+    /// The estimated pitch frequency in Hz. See [`MpmPitchResult::frequency`].
+    pub frequency: f32,
+    /// The clarity of the pitch estimate. See [`MpmPitchResult::clarity`].
+    pub clarity: f32,
+    /// The MIDI note number corresponding to the pitch frequency. See [`MpmPitchResult::midi_note_number`].
+    pub midi_note_number: f32,
+    /// The estimated pitch period in samples. See [`MpmPitchResult::pitch_period`].
+    pub pitch_period: f32,
+    /// The result of [`MpmPitchResult::is_tone`].
+    pub is_tone: bool,
+    /// The peak level of the analyzed window.
+    pub window_peak: f32,
+    /// The RMS level of the analyzed window.
+    pub window_rms: f32,
+    /// The index of the last input sample in the analyzed window. 0 for snapshots
+    /// created using [`MpmPitchResult::snapshot`], which has no notion of sample indices.
+    pub sample_index: usize,
+}
+
+impl PitchSnapshot {
+    pub(crate) fn new(result: &MpmPitchResult, sample_index: usize) -> Self {
+        PitchSnapshot {
+            sample_index,
+            ..result.snapshot()
+        }
+    }
+}
@@ -2,14 +2,33 @@ use micromath::F32Ext;
 
 use crate::alloc::boxed::Box;
 use crate::alloc::vec;
-use crate::common::freq_to_midi_note;
-use crate::common::{autocorr_fft, autocorr_fft_size};
+use crate::common::{autocorr_fft_size, autocorr_fft_with, guarded_ratio, RealFft};
+use crate::common::{freq_to_midi_note, freq_to_midi_note_with_reference, to_db, F32ArrayExt};
 use crate::mpm::key_max::KeyMax;
 use crate::mpm::util;
+use crate::mpm::{PitchSnapshot, Voicing};
 
-/// The maximum number of key maxima to gather during the peak finding phase.
+/// The default maximum number of key maxima to gather during the peak finding phase.
+/// See `MpmPitchResult::with_key_max_capacity`.
 pub const MAX_KEY_MAXIMA_COUNT: usize = 64;
-/// A pitch detection result.
+/// The maximum relative difference between the lag of a key maximum and half the
+/// selected lag for the key maximum to be considered by the octave correction.
+const OCTAVE_CORRECTION_LAG_TOLERANCE: f32 = 0.05;
+/// The maximum amount the value of a key maximum may be below the value of the selected
+/// key maximum for the key maximum to be considered by the octave correction.
+const OCTAVE_CORRECTION_CLARITY_TOLERANCE: f32 = 0.2;
+/// The number of bins on each side of a bin used to smooth the power spectrum
+/// when spectral whitening is enabled.
+const SPECTRAL_WHITENING_HALF_WIDTH: usize = 4;
+/// The smallest NSDF lag index of a key maximum that can be interpolated. The NSDF
+/// is 1 at lag 0 and must cross zero from below, i.e be non-positive at lag 1 at the
+/// earliest, before a key maximum can be found. The parabolic interpolation of the key
+/// maximum needs a neighbour on each side of it on the positive lobe after this zero
+/// crossing, which puts the key maximum at lag 3 at the earliest.
+const MIN_KEY_MAX_LAG_INDEX: usize = 3;
+/// A pitch detection result. Serializable if the `serde` feature is enabled,
+/// in which case the public fields and the valid key maxima, i.e `key_maxima()`,
+/// are serialized.
 pub struct MpmPitchResult {
     /// The estimated pitch frequency in Hz.
     pub frequency: f32,
@@ -23,32 +42,185 @@ pub struct MpmPitchResult {
     pub midi_note_number: f32,
     /// The estimated pitch period in samples.
     pub pitch_period: f32,
-    /// The analyzed window.
+    /// The peak level of the analyzed window.
+    pub window_peak: f32,
+    /// The [root mean square](https://en.wikipedia.org/wiki/Root_mean_square) level of the analyzed window.
+    pub window_rms: f32,
+    /// The [zero-crossing rate](https://en.wikipedia.org/wiki/Zero-crossing_rate) of the
+    /// analyzed window, i.e the fraction of adjacent sample pairs that change sign.
+    pub zero_crossing_rate: f32,
+    /// The window analyzed by `compute`, sampled at [`effective_sample_rate`](Self::effective_sample_rate).
+    /// Not modified by `compute_for`, which is what [`MpmPitchDetector`](crate::mpm::MpmPitchDetector)
+    /// uses to avoid copying each window. Empty for results owned by the detector, which instead
+    /// passes the analyzed, i.e downsampled, window to its result handler.
     pub window: Box<[f32]>,
-    /// The normalized square difference function
+    /// The normalized square difference function. Lags are given in samples at
+    /// `effective_sample_rate`.
     pub nsdf: Box<[f32]>,
     /// The number of key maxima found during the peak picking phase. May be 0, in which case
     /// the result is considered invalid.
     pub key_max_count: usize,
-    /// A fixed array of key maxima. The first `key_max_count` maxima are valid.
-    pub key_maxima: Box<[KeyMax]>,
-    /// The index into `key_maxima` of the selected key maximum
+    /// A fixed array of key maxima, whose length is the key max capacity.
+    /// The first `key_max_count` maxima are valid.
+    key_maxima: Box<[KeyMax]>,
+    /// Indicates if key maxima were dropped because the key max capacity was reached.
+    key_maxima_overflowed: bool,
+    /// The index into `key_maxima()` of the selected key maximum
     pub selected_key_max_index: usize,
+    /// The fraction of the largest key maximum a key maximum must reach to be selected.
+    peak_threshold_ratio: f32,
+    /// The smallest lag index at which key maxima are gathered.
+    min_lag_index: usize,
+    /// The largest lag index at which key maxima are gathered.
+    max_lag_index: usize,
+    /// Indicates if octave errors should be corrected after peak picking.
+    octave_correction: bool,
+    /// The frequency of A4 used when computing MIDI note numbers. 440 Hz if `None`.
+    reference_frequency: Option<f32>,
+    /// The sample rate passed to the most recent call to `compute` or `compute_for`.
+    sample_rate: f32,
+    /// The window RMS level in dB below which the NSDF is not computed. Disabled if `None`.
+    noise_gate: Option<f32>,
+    /// Indicates if the power spectrum should be whitened before computing the autocorrelation.
+    spectral_whitening: bool,
     ///
     r_prime: Box<[f32]>,
     scratch_buffer: Box<[f32]>,
+    /// The transform used to compute the autocorrelation.
+    fft: RealFft,
+    /// The length of the windows analyzed by `compute_for`. Equal to the length of
+    /// `window`, unless `window` is empty.
+    window_size: usize,
+}
+
+#[cfg(feature = "serde")]
+impl serde::Serialize for MpmPitchResult {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        use serde::ser::SerializeStruct;
+        let mut state = serializer.serialize_struct("MpmPitchResult", 12)?;
+        state.serialize_field("frequency", &self.frequency)?;
+        state.serialize_field("clarity", &self.clarity)?;
+        state.serialize_field("midi_note_number", &self.midi_note_number)?;
+        state.serialize_field("pitch_period", &self.pitch_period)?;
+        state.serialize_field("window_peak", &self.window_peak)?;
+        state.serialize_field("window_rms", &self.window_rms)?;
+        state.serialize_field("zero_crossing_rate", &self.zero_crossing_rate)?;
+        state.serialize_field("window", &self.window)?;
+        state.serialize_field("nsdf", &self.nsdf)?;
+        state.serialize_field("key_max_count", &self.key_max_count)?;
+        // Entries past key_max_count are left over from previous windows
+        state.serialize_field("key_maxima", self.key_maxima())?;
+        state.serialize_field("selected_key_max_index", &self.selected_key_max_index)?;
+        state.end()
+    }
 }
 
 impl MpmPitchResult {
     pub fn new(window_size: usize, lag_count: usize) -> Self {
-        // Allocate buffers
-        let window = (vec![0.0; window_size]).into_boxed_slice();
-        let nsdf = (vec![0.0; lag_count]).into_boxed_slice();
-        let r_prime = (vec![0.0; autocorr_fft_size(window_size, lag_count)]).into_boxed_slice();
-        let scratch_buffer =
-            (vec![0.0; autocorr_fft_size(window_size, lag_count)]).into_boxed_slice();
-
-        // Create the instance
+        MpmPitchResult::with_key_max_capacity(window_size, lag_count, MAX_KEY_MAXIMA_COUNT)
+    }
+
+    /// Creates a new `MpmPitchResult` gathering at most `key_max_capacity` key maxima
+    /// per window, instead of the default [`MAX_KEY_MAXIMA_COUNT`]. Very low fundamentals
+    /// may produce more NSDF maxima than the default allows for, in which case the
+    /// remaining maxima are dropped. High fundamentals need fewer, saving memory.
+    pub fn with_key_max_capacity(
+        window_size: usize,
+        lag_count: usize,
+        key_max_capacity: usize,
+    ) -> Self {
+        MpmPitchResult::allocate(
+            vec![0.0; window_size].into_boxed_slice(),
+            window_size,
+            lag_count,
+            key_max_capacity,
+        )
+    }
+
+    /// Creates a result without a `window`, for use with `compute_for` only. Used by
+    /// [`MpmPitchDetector`](crate::mpm::MpmPitchDetector), which analyzes its windows in place.
+    pub(crate) fn without_window(
+        window_size: usize,
+        lag_count: usize,
+        key_max_capacity: usize,
+    ) -> Self {
+        MpmPitchResult::allocate(Box::new([]), window_size, lag_count, key_max_capacity)
+    }
+
+    fn allocate(
+        window: Box<[f32]>,
+        window_size: usize,
+        lag_count: usize,
+        key_max_capacity: usize,
+    ) -> Self {
+        if key_max_capacity == 0 {
+            panic!("Key max capacity must be greater than 0")
+        }
+        util::validate_window_size_lag_count(window_size, lag_count);
+        let fft_size = autocorr_fft_size(window_size, lag_count);
+        MpmPitchResult::from_parts(
+            window,
+            window_size,
+            vec![0.0; lag_count].into_boxed_slice(),
+            vec![0.0; fft_size].into_boxed_slice(),
+            vec![0.0; fft_size].into_boxed_slice(),
+            vec![KeyMax::new(); key_max_capacity].into_boxed_slice(),
+        )
+    }
+
+    /// Creates a new `MpmPitchResult` using caller provided working memory instead of
+    /// allocating it, for example to allocate all buffers once at startup from a dedicated
+    /// allocator. The window size and lag count are given by the lengths of `window`
+    /// and `nsdf`. `r_prime` and `scratch_buffer` must both have a length of
+    /// `autocorr_fft_size(window.len(), nsdf.len())`. The length of `key_maxima`, which
+    /// must be greater than 0, is the key max capacity (see [`MpmPitchResult::with_key_max_capacity`]).
+    /// The buffer contents do not matter, except that `window` is analyzed by `compute`.
+    pub fn from_buffers(
+        window: Box<[f32]>,
+        nsdf: Box<[f32]>,
+        r_prime: Box<[f32]>,
+        scratch_buffer: Box<[f32]>,
+        key_maxima: Box<[KeyMax]>,
+    ) -> Self {
+        if key_maxima.is_empty() {
+            panic!("Key max capacity must be greater than 0")
+        }
+        util::validate_window_size_lag_count(window.len(), nsdf.len());
+        let fft_size = autocorr_fft_size(window.len(), nsdf.len());
+        if r_prime.len() != fft_size {
+            panic!(
+                "Got r_prime buffer of length {}, expected {}.",
+                r_prime.len(),
+                fft_size
+            )
+        }
+        if scratch_buffer.len() != fft_size {
+            panic!(
+                "Got scratch buffer of length {}, expected {}.",
+                scratch_buffer.len(),
+                fft_size
+            )
+        }
+        let window_size = window.len();
+        MpmPitchResult::from_parts(
+            window,
+            window_size,
+            nsdf,
+            r_prime,
+            scratch_buffer,
+            key_maxima,
+        )
+    }
+
+    fn from_parts(
+        window: Box<[f32]>,
+        window_size: usize,
+        nsdf: Box<[f32]>,
+        r_prime: Box<[f32]>,
+        scratch_buffer: Box<[f32]>,
+        key_maxima: Box<[KeyMax]>,
+    ) -> Self {
+        let fft = RealFft::new(r_prime.len());
         MpmPitchResult {
             frequency: 0.0,
             clarity: 0.0,
@@ -57,35 +229,333 @@ impl MpmPitchResult {
             nsdf,
             r_prime,
             scratch_buffer,
+            fft,
             key_max_count: 0,
-            key_maxima: vec![KeyMax::new(); MAX_KEY_MAXIMA_COUNT].into_boxed_slice(),
+            key_maxima,
+            key_maxima_overflowed: false,
             selected_key_max_index: 0,
             pitch_period: 0.0,
+            window_peak: 0.0,
+            window_rms: 0.0,
+            zero_crossing_rate: 0.0,
+            peak_threshold_ratio: 0.9,
+            min_lag_index: 0,
+            max_lag_index: usize::MAX,
+            octave_correction: false,
+            reference_frequency: None,
+            noise_gate: None,
+            spectral_whitening: false,
+            sample_rate: 0.0,
+            window_size,
         }
     }
 
-    /// Performs pitch detection on the current contents of `window`.
+    /// Returns the length of the windows analyzed by this result, i.e the window size
+    /// it was created with.
+    pub fn window_size(&self) -> usize {
+        self.window_size
+    }
+
+    /// Returns the key maximum selection threshold, relative to the largest key maximum.
+    pub fn peak_threshold_ratio(&self) -> f32 {
+        self.peak_threshold_ratio
+    }
+
+    /// Sets the key maximum selection threshold, relative to the largest key maximum.
+    /// The first key maximum with a value of at least this fraction of the largest
+    /// key maximum is selected. Larger values bias the selection towards the strongest
+    /// maximum, smaller values towards earlier maxima, i.e higher frequencies.
+    /// Defaults to 0.9.
+    pub fn set_peak_threshold_ratio(&mut self, peak_threshold_ratio: f32) {
+        self.peak_threshold_ratio = peak_threshold_ratio;
+    }
+
+    /// Restricts the peak picking to key maxima with a lag index in the range
+    /// `[min_lag_index, max_lag_index]`. If no key maxima are found in this range,
+    /// the result is invalid.
+    pub fn set_lag_index_range(&mut self, min_lag_index: usize, max_lag_index: usize) {
+        self.min_lag_index = min_lag_index;
+        self.max_lag_index = max_lag_index;
+    }
+
+    /// Removes any lag index restriction set using `set_lag_index_range`.
+    pub fn clear_lag_index_range(&mut self) {
+        self.set_lag_index_range(0, usize::MAX);
+    }
+
+    /// Enables or disables octave error correction, which is disabled by default.
+    /// When enabled, a key maximum with a lag within 5% of half the lag of the selected
+    /// key maximum is selected instead, if its value is at most 0.2 below the value of
+    /// the selected key maximum. This corrects cases where the pitch is detected an octave
+    /// too low because of a strong NSDF maximum at twice the pitch period.
+    pub fn set_octave_correction(&mut self, enabled: bool) {
+        self.octave_correction = enabled;
+    }
+
+    /// Indicates if octave error correction is enabled. See `set_octave_correction`.
+    pub fn octave_correction(&self) -> bool {
+        self.octave_correction
+    }
+
+    /// Sets the frequency in Hz of A4 (MIDI note 69) used when computing `midi_note_number`,
+    /// for example 432 or 415. If `None`, which is the default, 440 Hz is used.
+    pub fn set_reference_frequency(&mut self, a4_freq: Option<f32>) {
+        self.reference_frequency = a4_freq;
+    }
+
+    /// Returns the reference frequency set using `set_reference_frequency`.
+    pub fn reference_frequency(&self) -> Option<f32> {
+        self.reference_frequency
+    }
+
+    /// Sets the window RMS level in dB below which pitch detection is skipped.
+    /// Windows below this level give invalid results with an all zero `nsdf`, but
+    /// `window_peak` and `window_rms` are still computed. Saves the cost of computing
+    /// the NSDF for silent input. If `None`, which is the default, all windows are analyzed.
+    pub fn set_noise_gate(&mut self, threshold_db: Option<f32>) {
+        self.noise_gate = threshold_db;
+    }
+
+    /// Returns the noise gate threshold set using `set_noise_gate`.
+    pub fn noise_gate(&self) -> Option<f32> {
+        self.noise_gate
+    }
+
+    /// Enables or disables spectral whitening, which divides each bin of the power
+    /// spectrum by the mean power of the neighbouring bins before the autocorrelation
+    /// is computed from it. This flattens the spectral envelope, which helps to
+    /// prevent strong formants, for example in speech, from being mistaken for the
+    /// fundamental. Since whitening changes the autocorrelation, clarity values are only
+    /// approximately comparable to those computed without whitening. Costs a few additional
+    /// passes over the spectrum and a division per bin, which is small compared to the
+    /// FFTs. Disabled by default.
+    pub fn set_spectral_whitening(&mut self, enabled: bool) {
+        self.spectral_whitening = enabled;
+    }
+
+    /// Indicates if spectral whitening is enabled. See `set_spectral_whitening`.
+    pub fn spectral_whitening(&self) -> bool {
+        self.spectral_whitening
+    }
+
+    /// Performs pitch detection on the current contents of `window`. Panics if the
+    /// result has no `window`, as is the case for results owned by an
+    /// [`MpmPitchDetector`](crate::mpm::MpmPitchDetector). Use `compute_for` instead.
     pub fn compute(&mut self, sample_rate: f32) {
+        self.assert_has_window();
+        // Temporarily move the window out of self to be able to borrow it
+        // while computing. Replacing it with an empty boxed slice does not allocate.
+        let window = core::mem::take(&mut self.window);
+        self.analyze(&window, sample_rate);
+        self.window = window;
+    }
+
+    /// Performs pitch detection on a given window, whose length must be `window_size()`.
+    /// Avoids copying the window into `window` before calling `compute`.
+    pub fn compute_for(&mut self, window: &[f32], sample_rate: f32) {
+        if window.len() != self.window_size {
+            panic!(
+                "Got window of length {}, expected {}.",
+                window.len(),
+                self.window_size
+            )
+        }
+        self.analyze(window, sample_rate);
+    }
+
+    /// Computes only the autocorrelation of the current contents of `window`, available
+    /// through `autocorrelation`, for example for custom periodicity analysis. Skips the
+    /// NSDF, peak picking and pitch estimation, so the result is invalid and `nsdf` is
+    /// not updated. `window_peak`, `window_rms` and `zero_crossing_rate` are updated and the
+    /// noise gate is ignored.
+    /// Panics if the result has no `window`, see `compute`.
+    pub fn compute_autocorrelation_only(&mut self, sample_rate: f32) {
+        self.assert_has_window();
+        let window = core::mem::take(&mut self.window);
         self.reset();
-        self.compute_nsdf();
+        self.sample_rate = sample_rate;
+        self.window_peak = window.peak_level();
+        self.window_rms = window.rms_level();
+        self.zero_crossing_rate = window.zero_crossing_rate();
+        self.compute_r_prime(&window);
+        self.window = window;
+    }
+
+    /// Returns the autocorrelation, i.e r' in the MPM paper, of the most recently analyzed
+    /// window for lags 0 up to the lag count. Not normalized, so the lag 0 value is the window
+    /// energy. Computed by `compute`, `compute_for` and `compute_autocorrelation_only`.
+    /// All zeros for windows skipped by the noise gate. With spectral whitening
+    /// enabled, this is the whitened autocorrelation.
+    pub fn autocorrelation(&self) -> &[f32] {
+        &self.r_prime[..self.nsdf.len()]
+    }
+
+    /// Returns the sample rate in Hz of the most recently analyzed window, or 0 if no
+    /// window has been analyzed. For results computed by an [`MpmPitchDetector`](crate::mpm::MpmPitchDetector),
+    /// this is the detector's sample rate divided by its downsampling factor, i.e the sample
+    /// rate of the window passed to the detector's result handler. Useful for converting
+    /// sample positions in the analyzed window and lags in `nsdf` to seconds.
+    pub fn effective_sample_rate(&self) -> f32 {
+        self.sample_rate
+    }
+
+    fn analyze(&mut self, window: &[f32], sample_rate: f32) {
+        self.reset();
+        self.sample_rate = sample_rate;
+        self.window_peak = window.peak_level();
+        self.window_rms = window.rms_level();
+        self.zero_crossing_rate = window.zero_crossing_rate();
+        if let Some(threshold_db) = self.noise_gate {
+            if self.window_rms_db() < threshold_db {
+                for value in self.nsdf.iter_mut() {
+                    *value = 0.0;
+                }
+                for value in self.r_prime.iter_mut() {
+                    *value = 0.0;
+                }
+                return;
+            }
+        }
+        self.compute_nsdf(window);
         self.perform_peak_picking();
+        if self.octave_correction {
+            self.correct_octave_error();
+        }
         self.compute_pitch(sample_rate);
     }
 
+    /// Returns the maximum number of key maxima gathered per window.
+    pub fn key_max_capacity(&self) -> usize {
+        self.key_maxima.len()
+    }
+
+    /// Returns true if more key maxima than the key max capacity were found in the
+    /// most recently analyzed window, in which case the maxima at the largest lags were
+    /// dropped and the selected key maximum may be wrong. If this happens regularly,
+    /// consider increasing the capacity using `with_key_max_capacity`.
+    pub fn key_maxima_overflowed(&self) -> bool {
+        self.key_maxima_overflowed
+    }
+
+    /// Returns the key maxima found during the peak picking phase, in order of increasing lag.
+    pub fn key_maxima(&self) -> &[KeyMax] {
+        &self.key_maxima[..self.key_max_count]
+    }
+
     /// Indicates if the detection result has a valid pitch estimate. Note that this does not necessarily
     /// mean that the result corresponds to a tone. See `is_tone` and `is_tone_with_options`.
     pub fn is_valid(&self) -> bool {
         self.key_max_count > 0
     }
 
+    /// Returns the peak level of the analyzed window in dB, clamped to [`DB_FLOOR`](crate::common::DB_FLOOR).
+    pub fn window_peak_db(&self) -> f32 {
+        to_db(self.window_peak)
+    }
+
+    /// Returns the RMS level of the analyzed window in dB, clamped to [`DB_FLOOR`](crate::common::DB_FLOOR).
+    pub fn window_rms_db(&self) -> f32 {
+        to_db(self.window_rms)
+    }
+
     /// Returns the lowest detectable frequency in Hz at a give sample rate.
     pub fn min_detectable_frequency(&self, sample_rate: f32) -> f32 {
         sample_rate / (self.nsdf.len() as f32)
     }
 
+    /// Returns the highest detectable frequency in Hz at a given sample rate, corresponding
+    /// to the smallest lag at which a key maximum can be interpolated, i.e a third of the
+    /// sample rate. Note that pitch periods of only a few samples give coarse estimates and
+    /// may be mistaken for a multiple of the period, which gives a lower frequency.
+    pub fn max_detectable_frequency(&self, sample_rate: f32) -> f32 {
+        sample_rate / (MIN_KEY_MAX_LAG_INDEX as f32)
+    }
+
+    /// Returns the lowest and highest detectable frequencies in Hz at a given sample rate.
+    /// See `min_detectable_frequency` and `max_detectable_frequency`.
+    pub fn detectable_frequency_range(&self, sample_rate: f32) -> (f32, f32) {
+        (
+            self.min_detectable_frequency(sample_rate),
+            self.max_detectable_frequency(sample_rate),
+        )
+    }
+
     /// Returns the number of the lowest detectable MIDI note at a give sample rate.
     pub fn min_detectable_note_number(&self, sample_rate: f32) -> f32 {
-        freq_to_midi_note(self.min_detectable_frequency(sample_rate))
+        self.freq_to_midi_note(self.min_detectable_frequency(sample_rate))
+    }
+
+    /// Returns the number of the MIDI note closest to `midi_note_number`,
+    /// or 0 if the result is not valid.
+    pub fn nearest_note_number(&self) -> i32 {
+        if !self.is_valid() {
+            return 0;
+        }
+        F32Ext::round(self.midi_note_number) as i32
+    }
+
+    /// Returns the signed deviation in cents, between -50 and 50, of `midi_note_number`
+    /// from the nearest MIDI note, or 0 if the result is not valid. Positive values
+    /// mean that the pitch is sharp.
+    pub fn cents_offset(&self) -> f32 {
+        if !self.is_valid() {
+            return 0.0;
+        }
+        100.0 * (self.midi_note_number - (self.nearest_note_number() as f32))
+    }
+
+    /// Returns `clarity` clamped to [0, 1], or 0 if the result is not valid.
+    ///
+    /// The NSDF is normalized by the energy of the window, so clarity values are
+    /// comparable across windows of different sizes and levels, even though the
+    /// underlying autocorrelation scales with the window length. `clarity` is already
+    /// clamped to at most 1, but parabolic interpolation may in rare cases push the
+    /// interpolated value slightly outside [0, 1]. Use this method when comparing
+    /// clarity against thresholds shared between detector configurations.
+    pub fn normalized_clarity(&self) -> f32 {
+        if !self.is_valid() {
+            return 0.0;
+        }
+        self.clarity.clamp(0.0, 1.0)
+    }
+
+    /// Returns a rough estimate of the uncertainty in Hz of `frequency`, or 0 if the
+    /// result is not valid. Useful for widening an "in tune" band when the estimate is shaky.
+    ///
+    /// The heuristic models the NSDF around the selected key maximum as the interpolating
+    /// parabola `value + curvature * (lag - pitch_period)^2` and takes `1 - clarity` as the
+    /// noise floor, i.e the part of the window not explained by a periodic signal.
+    /// The lag uncertainty is the distance from the peak at which the parabola has dropped
+    /// by the noise floor, `sqrt((1 - clarity) / |curvature|)`, which is converted to a
+    /// frequency uncertainty using `frequency * lag_uncertainty / pitch_period`.
+    /// Broad or weak NSDF peaks thus give larger uncertainties than sharp, strong ones.
+    /// If the selected key maximum is not peaked, `frequency` is returned.
+    pub fn frequency_uncertainty(&self) -> f32 {
+        if !self.is_valid() {
+            return 0.0;
+        }
+        let selected_max = self.key_maxima[self.selected_key_max_index];
+        if selected_max.curvature >= 0.0 {
+            return self.frequency;
+        }
+        let noise_floor = 1.0 - self.clarity;
+        let lag_uncertainty = F32Ext::sqrt(noise_floor / -selected_max.curvature);
+        (self.frequency * lag_uncertainty / self.pitch_period).min(self.frequency)
+    }
+
+    /// Returns a small copyable summary of this result, which unlike the result itself
+    /// does not own any buffers. The `sample_index` of the snapshot is 0.
+    pub fn snapshot(&self) -> PitchSnapshot {
+        PitchSnapshot {
+            frequency: self.frequency,
+            clarity: self.clarity,
+            midi_note_number: self.midi_note_number,
+            pitch_period: self.pitch_period,
+            is_tone: self.is_tone(),
+            window_peak: self.window_peak,
+            window_rms: self.window_rms,
+            sample_index: 0,
+        }
     }
 
     /// Returns true if the input window has a discernable fundamental frequency. False otherwise.
@@ -95,7 +565,10 @@ impl MpmPitchResult {
 
     /// Returns true if the input window has a discernable fundamental frequency. False otherwise.
     /// Compares the selected key maximum _m_, and the key maximum _n_ closest to the double period to
-    /// a number of thresholds.
+    /// a number of thresholds. If no key maximum was gathered after _m_, for example because
+    /// the key max capacity was reached or the NSDF peak was too shallow, _n_ is instead found by
+    /// parabolic interpolation of the NSDF around twice the lag of _m_. If the double period
+    /// is outside the NSDF, only the clarity of _m_ is considered.
     /// # Arguments
     ///
     /// * `clarity_threshold` - The clarity of _m_ must be greater than this value.
@@ -112,7 +585,10 @@ impl MpmPitchResult {
             return false;
         }
 
-        let is_tone = match self.key_max_closest_to_double_period() {
+        let next_max = self
+            .key_max_closest_to_double_period()
+            .or_else(|| self.interpolated_max_at_double_period());
+        let is_tone = match next_max {
             Some(next_max) => {
                 let max = self.key_maxima[self.selected_key_max_index];
 
@@ -137,6 +613,39 @@ impl MpmPitchResult {
         is_tone
     }
 
+    /// Classifies the analyzed window as silence, unvoiced or voiced, using default thresholds.
+    /// See `voicing_with_options`.
+    pub fn voicing(&self) -> Voicing {
+        self.voicing_with_options(-50.0, 0.8, 0.25)
+    }
+
+    /// Classifies the analyzed window as silence, unvoiced or voiced. Windows that are not
+    /// silent are voiced if they have a valid pitch estimate with a sufficient clarity and
+    /// a sufficiently low zero-crossing rate, and unvoiced otherwise. Noise has a high
+    /// zero-crossing rate even when it occasionally gives a high clarity.
+    /// # Arguments
+    ///
+    /// * `silence_threshold_db` - Windows with an RMS level in dB below this value are silent.
+    /// * `clarity_threshold` - The clarity of voiced windows must be greater than this value.
+    /// * `max_voiced_zero_crossing_rate` - The zero-crossing rate of voiced windows must not be greater than this value. Note that the zero-crossing rate depends on the sample rate of the analyzed window.
+    pub fn voicing_with_options(
+        &self,
+        silence_threshold_db: f32,
+        clarity_threshold: f32,
+        max_voiced_zero_crossing_rate: f32,
+    ) -> Voicing {
+        if self.window_rms_db() < silence_threshold_db {
+            Voicing::Silence
+        } else if self.is_valid()
+            && self.clarity > clarity_threshold
+            && self.zero_crossing_rate <= max_voiced_zero_crossing_rate
+        {
+            Voicing::Voiced
+        } else {
+            Voicing::Unvoiced
+        }
+    }
+
     fn key_max_closest_to_double_period(&self) -> Option<KeyMax> {
         if self.key_max_count == 0 {
             return None;
@@ -175,71 +684,74 @@ impl MpmPitchResult {
         None
     }
 
+    /// Approximates the NSDF maximum closest to twice the lag of the selected key maximum
+    /// using the same parabolic interpolation as for key maxima, centered on the largest of
+    /// the NSDF values at the lag index closest to the double lag and its neighbors.
+    /// Returns `None` if the double lag is too close to or beyond the end of the NSDF.
+    fn interpolated_max_at_double_period(&self) -> Option<KeyMax> {
+        if self.key_max_count == 0 {
+            return None;
+        }
+        let selected_max = &self.key_maxima[self.selected_key_max_index];
+        let double_lag_index = F32Ext::round(2.0 * selected_max.lag) as usize;
+        if double_lag_index < 2 || double_lag_index + 2 >= self.nsdf.len() {
+            return None;
+        }
+        let mut lag_index = double_lag_index;
+        for candidate in [double_lag_index - 1, double_lag_index + 1] {
+            if self.nsdf[candidate] > self.nsdf[lag_index] {
+                lag_index = candidate;
+            }
+        }
+        let mut max = KeyMax::new();
+        max.set(&self.nsdf, lag_index);
+        Some(max)
+    }
+
+    fn assert_has_window(&self) {
+        if self.window.is_empty() {
+            panic!("The result has no window, use compute_for to analyze a given window.")
+        }
+    }
+
     fn reset(&mut self) {
         self.frequency = 0.0;
         self.clarity = 0.0;
         self.midi_note_number = 0.0;
         self.key_max_count = 0;
+        self.key_maxima_overflowed = false;
         self.selected_key_max_index = 0;
         self.pitch_period = 0.0;
     }
 
     fn perform_peak_picking(&mut self) {
-        let nsdf = &mut self.nsdf[..];
+        let nsdf = &self.nsdf[..];
+        let key_maxima = &mut self.key_maxima[..];
+        let min_lag_index = self.min_lag_index;
+        let max_lag_index = self.max_lag_index;
 
         // Perform peak picking.
-        // Step 1: gather key maxima.
-        self.key_max_count = 0;
-        let mut is_detecting = false;
-        let mut maximum_value: f32 = 0.0;
-        let mut maximum_index: usize = 0;
-        let mut prev = nsdf[0];
-        for i in 1..nsdf.len() {
-            let is_last_lag = i == nsdf.len() - 1;
-            let curr = nsdf[i];
-            if prev <= 0.0 && curr > 0.0 {
-                // positive zero crossing, going from - to +.
-                // start looking for a key maximum
-                is_detecting = true;
-                maximum_value = curr;
-                maximum_index = i;
-            } else if prev >= 0.0 && curr < 0.0 {
-                // We reached a negative zero crossing (going from + to -) or the last lag.
-                // Stop looking for a key maximum and store the one we've got
-                // (unless we have collected the maximum number of key maxima)
-                if is_detecting && self.key_max_count < self.key_maxima.len() {
-                    self.key_maxima[self.key_max_count].set(&nsdf, maximum_index);
-                    self.key_max_count += 1
-                }
-                is_detecting = false;
+        // Step 1: gather key maxima, unless we have collected the maximum
+        // number of key maxima or the maximum is outside the allowed lag range.
+        let mut key_max_count = 0;
+        let mut key_maxima_overflowed = false;
+        util::for_each_key_max(nsdf, |lag_index| {
+            if lag_index < min_lag_index || lag_index > max_lag_index {
+                return;
             }
-
-            if is_detecting {
-                if is_last_lag {
-                    // Reached the last lag while looking for a new max.
-                    if self.key_max_count < self.key_maxima.len() {
-                        let last_max_index = if curr > maximum_value {
-                            i
-                        } else {
-                            maximum_index
-                        };
-                        self.key_maxima[self.key_max_count].set(&nsdf, last_max_index);
-                        self.key_max_count += 1
-                    }
-                } else if curr > maximum_value {
-                    // If we're looking for a key maximum and the current
-                    // value is greater than the current max, set a new max.
-                    maximum_value = curr;
-                    maximum_index = i;
-                }
+            if key_max_count < key_maxima.len() {
+                key_maxima[key_max_count].set(nsdf, lag_index);
+                key_max_count += 1
+            } else {
+                key_maxima_overflowed = true;
             }
-
-            prev = curr;
-        }
+        });
+        self.key_max_count = key_max_count;
+        self.key_maxima_overflowed = key_maxima_overflowed;
 
         // Step 2: Find the largest key maximum
         let mut largest_key_maximum: f32 = 0.0;
-        for (i, key_max) in self.key_maxima.iter().enumerate() {
+        for (i, key_max) in self.key_maxima().iter().enumerate() {
             let value = key_max.value_at_lag_index;
             if value > largest_key_maximum || i == 0 {
                 largest_key_maximum = value;
@@ -247,8 +759,7 @@ impl MpmPitchResult {
         }
 
         // Step 3: Select the final maximum
-        let k: f32 = 0.9;
-        let threshold = k * largest_key_maximum;
+        let threshold = self.peak_threshold_ratio * largest_key_maximum;
         for (key_max_index, key_max) in self.key_maxima.iter().take(self.key_max_count).enumerate()
         {
             if key_max.value >= threshold {
@@ -258,6 +769,32 @@ impl MpmPitchResult {
         }
     }
 
+    /// Selects the key maximum closest to half the selected lag, if it meets the octave
+    /// correction lag and clarity tolerances.
+    fn correct_octave_error(&mut self) {
+        if self.key_max_count == 0 {
+            return;
+        }
+
+        let selected_max = self.key_maxima[self.selected_key_max_index];
+        let half_lag = 0.5 * selected_max.lag;
+        let mut min_distance = OCTAVE_CORRECTION_LAG_TOLERANCE * half_lag;
+        for (i, key_max) in self
+            .key_maxima
+            .iter()
+            .take(self.selected_key_max_index)
+            .enumerate()
+        {
+            let distance = F32Ext::abs(key_max.lag - half_lag);
+            let meets_clarity_tolerance =
+                key_max.value >= selected_max.value - OCTAVE_CORRECTION_CLARITY_TOLERANCE;
+            if distance <= min_distance && meets_clarity_tolerance {
+                min_distance = distance;
+                self.selected_key_max_index = i;
+            }
+        }
+    }
+
     /// Computes pitch parameters from the currently selected key maximum.
     fn compute_pitch(&mut self, sample_rate: f32) {
         if self.key_max_count > 0 {
@@ -272,36 +809,69 @@ impl MpmPitchResult {
 
             let pitch_period = self.pitch_period / sample_rate;
             self.frequency = 1.0 / pitch_period;
-            self.midi_note_number = freq_to_midi_note(self.frequency);
+            self.midi_note_number = self.freq_to_midi_note(self.frequency);
         }
     }
 
-    /// Computes the normalized square difference function from the current contents of `window`.
-    fn compute_nsdf(&mut self) {
-        let window = &self.window[..];
-        let nsdf = &mut self.nsdf[..];
+    fn freq_to_midi_note(&self, freq: f32) -> f32 {
+        match self.reference_frequency {
+            Some(a4_freq) => freq_to_midi_note_with_reference(freq, a4_freq),
+            None => freq_to_midi_note(freq),
+        }
+    }
+
+    /// Computes the autocorrelation r' of `window` and stores it in `r_prime`.
+    fn compute_r_prime(&mut self, window: &[f32]) {
+        let lag_count = self.nsdf.len();
         let mut r_prime = &mut self.r_prime[..];
         let mut scratch_buffer = &mut self.scratch_buffer[..];
 
-        autocorr_fft(
-            &self.window[..],
+        let whitening_half_width = if self.spectral_whitening {
+            Some(SPECTRAL_WHITENING_HALF_WIDTH)
+        } else {
+            None
+        };
+        autocorr_fft_with(
+            &self.fft,
+            window,
             &mut r_prime,
             &mut scratch_buffer,
-            nsdf.len(),
+            lag_count,
+            whitening_half_width,
         );
 
+        if self.spectral_whitening {
+            // Rescale the whitened autocorrelation to the energy of the window,
+            // which m' is based on.
+            let energy: f32 = window.iter().map(|x| x * x).sum();
+            let scale = if r_prime[0] > 0.0 {
+                energy / r_prime[0]
+            } else {
+                0.0
+            };
+            for value in r_prime.iter_mut().take(lag_count) {
+                *value *= scale;
+            }
+        }
+    }
+
+    fn compute_nsdf(&mut self, window: &[f32]) {
+        self.compute_r_prime(window);
+        let nsdf = &mut self.nsdf[..];
+        let r_prime = &self.r_prime[..];
+
         // Compute m' and store it in the nsdf buffer
         let autocorr_at_lag_0 = r_prime[0];
         util::m_prime_incremental(window, autocorr_at_lag_0, nsdf);
 
         // Compute the NSDF as 2 * r' / m'
-        for i in 0..nsdf.len() {
-            let denominator = nsdf[i];
-            nsdf[i] = if F32Ext::abs(denominator) <= f32::EPSILON {
-                0.0
-            } else {
-                2.0 * r_prime[i] / denominator
-            };
+        guarded_ratio(r_prime, nsdf, 2.0);
+
+        if self.spectral_whitening {
+            // The whitened NSDF is not bounded by 1
+            for value in nsdf.iter_mut() {
+                *value = value.min(1.0);
+            }
         }
     }
 }
@@ -309,6 +879,9 @@ impl MpmPitchResult {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::common::{autocorr_conv, AutocorrScaling, DB_FLOOR};
+    use alloc::vec::Vec;
+    use rand::{rngs::StdRng, Rng, SeedableRng};
 
     #[test]
     fn test_silence() {
@@ -319,6 +892,8 @@ mod tests {
         result.compute(sample_rate);
         assert_eq!(result.nsdf[0], 0.);
         assert_eq!(result.key_max_count, 0);
+        assert_eq!(result.window_peak_db(), DB_FLOOR);
+        assert_eq!(result.window_rms_db(), DB_FLOOR);
     }
 
     #[test]
@@ -349,7 +924,7 @@ mod tests {
             assert_eq!(result.key_max_count, 2, "Unexpected key max count");
 
             // The value of the last key max should be reasonable
-            let last_max = result.key_maxima[result.key_max_count - 1];
+            let last_max = result.key_maxima()[result.key_max_count - 1];
             let last_max_lag = last_max.lag;
             let last_max_lag_index = last_max.lag_index;
             let last_max_value = last_max.value;
@@ -364,4 +939,522 @@ mod tests {
             );
         }
     }
+
+    #[test]
+    fn test_compute_for() {
+        let sample_rate: f32 = 44100.0;
+        let f = 441.0;
+        let mut window = [0.0; 1024];
+        for (i, value) in window.iter_mut().enumerate() {
+            *value = (2.0 * core::f32::consts::PI * f * (i as f32) / sample_rate).sin();
+        }
+        let mut result = MpmPitchResult::new(window.len(), window.len() / 2);
+        assert_eq!(result.effective_sample_rate(), 0.0);
+        assert_eq!(result.normalized_clarity(), 0.0);
+        result.compute_for(&window, sample_rate);
+        assert_eq!(result.effective_sample_rate(), sample_rate);
+        assert!(result.normalized_clarity() > 0.99 && result.normalized_clarity() <= 1.0);
+
+        let snapshot = result.snapshot();
+        assert_eq!(snapshot.frequency, result.frequency);
+        assert_eq!(snapshot.midi_note_number, result.midi_note_number);
+        assert_eq!(snapshot.pitch_period, result.pitch_period);
+        assert_eq!(snapshot.window_rms, result.window_rms);
+        assert!(snapshot.is_tone);
+        assert_eq!(snapshot.sample_index, 0);
+
+        result.clarity = -0.01;
+        assert_eq!(result.normalized_clarity(), 0.0);
+        assert!((f - result.frequency).abs() <= 0.01);
+        assert!((result.window_peak - 1.0).abs() < 1e-3);
+        assert!(result.window_peak_db().abs() < 0.01);
+        // The owned window is left untouched
+        assert!(result.window.iter().all(|value| *value == 0.0));
+    }
+
+    #[test]
+    #[should_panic]
+    fn test_compute_for_wrong_window_size() {
+        let mut result = MpmPitchResult::new(1024, 512);
+        result.compute_for(&[0.0; 512], 44100.0);
+    }
+
+    #[test]
+    fn test_without_window() {
+        let mut result = MpmPitchResult::without_window(1024, 512, 8);
+        assert!(result.window.is_empty());
+        assert_eq!(result.window_size(), 1024);
+        assert_eq!(result.key_max_capacity(), 8);
+        let window: Vec<f32> = (0..1024)
+            .map(|i| (2.0 * core::f32::consts::PI * 440.0 * i as f32 / 44100.0).sin())
+            .collect();
+        result.compute_for(&window, 44100.0);
+        assert!((result.frequency - 440.0).abs() < 1.0);
+    }
+
+    #[test]
+    #[should_panic]
+    fn test_compute_without_window() {
+        let mut result = MpmPitchResult::without_window(1024, 512, 8);
+        result.compute(44100.0);
+    }
+
+    #[test]
+    fn test_reference_frequency() {
+        let sample_rate: f32 = 44100.0;
+        let mut result = MpmPitchResult::new(1024, 512);
+        for i in 0..result.window.len() {
+            let phase = 2.0 * core::f32::consts::PI * 432.0 * (i as f32) / sample_rate;
+            result.window[i] = phase.sin();
+        }
+        result.compute(sample_rate);
+        assert_eq!(result.nearest_note_number(), 69);
+        assert!((result.cents_offset() + 31.77).abs() < 0.5);
+
+        result.set_reference_frequency(Some(432.0));
+        result.compute(sample_rate);
+        assert_eq!(result.nearest_note_number(), 69);
+        assert!(result.cents_offset().abs() < 0.5);
+    }
+
+    #[test]
+    fn test_cents_offset() {
+        let mut result = MpmPitchResult::new(128, 64);
+        result.compute(44100.0);
+        assert!(!result.is_valid());
+        assert_eq!(result.nearest_note_number(), 0);
+        assert_eq!(result.cents_offset(), 0.0);
+
+        result.key_max_count = 1;
+        for (midi_note_number, nearest, cents) in
+            [(69.25, 69, 25.0), (68.6, 69, -40.0), (60.0, 60, 0.0)]
+        {
+            result.midi_note_number = midi_note_number;
+            assert_eq!(result.nearest_note_number(), nearest);
+            assert!((result.cents_offset() - cents).abs() < 1e-3);
+        }
+    }
+
+    #[test]
+    fn test_lag_index_range() {
+        let window_size = 1024;
+        let lag_count = 512;
+        let sample_rate: f32 = 44100.0;
+        let f = 441.0; // 100 samples per period
+        let mut result = MpmPitchResult::new(window_size, lag_count);
+        for i in 0..window_size {
+            result.window[i] = (2.0 * core::f32::consts::PI * f * (i as f32) / sample_rate).sin();
+        }
+
+        // Maxima at 100, 200 and 300 samples. Only allow the second one.
+        result.set_lag_index_range(150, 250);
+        result.compute(sample_rate);
+        assert!(result.is_valid());
+        assert!(result
+            .key_maxima()
+            .iter()
+            .all(|key_max| key_max.lag_index >= 150 && key_max.lag_index <= 250));
+        assert!((result.pitch_period - 200.0).abs() < 1.0);
+
+        // No maxima in range
+        result.set_lag_index_range(20, 80);
+        result.compute(sample_rate);
+        assert!(!result.is_valid());
+
+        result.clear_lag_index_range();
+        result.compute(sample_rate);
+        assert!((f - result.frequency).abs() <= 0.01);
+    }
+
+    #[test]
+    fn test_octave_correction() {
+        // A signal with a period of 100 samples and a dominating second harmonic,
+        // giving an NSDF maximum at 50 samples slightly below the one at 100 samples.
+        let window_size = 1024;
+        let sample_rate: f32 = 44100.0;
+        let mut result = MpmPitchResult::new(window_size, window_size / 2);
+        for i in 0..window_size {
+            let phase = 2.0 * core::f32::consts::PI * (i as f32);
+            result.window[i] = 0.25 * (phase / 100.0).sin() + (phase / 50.0).sin();
+        }
+
+        assert!(!result.octave_correction());
+        result.compute(sample_rate);
+        assert!((result.pitch_period - 100.0).abs() < 1.0);
+
+        result.set_octave_correction(true);
+        result.compute(sample_rate);
+        assert!((result.pitch_period - 50.0).abs() < 1.0);
+    }
+
+    #[test]
+    fn test_peak_threshold_ratio() {
+        // A signal with a period of 100 samples and a strong second harmonic,
+        // giving a weaker NSDF maximum at 50 samples and a stronger one at 100 samples.
+        let window_size = 1024;
+        let lag_count = 512;
+        let sample_rate: f32 = 44100.0;
+        let mut result = MpmPitchResult::new(window_size, lag_count);
+        for i in 0..window_size {
+            let phase = 2.0 * core::f32::consts::PI * (i as f32);
+            result.window[i] = 0.5 * (phase / 100.0).sin() + (phase / 50.0).sin();
+        }
+
+        result.compute(sample_rate);
+        assert_eq!(result.peak_threshold_ratio(), 0.9);
+        let default_period = result.pitch_period;
+        assert!((default_period - 100.0).abs() < 1.0);
+
+        result.set_peak_threshold_ratio(0.5);
+        result.compute(sample_rate);
+        assert!((result.pitch_period - 50.0).abs() < 1.0);
+    }
+
+    #[test]
+    fn test_key_maxima() {
+        let sample_rate = 44100.0;
+        let window_size = 1024;
+        let mut result = MpmPitchResult::new(window_size, window_size / 2);
+        for i in 0..window_size {
+            result.window[i] =
+                (2.0 * core::f32::consts::PI * 441.0 * (i as f32) / sample_rate).sin();
+        }
+        result.compute(sample_rate);
+        assert_eq!(result.key_maxima().len(), result.key_max_count);
+        assert!(result.key_max_count > 1);
+
+        // Stale maxima from the previous window are not exposed
+        for sample in result.window.iter_mut() {
+            *sample = 0.0;
+        }
+        result.compute(sample_rate);
+        assert!(result.key_maxima().is_empty());
+    }
+
+    #[test]
+    fn test_frequency_uncertainty() {
+        use rand::{rngs::StdRng, Rng, SeedableRng};
+        let sample_rate = 44100.0;
+        let window_size = 1024;
+        let mut rng = StdRng::seed_from_u64(0);
+        let mut uncertainty = |noise_amplitude: f32| {
+            let mut result = MpmPitchResult::new(window_size, window_size / 2);
+            for (i, sample) in result.window.iter_mut().enumerate() {
+                let noise: f32 = rng.gen_range(-1.0..1.0);
+                *sample = (2.0 * core::f32::consts::PI * 440.0 * (i as f32) / sample_rate).sin()
+                    + noise_amplitude * noise;
+            }
+            result.compute(sample_rate);
+            assert!(result.is_valid());
+            result.frequency_uncertainty()
+        };
+
+        let clean = uncertainty(0.0);
+        let noisy = uncertainty(0.5);
+        assert!(clean >= 0.0 && clean < 1.0);
+        assert!(noisy > 2.0 * clean);
+        assert!(noisy < 440.0);
+
+        let silent = MpmPitchResult::new(window_size, window_size / 2);
+        assert_eq!(silent.frequency_uncertainty(), 0.0);
+    }
+
+    #[test]
+    fn test_noise_gate() {
+        let sample_rate = 44100.0;
+        let window_size = 1024;
+        let mut result = MpmPitchResult::new(window_size, window_size / 2);
+        result.set_noise_gate(Some(-40.0));
+        assert_eq!(result.noise_gate(), Some(-40.0));
+
+        let mut window = [0.0; 1024];
+        for (i, sample) in window.iter_mut().enumerate() {
+            *sample =
+                0.001 * (2.0 * core::f32::consts::PI * 440.0 * (i as f32) / sample_rate).sin();
+        }
+        result.compute_for(&window, sample_rate);
+        assert!(!result.is_valid());
+        assert!(result.window_rms > 0.0);
+        assert!(result.nsdf.iter().all(|value| *value == 0.0));
+
+        for sample in window.iter_mut() {
+            *sample *= 1000.0;
+        }
+        result.compute_for(&window, sample_rate);
+        assert!(result.is_valid());
+        assert!((result.frequency - 440.0).abs() < 0.1);
+
+        result.set_noise_gate(None);
+        for sample in window.iter_mut() {
+            *sample *= 0.001;
+        }
+        result.compute_for(&window, sample_rate);
+        assert!(result.is_valid());
+    }
+
+    #[test]
+    fn test_key_max_capacity() {
+        let sample_rate = 44100.0;
+        let window_size = 1024;
+        let mut window = [0.0; 1024];
+        for (i, sample) in window.iter_mut().enumerate() {
+            *sample = (2.0 * core::f32::consts::PI * 2205.0 * (i as f32) / sample_rate).sin();
+        }
+
+        let mut result = MpmPitchResult::new(window_size, window_size / 2);
+        assert_eq!(result.key_max_capacity(), MAX_KEY_MAXIMA_COUNT);
+        result.compute_for(&window, sample_rate);
+        let key_max_count = result.key_max_count;
+        assert!(key_max_count > 4);
+        assert!(!result.key_maxima_overflowed());
+
+        let mut result = MpmPitchResult::with_key_max_capacity(window_size, window_size / 2, 4);
+        assert_eq!(result.key_max_capacity(), 4);
+        result.compute_for(&window, sample_rate);
+        assert_eq!(result.key_max_count, 4);
+        assert!(result.key_maxima_overflowed());
+        assert!((result.frequency - 2205.0).abs() < 0.5);
+
+        // The flag is cleared for each new window
+        for sample in window.iter_mut() {
+            *sample = 0.0;
+        }
+        result.compute_for(&window, sample_rate);
+        assert!(!result.key_maxima_overflowed());
+    }
+
+    #[test]
+    fn test_spectral_whitening() {
+        // A 150 Hz tone with a dominant 5th harmonic, resembling a strong formant
+        let sample_rate = 44100.0;
+        let window_size = 1024;
+        let f0 = 150.0;
+        let window: Vec<f32> = (0..window_size)
+            .map(|i| {
+                let t = i as f32 / sample_rate;
+                (1..=10)
+                    .map(|harmonic| {
+                        let amplitude = if harmonic == 5 { 1.0 } else { 0.02 };
+                        amplitude * (2.0 * core::f32::consts::PI * f0 * harmonic as f32 * t).sin()
+                    })
+                    .sum()
+            })
+            .collect();
+
+        let mut result = MpmPitchResult::new(window_size, window_size / 2);
+        result.compute_for(&window, sample_rate);
+        assert!((result.frequency - 5.0 * f0).abs() < 5.0);
+
+        result.set_spectral_whitening(true);
+        assert!(result.spectral_whitening());
+        result.compute_for(&window, sample_rate);
+        assert!((result.frequency - f0).abs() < 2.0, "{}", result.frequency);
+        assert!(result.nsdf.iter().all(|value| *value <= 1.0));
+    }
+
+    #[test]
+    fn test_is_tone_without_key_max_at_double_period() {
+        // With a capacity of 1, only the key maximum at the period is gathered
+        let sample_rate = 44100.0;
+        let window_size = 1024;
+        let mut result = MpmPitchResult::with_key_max_capacity(window_size, window_size / 2, 1);
+        for i in 0..window_size {
+            result.window[i] =
+                (2.0 * core::f32::consts::PI * 440.0 * (i as f32) / sample_rate).sin();
+        }
+        result.compute(sample_rate);
+        assert_eq!(result.key_maxima().len(), 1);
+        assert!(result.is_tone());
+
+        // Flatten the NSDF around the double period, which the interpolated maximum should reflect
+        let double_lag_index = (2.0 * result.key_maxima()[0].lag).round() as usize;
+        for value in result.nsdf[double_lag_index - 5..double_lag_index + 5].iter_mut() {
+            *value = 0.0;
+        }
+        assert!(!result.is_tone());
+    }
+
+    #[test]
+    fn test_voicing() {
+        let sample_rate = 44100.0;
+        let window_size = 1024;
+        let mut result = MpmPitchResult::new(window_size, window_size / 2);
+        result.compute(sample_rate);
+        assert_eq!(result.voicing(), Voicing::Silence);
+
+        let tone: Vec<f32> = (0..window_size)
+            .map(|i| 0.5 * (2.0 * core::f32::consts::PI * 220.0 * (i as f32) / sample_rate).sin())
+            .collect();
+        result.compute_for(&tone, sample_rate);
+        assert!(result.zero_crossing_rate < 0.05);
+        assert_eq!(result.voicing(), Voicing::Voiced);
+        // A high level gate makes the tone silent
+        assert_eq!(
+            result.voicing_with_options(0.0, 0.8, 0.25),
+            Voicing::Silence
+        );
+
+        let mut rng = StdRng::seed_from_u64(123);
+        let noise: Vec<f32> = (0..window_size)
+            .map(|_| 0.5 * rng.gen_range(-1.0..=1.0))
+            .collect();
+        result.compute_for(&noise, sample_rate);
+        assert!(result.zero_crossing_rate > 0.25);
+        assert_eq!(result.voicing(), Voicing::Unvoiced);
+
+        // Computing only the autocorrelation also updates the zero-crossing rate
+        result.window.copy_from_slice(&tone);
+        result.compute_autocorrelation_only(sample_rate);
+        assert!(result.zero_crossing_rate < 0.05);
+    }
+
+    #[test]
+    fn test_compute_autocorrelation_only() {
+        let window_size = 64;
+        let lag_count = 16;
+        let mut result = MpmPitchResult::new(window_size, lag_count);
+        for (i, value) in result.window.iter_mut().enumerate() {
+            *value = (0.3 * i as f32).sin();
+        }
+        let mut expected = vec![0.0; lag_count];
+        autocorr_conv(&result.window, &mut expected, AutocorrScaling::Biased);
+
+        result.compute_autocorrelation_only(44100.0);
+        assert!(!result.is_valid());
+        assert!(result.nsdf.iter().all(|value| *value == 0.0));
+        assert_eq!(result.autocorrelation().len(), lag_count);
+        for (actual, expected) in result.autocorrelation().iter().zip(expected.iter()) {
+            assert!((actual - expected).abs() < 1e-4);
+        }
+
+        // A full computation also provides the autocorrelation
+        result.compute(44100.0);
+        for (actual, expected) in result.autocorrelation().iter().zip(expected.iter()) {
+            assert!((actual - expected).abs() < 1e-4);
+        }
+    }
+
+    #[test]
+    fn test_from_buffers() {
+        let sample_rate = 44100.0;
+        let window_size = 1024;
+        let lag_count = 512;
+        let fft_size = autocorr_fft_size(window_size, lag_count);
+        let mut result = MpmPitchResult::from_buffers(
+            vec![0.0; window_size].into_boxed_slice(),
+            vec![1.0; lag_count].into_boxed_slice(),
+            vec![2.0; fft_size].into_boxed_slice(),
+            vec![3.0; fft_size].into_boxed_slice(),
+            vec![KeyMax::new(); MAX_KEY_MAXIMA_COUNT].into_boxed_slice(),
+        );
+        assert_eq!(result.key_max_capacity(), MAX_KEY_MAXIMA_COUNT);
+
+        let mut expected = MpmPitchResult::new(window_size, lag_count);
+        for i in 0..window_size {
+            let value = (2.0 * core::f32::consts::PI * 440.0 * (i as f32) / sample_rate).sin();
+            result.window[i] = value;
+            expected.window[i] = value;
+        }
+        result.compute(sample_rate);
+        expected.compute(sample_rate);
+        assert_eq!(result.frequency, expected.frequency);
+        assert_eq!(result.nsdf, expected.nsdf);
+    }
+
+    #[test]
+    #[should_panic]
+    fn test_from_buffers_wrong_scratch_buffer_length() {
+        MpmPitchResult::from_buffers(
+            vec![0.0; 1024].into_boxed_slice(),
+            vec![0.0; 512].into_boxed_slice(),
+            vec![0.0; 2048].into_boxed_slice(),
+            vec![0.0; 1024].into_boxed_slice(),
+            vec![KeyMax::new(); MAX_KEY_MAXIMA_COUNT].into_boxed_slice(),
+        );
+    }
+
+    #[test]
+    #[should_panic]
+    fn test_from_buffers_empty_key_maxima() {
+        MpmPitchResult::from_buffers(
+            vec![0.0; 1024].into_boxed_slice(),
+            vec![0.0; 512].into_boxed_slice(),
+            vec![0.0; 2048].into_boxed_slice(),
+            vec![0.0; 2048].into_boxed_slice(),
+            Vec::new().into_boxed_slice(),
+        );
+    }
+
+    #[test]
+    #[should_panic]
+    fn test_zero_key_max_capacity() {
+        MpmPitchResult::with_key_max_capacity(1024, 512, 0);
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn test_serialize() {
+        let sample_rate = 44100.0;
+        let window_size = 1024;
+        let mut result = MpmPitchResult::new(window_size, window_size / 2);
+        for (i, sample) in result.window.iter_mut().enumerate() {
+            *sample = (2.0 * core::f32::consts::PI * 440.0 * (i as f32) / sample_rate).sin();
+        }
+        result.compute(sample_rate);
+
+        let json = serde_json::to_value(&result).unwrap();
+        assert_eq!(json["frequency"].as_f64().unwrap() as f32, result.frequency);
+        assert_eq!(json["clarity"].as_f64().unwrap() as f32, result.clarity);
+        assert_eq!(
+            json["midi_note_number"].as_f64().unwrap() as f32,
+            result.midi_note_number
+        );
+        assert_eq!(json["nsdf"].as_array().unwrap().len(), result.nsdf.len());
+        let key_max = &json["key_maxima"][result.selected_key_max_index];
+        assert_eq!(
+            key_max["lag"].as_f64().unwrap() as f32,
+            result.key_maxima()[result.selected_key_max_index].lag
+        );
+        assert!(json.get("r_prime").is_none());
+
+        // Only the public fields and the valid key maxima are serialized
+        let mut keys: Vec<&str> = json
+            .as_object()
+            .unwrap()
+            .keys()
+            .map(|key| key.as_str())
+            .collect();
+        keys.sort_unstable();
+        assert_eq!(
+            keys,
+            [
+                "clarity",
+                "frequency",
+                "key_max_count",
+                "key_maxima",
+                "midi_note_number",
+                "nsdf",
+                "pitch_period",
+                "selected_key_max_index",
+                "window",
+                "window_peak",
+                "window_rms",
+                "zero_crossing_rate"
+            ]
+        );
+        assert!(result.key_max_count > 0);
+        assert_eq!(
+            json["key_maxima"].as_array().unwrap().len(),
+            result.key_max_count
+        );
+
+        // Key maxima left over from the previous window are not serialized
+        for sample in result.window.iter_mut() {
+            *sample = 0.0;
+        }
+        result.compute(sample_rate);
+        assert_eq!(result.key_max_count, 0);
+        let json = serde_json::to_value(&result).unwrap();
+        assert_eq!(json["key_maxima"], serde_json::json!([]));
+    }
 }
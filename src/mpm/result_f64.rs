@@ -0,0 +1,229 @@
+use crate::alloc::boxed::Box;
+use crate::alloc::vec;
+use crate::common::{freq_to_midi_note, parabolic_peak};
+use crate::mpm::result::MAX_KEY_MAXIMA_COUNT;
+use crate::mpm::util::{for_each_key_max, validate_window_size_lag_count};
+
+/// A double precision version of [`MpmPitchResult`](super::MpmPitchResult), for
+/// offline analysis where the rounding errors of the single precision computations
+/// matter. The autocorrelation is computed by direct summation, i.e in `O(window_size * lag_count)`
+/// time, so this is considerably slower than the single precision version.
+/// Octave correction and lag index ranges are not supported.
+pub struct MpmPitchResultF64 {
+    /// The estimated pitch frequency in Hz.
+    pub frequency: f64,
+    /// The value of the NSDF at the maximum corresponding to the pitch period.
+    pub clarity: f64,
+    /// The [MIDI note number](https://newt.phys.unsw.edu.au/jw/notes.html) corresponding to the pitch frequency.
+    /// Converted using the single precision [`freq_to_midi_note`], which is accurate to about 0.1 cents.
+    pub midi_note_number: f64,
+    /// The estimated pitch period in samples.
+    pub pitch_period: f64,
+    /// The normalized square difference function
+    pub nsdf: Box<[f64]>,
+    /// The window size.
+    window_size: usize,
+    /// The fraction of the largest key maximum a key maximum must reach to be selected.
+    peak_threshold_ratio: f64,
+    /// Indicates if a key maximum was found.
+    is_valid: bool,
+}
+
+impl MpmPitchResultF64 {
+    pub fn new(window_size: usize, lag_count: usize) -> Self {
+        validate_window_size_lag_count(window_size, lag_count);
+        MpmPitchResultF64 {
+            frequency: 0.0,
+            clarity: 0.0,
+            midi_note_number: 0.0,
+            pitch_period: 0.0,
+            nsdf: vec![0.0; lag_count].into_boxed_slice(),
+            window_size,
+            peak_threshold_ratio: 0.9,
+            is_valid: false,
+        }
+    }
+
+    /// Sets the key maximum selection threshold, relative to the largest key maximum.
+    /// See [`MpmPitchResult::set_peak_threshold_ratio`](super::MpmPitchResult::set_peak_threshold_ratio).
+    pub fn set_peak_threshold_ratio(&mut self, peak_threshold_ratio: f64) {
+        self.peak_threshold_ratio = peak_threshold_ratio;
+    }
+
+    /// Indicates if the detection result has a valid pitch estimate.
+    pub fn is_valid(&self) -> bool {
+        self.is_valid
+    }
+
+    /// Performs pitch detection on a given window.
+    pub fn compute_for(&mut self, window: &[f64], sample_rate: f64) {
+        if window.len() != self.window_size {
+            panic!(
+                "Got window of length {}, expected {}.",
+                window.len(),
+                self.window_size
+            )
+        }
+        self.frequency = 0.0;
+        self.clarity = 0.0;
+        self.midi_note_number = 0.0;
+        self.pitch_period = 0.0;
+        self.is_valid = false;
+
+        self.compute_nsdf(window);
+        if let Some((lag, value)) = self.perform_peak_picking() {
+            self.is_valid = true;
+            self.pitch_period = lag;
+            self.clarity = value.min(1.0);
+            self.frequency = sample_rate / lag;
+            self.midi_note_number = freq_to_midi_note(self.frequency as f32) as f64;
+        }
+    }
+
+    fn compute_nsdf(&mut self, window: &[f64]) {
+        let window_size = window.len();
+        // m' is computed using incremental subtraction, as in the single precision version
+        let mut m_prime = 0.0;
+        for (tau, nsdf) in self.nsdf.iter_mut().enumerate() {
+            let r: f64 = window[..window_size - tau]
+                .iter()
+                .zip(window[tau..].iter())
+                .map(|(a, b)| a * b)
+                .sum();
+            if tau == 0 {
+                m_prime = 2.0 * r;
+            } else {
+                let v1 = window[window_size - tau];
+                let v2 = window[tau - 1];
+                m_prime -= v1 * v1 + v2 * v2;
+            }
+            *nsdf = if m_prime > 0.0 {
+                2.0 * r / m_prime
+            } else {
+                0.0
+            };
+        }
+    }
+
+    /// Returns the interpolated lag and value of the selected key maximum, if any.
+    fn perform_peak_picking(&self) -> Option<(f64, f64)> {
+        let nsdf = &self.nsdf[..];
+        let mut key_maxima = [0usize; MAX_KEY_MAXIMA_COUNT];
+        let mut key_max_count = 0;
+        for_each_key_max(nsdf, |lag_index| {
+            if key_max_count < key_maxima.len() {
+                key_maxima[key_max_count] = lag_index;
+                key_max_count += 1;
+            }
+        });
+
+        // Select the first key maximum reaching the threshold
+        let largest_value = key_maxima[..key_max_count]
+            .iter()
+            .fold(0.0, |max: f64, index| max.max(nsdf[*index]));
+        let threshold = self.peak_threshold_ratio * largest_value;
+        key_maxima[..key_max_count]
+            .iter()
+            .map(|index| interpolate_max(nsdf, *index))
+            .find(|(_, value)| *value >= threshold)
+    }
+}
+
+/// Returns the lag and value of the maximum of the parabola passing through
+/// the values at a given index and its neighbors.
+fn interpolate_max(nsdf: &[f64], index: usize) -> (f64, f64) {
+    // Key maxima are never at lag 0
+    let right = nsdf[(nsdf.len() - 1).min(index + 1)];
+    let (x_max, value) = parabolic_peak(nsdf[index - 1], nsdf[index], right);
+    ((index as f64) + x_max, value)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::alloc::vec::Vec;
+    use crate::mpm::MpmPitchResult;
+
+    fn generate_sine(sample_rate: f64, frequency: f64, sample_count: usize) -> Vec<f64> {
+        (0..sample_count)
+            .map(|i| (2.0 * core::f64::consts::PI * frequency * (i as f64) / sample_rate).sin())
+            .collect()
+    }
+
+    #[test]
+    fn test_precision() {
+        let sample_rate = 44100.0;
+        let window_size = 4096;
+        let lag_count = 1024;
+        let frequency: f64 = 261.6255653005986;
+        let window = generate_sine(sample_rate, frequency, window_size);
+
+        let mut result = MpmPitchResultF64::new(window_size, lag_count);
+        result.compute_for(&window, sample_rate);
+        assert!(result.is_valid());
+        assert!(result.clarity > 0.99);
+        assert!((result.frequency - frequency).abs() < 1e-4);
+        // Within the accuracy of the single precision note number conversion
+        assert!((result.midi_note_number - 60.0).abs() < 2e-3);
+    }
+
+    #[test]
+    fn test_precision_vs_f32() {
+        let sample_rate = 44100.0;
+        let window_size = 2048;
+        let lag_count = 1024;
+        let frequency: f64 = 261.6255653005986;
+        let window = generate_sine(sample_rate, frequency, window_size);
+        let mut result = MpmPitchResultF64::new(window_size, lag_count);
+        result.compute_for(&window, sample_rate);
+
+        // The single precision NSDF deviates from the double precision one
+        // by the accumulated rounding errors
+        let mut result_f32 = MpmPitchResult::new(window_size, lag_count);
+        let window_f32: Vec<f32> = window.iter().map(|x| *x as f32).collect();
+        result_f32.compute_for(&window_f32, sample_rate as f32);
+        let max_nsdf_error = result
+            .nsdf
+            .iter()
+            .zip(result_f32.nsdf.iter())
+            .fold(0.0, |max: f64, (a, b)| max.max((a - (*b as f64)).abs()));
+        assert!(max_nsdf_error > 0.0);
+        assert!(max_nsdf_error < 1e-3);
+        assert!((result.frequency - (result_f32.frequency as f64)).abs() < 1e-3);
+
+        // The rounding errors are largest for long pitch periods, where the
+        // double precision estimates are closer to the true frequencies
+        let lag_count = 2048;
+        let mut error = 0.0;
+        let mut error_f32 = 0.0;
+        for period in [650.5, 812.25, 1003.75, 1250.5] {
+            let frequency = sample_rate / period;
+            let window = generate_sine(sample_rate, frequency, window_size);
+            let mut result = MpmPitchResultF64::new(window_size, lag_count);
+            result.compute_for(&window, sample_rate);
+            let mut result_f32 = MpmPitchResult::new(window_size, lag_count);
+            let window_f32: Vec<f32> = window.iter().map(|x| *x as f32).collect();
+            result_f32.compute_for(&window_f32, sample_rate as f32);
+            error += (result.frequency - frequency).abs();
+            error_f32 += ((result_f32.frequency as f64) - frequency).abs();
+        }
+        assert!(error < error_f32);
+    }
+
+    #[test]
+    fn test_key_maxima_match_f32() {
+        // Both versions gather key maxima using the same peak picking
+        let sample_rate = 8000.0;
+        let window_size = 512;
+        let lag_count = 256;
+        for frequency in [100.0, 440.0, 1000.0] {
+            let window = generate_sine(sample_rate, frequency, window_size);
+            let mut result = MpmPitchResultF64::new(window_size, lag_count);
+            result.compute_for(&window, sample_rate);
+            let mut result_f32 = MpmPitchResult::new(window_size, lag_count);
+            let window_f32: Vec<f32> = window.iter().map(|x| *x as f32).collect();
+            result_f32.compute_for(&window_f32, sample_rate as f32);
+            assert!((result.pitch_period - (result_f32.pitch_period as f64)).abs() < 1e-3);
+        }
+    }
+}
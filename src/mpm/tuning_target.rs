@@ -0,0 +1,86 @@
+use alloc::boxed::Box;
+
+use crate::common::freq_to_cents;
+
+/// A set of target frequencies, for example the open strings of an instrument,
+/// that detected pitch frequencies can be snapped to, e.g for implementing a tuner.
+pub struct TuningTarget {
+    frequencies: Box<[f32]>,
+}
+
+impl TuningTarget {
+    /// Creates a new `TuningTarget` instance from a non-empty list
+    /// of positive target frequencies in Hz.
+    pub fn new(frequencies: &[f32]) -> Self {
+        if frequencies.is_empty() {
+            panic!("Tuning target must have at least one frequency")
+        }
+        if frequencies.iter().any(|f| *f <= 0.0) {
+            panic!("Tuning target frequencies must be positive")
+        }
+        TuningTarget {
+            frequencies: frequencies.into(),
+        }
+    }
+
+    /// Creates a `TuningTarget` for a guitar in standard tuning,
+    /// i.e E2, A2, D3, G3, B3 and E4 with A4 at 440 Hz.
+    pub fn standard_guitar() -> Self {
+        TuningTarget::new(&[82.407, 110.0, 146.832, 195.998, 246.942, 329.628])
+    }
+
+    /// Returns the target frequencies in Hz.
+    pub fn frequencies(&self) -> &[f32] {
+        &self.frequencies
+    }
+
+    /// Returns the index of the target frequency closest to `freq`, measured in cents,
+    /// and the deviation in cents of `freq` from that target. The deviation is positive
+    /// if `freq` is sharp. `freq` must be positive, e.g the `frequency` of a valid
+    /// [`MpmPitchResult`](crate::mpm::MpmPitchResult).
+    pub fn nearest(&self, freq: f32) -> (usize, f32) {
+        let mut nearest = (0, freq_to_cents(freq, self.frequencies[0]));
+        for (index, target) in self.frequencies.iter().enumerate().skip(1) {
+            let cents = freq_to_cents(freq, *target);
+            if cents.abs() < nearest.1.abs() {
+                nearest = (index, cents);
+            }
+        }
+        nearest
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_nearest() {
+        let target = TuningTarget::standard_guitar();
+        assert_eq!(target.frequencies().len(), 6);
+
+        // A slightly flat low E string
+        let (index, cents) = target.nearest(82.0);
+        assert_eq!(index, 0);
+        assert!((cents + 8.58).abs() < 0.2, "{}", cents);
+
+        // 20 cents sharp A string
+        let (index, cents) = target.nearest(110.0 * 2.0_f32.powf(20.0 / 1200.0));
+        assert_eq!(index, 1);
+        assert!((cents - 20.0).abs() < 0.2, "{}", cents);
+
+        // Between G3 and B3, but closer to B3 in cents
+        let (index, _) = target.nearest(225.0);
+        assert_eq!(index, 4);
+
+        // Far outside the range of targets
+        assert_eq!(target.nearest(1000.0).0, 5);
+        assert_eq!(target.nearest(20.0).0, 0);
+    }
+
+    #[test]
+    #[should_panic]
+    fn test_empty() {
+        TuningTarget::new(&[]);
+    }
+}
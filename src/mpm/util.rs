@@ -1,9 +1,30 @@
+use crate::common::Float;
+use crate::Error;
+
 pub(crate) fn validate_window_size_lag_count(window_size: usize, lag_count: usize) {
+    if lag_count == 0 {
+        panic!("Lag count must be greater than 0");
+    }
     if lag_count > window_size {
         panic!("Lag count must not be greater than the window size");
     }
 }
 
+/// Validates the lag count passed to a pitch detector constructor, so that
+/// an invalid lag count is reported instead of failing deep inside the analysis.
+pub(crate) fn validate_downsampled_lag_count(
+    downsampled_window_size: usize,
+    downsampled_lag_count: usize,
+) -> Result<(), Error> {
+    if downsampled_lag_count == 0 || downsampled_lag_count > downsampled_window_size {
+        return Err(Error::LagCountOutOfRange {
+            lag_count: downsampled_lag_count,
+            window_size: downsampled_window_size,
+        });
+    }
+    Ok(())
+}
+
 /// Computes m' defined in eq (6), using the incremental subtraction
 /// algorithm described in section 6 - Efficient calculation of SDF.
 pub(crate) fn m_prime_incremental(window: &[f32], autocorr_at_lag_0: f32, result: &mut [f32]) {
@@ -19,12 +40,61 @@ pub(crate) fn m_prime_incremental(window: &[f32], autocorr_at_lag_0: f32, result
     }
 }
 
+/// Calls `key_max_handler` with the lag index of each key maximum of `nsdf`, i.e the
+/// index of the largest value between a positive zero crossing and the following
+/// negative zero crossing, or the last lag. Step 1 of the peak picking.
+pub(crate) fn for_each_key_max<T, F>(nsdf: &[T], mut key_max_handler: F)
+where
+    T: Float,
+    F: FnMut(usize),
+{
+    let mut is_detecting = false;
+    let mut maximum_value = T::ZERO;
+    let mut maximum_index: usize = 0;
+    let mut prev = nsdf[0];
+    for (i, &curr) in nsdf.iter().enumerate().skip(1) {
+        let is_last_lag = i == nsdf.len() - 1;
+        if prev <= T::ZERO && curr > T::ZERO {
+            // positive zero crossing, going from - to +.
+            // start looking for a key maximum
+            is_detecting = true;
+            maximum_value = curr;
+            maximum_index = i;
+        } else if prev >= T::ZERO && curr < T::ZERO {
+            // We reached a negative zero crossing (going from + to -).
+            // Stop looking for a key maximum and report the one we've got.
+            if is_detecting {
+                key_max_handler(maximum_index);
+            }
+            is_detecting = false;
+        }
+
+        if is_detecting {
+            if is_last_lag {
+                // Reached the last lag while looking for a new max.
+                key_max_handler(if curr > maximum_value {
+                    i
+                } else {
+                    maximum_index
+                });
+            } else if curr > maximum_value {
+                // If we're looking for a key maximum and the current
+                // value is greater than the current max, set a new max.
+                maximum_value = curr;
+                maximum_index = i;
+            }
+        }
+
+        prev = curr;
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
     use crate::alloc::vec;
     use crate::alloc::vec::Vec;
-    use crate::common::autocorr_conv;
+    use crate::common::{autocorr_conv, AutocorrScaling};
 
     // Computes m', defined in eq (6), as a naive inefficient summation.
     // Only used for testing purposes.
@@ -62,7 +132,7 @@ mod tests {
 
         // Compute m' by incremental subtraction
         let mut autocorr: Vec<f32> = vec![0.0; lag_count];
-        autocorr_conv(&signal[..], &mut autocorr[..]);
+        autocorr_conv(&signal[..], &mut autocorr[..], AutocorrScaling::Biased);
         let mut m_prime_incr: Vec<f32> = vec![0.0; lag_count];
         m_prime_incremental(&signal[..], autocorr[0], &mut m_prime_incr[..]);
 
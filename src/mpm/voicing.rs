@@ -0,0 +1,13 @@
+/// A classification of an analyzed window, e.g for speech processing.
+/// See [`MpmPitchResult::voicing`](crate::mpm::MpmPitchResult::voicing).
+#[derive(Clone, Copy, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub enum Voicing {
+    /// The window level is below the silence threshold.
+    Silence,
+    /// The window is not silent, but has no clear fundamental frequency,
+    /// for example noise or unvoiced consonants.
+    Unvoiced,
+    /// The window has a clear fundamental frequency, for example vowels or tones.
+    Voiced,
+}
@@ -0,0 +1,36 @@
+//! An implementation of the [YIN](http://audition.ens.fr/adc/pdf/2002_JASA_YIN.pdf) pitch detection
+//! algorithm, sharing the FFT based autocorrelation with the MPM implementation. The squared
+//! difference function is derived from the autocorrelation and normalized using YIN's
+//! cumulative mean normalization. YIN may be more robust than MPM for very low pitched voices.
+//!
+//! # Example
+//! ```
+//! use microdsp::mpm::yin::YinPitchDetector;
+//!
+//! // Create an input buffer containing a pure tone at 440 Hz.
+//! let sample_rate = 44100.0;
+//! let sine_frequency = 440.0;
+//! let mut chunk: Vec<f32> = vec![0.0; 10000];
+//! for i in 0..chunk.len() {
+//!     let sine_value = (2.0 * core::f32::consts::PI * sine_frequency * (i as f32) / sample_rate).sin();
+//!     chunk[i] = sine_value;
+//! }
+//!
+//! // Create a pitch detector instance
+//! let window_size = 1024;
+//! let hop_size = 256;
+//! let mut detector = YinPitchDetector::new(sample_rate, window_size, hop_size);
+//!
+//! // Perform pitch detection.
+//! detector.process(&chunk[..], |result, sample_index, _| {
+//!     assert!(result.is_tone());
+//!     println!("Sample {}: frequency {} Hz, confidence {}", sample_index, result.frequency, result.confidence);
+//!     assert!((sine_frequency - result.frequency).abs() <= 0.1);
+//! });
+//! ```
+
+mod result;
+mod yin_pitch_detector;
+
+pub use result::YinPitchResult;
+pub use yin_pitch_detector::YinPitchDetector;
@@ -0,0 +1,210 @@
+use micromath::F32Ext;
+
+use crate::alloc::boxed::Box;
+use crate::alloc::vec;
+use crate::common::freq_to_midi_note;
+use crate::common::{autocorr_fft, autocorr_fft_size};
+use crate::mpm::util;
+
+/// A YIN pitch detection result.
+pub struct YinPitchResult {
+    /// The estimated pitch frequency in Hz.
+    pub frequency: f32,
+    /// One minus the value of the cumulative mean normalized difference function at
+    /// the selected lag. At most 1. Pure tones have a value close to 1.
+    pub confidence: f32,
+    /// The [MIDI note number](https://newt.phys.unsw.edu.au/jw/notes.html) corresponding to the pitch frequency.
+    pub midi_note_number: f32,
+    /// The estimated pitch period in samples.
+    pub pitch_period: f32,
+    /// The window analyzed by `compute`. Not modified by `compute_for`, which is what
+    /// [`YinPitchDetector`](crate::mpm::yin::YinPitchDetector) uses to avoid copying each
+    /// window. The detector instead passes the analyzed window to its result handler.
+    pub window: Box<[f32]>,
+    /// The cumulative mean normalized difference function.
+    pub cmndf: Box<[f32]>,
+    /// The absolute threshold the cumulative mean normalized difference function must
+    /// fall below for the input to be considered a tone.
+    threshold: f32,
+    is_below_threshold: bool,
+    r_prime: Box<[f32]>,
+    scratch_buffer: Box<[f32]>,
+}
+
+impl YinPitchResult {
+    /// Creates a new `YinPitchResult`. Lag 0 is never selected, so with a lag
+    /// count of 1 the result is never a tone.
+    pub fn new(window_size: usize, lag_count: usize) -> Self {
+        util::validate_window_size_lag_count(window_size, lag_count);
+        let fft_size = autocorr_fft_size(window_size, lag_count);
+        YinPitchResult {
+            frequency: 0.0,
+            confidence: 0.0,
+            midi_note_number: 0.0,
+            pitch_period: 0.0,
+            window: vec![0.0; window_size].into_boxed_slice(),
+            cmndf: vec![0.0; lag_count].into_boxed_slice(),
+            threshold: 0.1,
+            is_below_threshold: false,
+            r_prime: vec![0.0; fft_size].into_boxed_slice(),
+            scratch_buffer: vec![0.0; fft_size].into_boxed_slice(),
+        }
+    }
+
+    /// Returns the absolute threshold. See `set_threshold`.
+    pub fn threshold(&self) -> f32 {
+        self.threshold
+    }
+
+    /// Sets the absolute threshold. The pitch period is the first lag at which
+    /// the cumulative mean normalized difference function has a local minimum
+    /// below this value. Defaults to 0.1.
+    pub fn set_threshold(&mut self, threshold: f32) {
+        self.threshold = threshold;
+    }
+
+    /// Returns true if the cumulative mean normalized difference function fell
+    /// below the absolute threshold, i.e if the input window has a discernable
+    /// fundamental frequency. False otherwise.
+    pub fn is_tone(&self) -> bool {
+        self.is_below_threshold
+    }
+
+    /// Performs pitch detection on the current contents of `window`.
+    pub fn compute(&mut self, sample_rate: f32) {
+        let window = core::mem::take(&mut self.window);
+        self.analyze(&window, sample_rate);
+        self.window = window;
+    }
+
+    /// Performs pitch detection on a given window, which must have the same length
+    /// as `window`.
+    pub fn compute_for(&mut self, window: &[f32], sample_rate: f32) {
+        if window.len() != self.window.len() {
+            panic!(
+                "Got window of length {}, expected {}.",
+                window.len(),
+                self.window.len()
+            )
+        }
+        self.analyze(window, sample_rate);
+    }
+
+    fn analyze(&mut self, window: &[f32], sample_rate: f32) {
+        self.compute_cmndf(window);
+        if self.cmndf.len() < 2 {
+            // There is no lag to select, since lag 0 is always excluded.
+            self.is_below_threshold = false;
+            self.pitch_period = 0.0;
+            self.confidence = 0.0;
+            self.frequency = 0.0;
+            self.midi_note_number = 0.0;
+            return;
+        }
+        let (lag_index, is_below_threshold) = self.select_lag_index();
+        self.is_below_threshold = is_below_threshold;
+
+        // Use parabolic interpolation to approximate the true minimum
+        let cmndf = &self.cmndf;
+        let center = cmndf[lag_index];
+        let left = cmndf[lag_index - 1];
+        let right = cmndf[(cmndf.len() - 1).min(lag_index + 1)];
+        let a = 0.5 * (right - 2.0 * center + left);
+        let b = 0.5 * (right - left);
+        let x_min = if a > 0.0 { -b / (2.0 * a) } else { 0.0 };
+        let value = a * x_min * x_min + b * x_min + center;
+
+        self.pitch_period = (lag_index as f32) + x_min;
+        self.confidence = 1.0 - value.max(0.0);
+        self.frequency = sample_rate / self.pitch_period;
+        self.midi_note_number = freq_to_midi_note(self.frequency);
+    }
+
+    /// Returns the index of the first local minimum below the threshold and true
+    /// or, if there is no such minimum, the index of the global minimum and false.
+    fn select_lag_index(&self) -> (usize, bool) {
+        let cmndf = &self.cmndf;
+        let mut min_index = 1;
+        for lag_index in 1..cmndf.len() {
+            let value = cmndf[lag_index];
+            if value < self.threshold {
+                // Proceed to the bottom of the dip
+                let mut index = lag_index;
+                while index + 1 < cmndf.len() && cmndf[index + 1] < cmndf[index] {
+                    index += 1;
+                }
+                return (index, true);
+            }
+            if value < cmndf[min_index] {
+                min_index = lag_index;
+            }
+        }
+        (min_index, false)
+    }
+
+    /// Computes the cumulative mean normalized difference function of a given window.
+    fn compute_cmndf(&mut self, window: &[f32]) {
+        let cmndf = &mut self.cmndf[..];
+        let r_prime = &mut self.r_prime[..];
+        autocorr_fft(window, r_prime, &mut self.scratch_buffer, cmndf.len());
+
+        // The squared difference function is m' - 2 * r'. Store m' in
+        // the output buffer and subtract 2 * r' in place.
+        util::m_prime_incremental(window, r_prime[0], cmndf);
+
+        let mut running_sum = 0.0;
+        cmndf[0] = 1.0;
+        for lag_index in 1..cmndf.len() {
+            let difference = cmndf[lag_index] - 2.0 * r_prime[lag_index];
+            running_sum += difference;
+            cmndf[lag_index] = if F32Ext::abs(running_sum) <= f32::EPSILON {
+                1.0
+            } else {
+                difference * (lag_index as f32) / running_sum
+            };
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_single_lag() {
+        let mut result = YinPitchResult::new(64, 1);
+        for (i, sample) in result.window.iter_mut().enumerate() {
+            *sample = (0.3 * i as f32).sin();
+        }
+        result.compute(44100.0);
+        assert!(!result.is_tone());
+        assert_eq!(result.frequency, 0.0);
+        assert_eq!(result.confidence, 0.0);
+        assert_eq!(result.cmndf[..], [1.0]);
+    }
+
+    #[test]
+    fn test_silence() {
+        let mut result = YinPitchResult::new(1024, 512);
+        result.compute(44100.0);
+        assert!(!result.is_tone());
+        assert_eq!(result.confidence, 0.0);
+    }
+
+    #[test]
+    fn test_sine() {
+        for f in [82.0_f32, 154.0, 440.0, 1000.0] {
+            let sample_rate: f32 = 44100.0;
+            let window_size = 2048;
+            let mut result = YinPitchResult::new(window_size, window_size / 2);
+            for i in 0..window_size {
+                let phase = 2.0 * core::f32::consts::PI * f * (i as f32) / sample_rate;
+                result.window[i] = phase.sin();
+            }
+            result.compute(sample_rate);
+            assert!(result.is_tone());
+            assert!(result.confidence > 0.9);
+            assert!((f - result.frequency).abs() / f <= 1e-3);
+        }
+    }
+}
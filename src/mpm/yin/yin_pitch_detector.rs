@@ -0,0 +1,146 @@
+use crate::common::WindowProcessor;
+use crate::mpm::util::validate_downsampled_lag_count;
+use crate::mpm::yin::result::YinPitchResult;
+
+/// A streaming YIN pitch detector. Mirrors [`MpmPitchDetector`](crate::mpm::MpmPitchDetector).
+pub struct YinPitchDetector {
+    sample_rate: f32,
+    window_processor: WindowProcessor,
+    result: YinPitchResult,
+}
+
+impl YinPitchDetector {
+    pub fn new(sample_rate: f32, window_size: usize, hop_size: usize) -> Self {
+        YinPitchDetector::from_options(sample_rate, window_size, hop_size, window_size / 2, 1)
+    }
+
+    pub fn from_options(
+        sample_rate: f32,
+        downsampled_window_size: usize,
+        downsampled_hop_size: usize,
+        downsampled_lag_count: usize,
+        downsampling: usize,
+    ) -> Self {
+        if let Err(error) =
+            validate_downsampled_lag_count(downsampled_window_size, downsampled_lag_count)
+        {
+            panic!("{}", error)
+        }
+        YinPitchDetector {
+            sample_rate,
+            result: YinPitchResult::new(downsampled_window_size, downsampled_lag_count),
+            window_processor: WindowProcessor::new(
+                downsampled_window_size,
+                downsampled_hop_size,
+                downsampling,
+            ),
+        }
+    }
+
+    /// Processes an arbitrarily sized buffer of input samples. Invokes the provided
+    /// handler with the result of each newly analyzed window, the index of the last
+    /// input sample in that window, in the non-downsampled sample domain, and the
+    /// analyzed, possibly downsampled, window. The window is passed to the handler
+    /// instead of being copied to [`YinPitchResult::window`].
+    pub fn process<F>(&mut self, buffer: &[f32], mut result_handler: F)
+    where
+        F: FnMut(&YinPitchResult, usize, &[f32]),
+    {
+        let result = &mut self.result;
+        let downsampling = self.window_processor.downsampling();
+        let downsampled_window_size = self.window_processor.downsampled_window_size();
+        let downsampled_hop_size = self.window_processor.downsampled_hop_size();
+        let sample_rate = self.sample_rate;
+        self.window_processor
+            .process(buffer, |window_index, window| {
+                result.compute_for(window, sample_rate / (downsampling as f32));
+                let last_downsampled_index =
+                    downsampled_window_size - 1 + window_index * downsampled_hop_size;
+                result_handler(result, downsampling * last_downsampled_index, window);
+            });
+    }
+
+    /// Returns the most recently computed pitch detection result.
+    pub fn result(&self) -> &YinPitchResult {
+        &self.result
+    }
+
+    /// Returns the current sample rate in Hz.
+    pub fn sample_rate(&self) -> f32 {
+        self.sample_rate
+    }
+
+    /// Sets the sample rate in Hz.
+    pub fn set_sample_rate(&mut self, sample_rate: f32) {
+        self.sample_rate = sample_rate;
+    }
+
+    /// Sets the absolute threshold. See [`YinPitchResult::set_threshold`].
+    pub fn set_threshold(&mut self, threshold: f32) {
+        self.result.set_threshold(threshold);
+    }
+
+    pub fn downsampled_window_size(&self) -> usize {
+        self.window_processor.downsampled_window_size()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::alloc::vec::Vec;
+
+    #[test]
+    #[should_panic(expected = "Lag count 129 must be in the range [1, 128]")]
+    fn test_invalid_lag_count() {
+        YinPitchDetector::from_options(44100.0, 128, 64, 129, 1);
+    }
+
+    #[test]
+    fn test_single_lag() {
+        let mut detector = YinPitchDetector::from_options(44100.0, 128, 64, 1, 1);
+        let signal: Vec<f32> = (0..256).map(|i| (0.3 * i as f32).sin()).collect();
+        let mut window_count = 0;
+        detector.process(&signal, |result, _, _| {
+            assert!(!result.is_tone());
+            assert_eq!(result.frequency, 0.0);
+            window_count += 1;
+        });
+        assert_eq!(window_count, 3);
+    }
+
+    #[test]
+    #[should_panic(expected = "Lag count 0 must be in the range [1, 128]")]
+    fn test_zero_lag_count() {
+        YinPitchDetector::from_options(44100.0, 128, 64, 0, 1);
+    }
+
+    #[test]
+    fn test_from_options_sizes() {
+        let detector = YinPitchDetector::from_options(44100.0, 256, 64, 128, 3);
+        assert_eq!(detector.window_processor.downsampled_window_size(), 256);
+        assert_eq!(detector.window_processor.downsampled_hop_size(), 64);
+        assert_eq!(detector.window_processor.downsampling(), 3);
+    }
+
+    #[test]
+    fn test_downsampled_sine_detection() {
+        let sample_rate: f32 = 44100.0;
+        let frequency: f32 = 110.0;
+        let signal: Vec<f32> = (0..8192)
+            .map(|i| (2.0 * core::f32::consts::PI * frequency * (i as f32) / sample_rate).sin())
+            .collect();
+        let mut detector = YinPitchDetector::from_options(sample_rate, 1024, 512, 512, 4);
+        let mut window_count = 0;
+        detector.process(&signal[..], |result, sample_index, window| {
+            // The handler gets the downsampled window ending at the sample index
+            assert_eq!(window.len(), 1024);
+            assert_eq!(window[1023], signal[sample_index]);
+            assert_eq!(window[1022], signal[sample_index - 4]);
+            assert!(result.is_tone());
+            assert!((frequency - result.frequency).abs() <= 0.1);
+            window_count += 1;
+        });
+        assert!(window_count > 0);
+    }
+}
@@ -0,0 +1,116 @@
+use alloc::{vec, vec::Vec};
+use microfft::Complex32;
+
+/// A complex valued version of [`NlmsFilter`](super::NlmsFilter), e.g for
+/// IQ baseband signals. Filter output is `y = h·x` and coefficients are
+/// updated using `h += μ e conj(x) / (||x||² + ε)`.
+pub struct ComplexNlmsFilter {
+    /// FIR filter coefficients
+    h: Vec<Complex32>,
+    /// Most recent input values. Newest sample is at index `buffer_pos`.
+    x: Vec<Complex32>,
+    /// Step size scale
+    μ: f32,
+    /// Running sum of current input signal power.
+    x_power: f32,
+    /// Constant added to the update step denominator to avoid division by zero.
+    ε: f32,
+    buffer_pos: usize,
+}
+
+impl ComplexNlmsFilter {
+    pub fn new(order: usize, mu: f32, eps: f32) -> Self {
+        let zero = Complex32::new(0.0, 0.0);
+        ComplexNlmsFilter {
+            h: vec![zero; order],
+            // Double size for x to avoid index wrapping in the update method,
+            // like in NlmsFilter.
+            x: vec![zero; 2 * order],
+            μ: mu,
+            ε: eps,
+            buffer_pos: 0,
+            x_power: 0.0,
+        }
+    }
+
+    pub fn h(&self) -> &[Complex32] {
+        &self.h
+    }
+
+    pub fn order(&self) -> usize {
+        self.h.len()
+    }
+
+    /// Adds a new input sample and adapts the filter coefficients to reduce the
+    /// error between the filter output and the desired output `d`. Returns the error.
+    pub fn update(&mut self, x: Complex32, d: Complex32) -> Complex32 {
+        assert!(self.buffer_pos < self.order());
+        let order = self.order();
+        self.x[self.buffer_pos] = x;
+        self.x[self.buffer_pos + order] = x;
+
+        // Add new input sample to signal power
+        self.x_power += x.norm_sqr();
+
+        let x_newest_first = &self.x[self.buffer_pos..(self.buffer_pos + order)];
+        let mut y = Complex32::new(0.0, 0.0);
+        for (h, x) in self.h.iter().zip(x_newest_first.iter()) {
+            y += h * x;
+        }
+
+        let e = d - y;
+        let delta_scale = e * (self.μ / (self.x_power + self.ε));
+        for (h, x) in self.h.iter_mut().zip(x_newest_first.iter()) {
+            *h += delta_scale * x.conj();
+        }
+
+        // Subtract oldest input sample from signal power and advance buffer position
+        let next_buffer_pos = if self.buffer_pos == 0 {
+            order - 1
+        } else {
+            self.buffer_pos - 1
+        };
+        self.x_power -= self.x[next_buffer_pos].norm_sqr();
+        self.buffer_pos = next_buffer_pos;
+
+        e
+    }
+
+    pub fn reset(&mut self) {
+        let zero = Complex32::new(0.0, 0.0);
+        for value in self.h.iter_mut().chain(self.x.iter_mut()) {
+            *value = zero;
+        }
+        self.buffer_pos = 0;
+        self.x_power = 0.0;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::{rngs::StdRng, Rng, SeedableRng};
+
+    #[test]
+    fn test_complex_identity_filter() {
+        let mut rng = StdRng::seed_from_u64(123);
+        let mut filter = ComplexNlmsFilter::new(4, 0.5, 0.00001);
+        // A pure phase rotation, which can not be represented by
+        // filtering the real and imaginary parts separately
+        let gain = Complex32::new(0.6, 0.8);
+        for i in 0..1000 {
+            let x = Complex32::new(rng.gen_range(-1.0..=1.0), rng.gen_range(-1.0..=1.0));
+            let e = filter.update(x, gain * x);
+            if i > 200 {
+                assert!(e.norm_sqr() < 1e-8);
+            }
+        }
+        assert!((filter.h()[0] - gain).norm_sqr() < 1e-8);
+        for h in filter.h()[1..].iter() {
+            assert!(h.norm_sqr() < 1e-8);
+        }
+
+        filter.reset();
+        assert!(filter.h().iter().all(|h| h.norm_sqr() == 0.0));
+    }
+}
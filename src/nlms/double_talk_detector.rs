@@ -0,0 +1,113 @@
+use alloc::{vec, vec::Vec};
+
+/// A [Geigel](https://doi.org/10.1109/TCOM.1986.1096547) double-talk detector for
+/// echo cancellation. Double-talk, i.e near-end signal in addition to the echo of the
+/// far-end signal, is declared when the magnitude of a near-end sample exceeds a
+/// threshold times the largest far-end magnitude in a recent window. Once detected,
+/// double-talk is reported for a hangover period. The result can be passed to
+/// [`NlmsFilter::freeze`](super::NlmsFilter::freeze) to stop adaptation during double-talk.
+pub struct DoubleTalkDetector {
+    /// Magnitudes of the most recent far-end samples.
+    x_abs: Vec<f32>,
+    /// The ring buffer index of the most recent far-end sample.
+    position: usize,
+    threshold: f32,
+    hangover: usize,
+    /// The number of samples left to report double-talk for.
+    hangover_counter: usize,
+}
+
+impl DoubleTalkDetector {
+    /// Creates a new `DoubleTalkDetector` with a threshold of 0.5 (-6 dB)
+    /// and a hangover equal to the window length.
+    /// # Arguments
+    ///
+    /// * `window_length` - The number of far-end samples to consider. Should be at least the length of the echo path.
+    pub fn new(window_length: usize) -> Self {
+        DoubleTalkDetector::from_options(window_length, 0.5, window_length)
+    }
+
+    /// Creates a new `DoubleTalkDetector`.
+    /// # Arguments
+    ///
+    /// * `window_length` - The number of far-end samples to consider. Should be at least the length of the echo path.
+    /// * `threshold` - The near-end to far-end magnitude ratio above which double-talk is detected. Should be the largest expected echo path gain.
+    /// * `hangover` - The number of samples to keep reporting double-talk for after the last detection.
+    pub fn from_options(window_length: usize, threshold: f32, hangover: usize) -> Self {
+        if window_length == 0 {
+            panic!("Double-talk detector window length must be greater than 0")
+        }
+        if threshold <= 0.0 {
+            panic!("Double-talk detector threshold must be greater than 0")
+        }
+        DoubleTalkDetector {
+            x_abs: vec![0.0; window_length],
+            position: 0,
+            threshold,
+            hangover,
+            hangover_counter: 0,
+        }
+    }
+
+    /// Processes a far-end sample `x` and a near-end sample `d`.
+    /// Returns true if double-talk is present.
+    pub fn update(&mut self, x: f32, d: f32) -> bool {
+        self.position = (self.position + 1) % self.x_abs.len();
+        self.x_abs[self.position] = x.abs();
+
+        let x_max = self.x_abs.iter().fold(0.0, |max: f32, x| max.max(*x));
+        if d.abs() > self.threshold * x_max {
+            self.hangover_counter = self.hangover;
+            true
+        } else if self.hangover_counter > 0 {
+            self.hangover_counter -= 1;
+            true
+        } else {
+            false
+        }
+    }
+
+    pub fn reset(&mut self) {
+        for x in self.x_abs.iter_mut() {
+            *x = 0.0;
+        }
+        self.position = 0;
+        self.hangover_counter = 0;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::{rngs::StdRng, Rng, SeedableRng};
+
+    #[test]
+    fn test_near_end_burst() {
+        const ECHO_DELAY: usize = 10;
+        let mut rng = StdRng::seed_from_u64(123);
+        let x: Vec<f32> = (0..3000).map(|_| rng.gen_range(-1.0..=1.0)).collect();
+        let burst = 1000..1500;
+
+        let mut detector = DoubleTalkDetector::from_options(32, 0.5, 100);
+        for i in 0..x.len() {
+            // Echo of the far-end signal, plus a near-end burst
+            let echo = if i >= ECHO_DELAY {
+                0.3 * x[i - ECHO_DELAY]
+            } else {
+                0.0
+            };
+            let near_end = if burst.contains(&i) {
+                2.0 * rng.gen_range(-1.0..=1.0)
+            } else {
+                0.0
+            };
+            let double_talk = detector.update(x[i], echo + near_end);
+            if i < burst.start || i >= burst.end + 100 {
+                assert!(!double_talk);
+            }
+            if i >= burst.start + 50 && i < burst.end {
+                assert!(double_talk);
+            }
+        }
+    }
+}
@@ -38,6 +38,12 @@
 //!     }
 //! }
 
+mod complex_nlms_filter;
+mod double_talk_detector;
 mod nlms_filter;
+mod sign_sign_lms;
 
-pub use nlms_filter::NlmsFilter;
+pub use complex_nlms_filter::ComplexNlmsFilter;
+pub use double_talk_detector::DoubleTalkDetector;
+pub use nlms_filter::{FixedNlmsFilter, NlmsFilter};
+pub use sign_sign_lms::SignSignLms;
@@ -1,5 +1,7 @@
 use alloc::{vec, vec::Vec};
 
+const DEFAULT_MSE_SMOOTHING: f32 = 0.01;
+
 /// An adaptive [normalized least mean squares filter](https://en.wikipedia.org/wiki/Least_mean_squares_filter#Normalized_least_mean_squares_filter_(NLMS)).
 /// Using the same notation as in the linked description.
 pub struct NlmsFilter {
@@ -13,6 +15,14 @@ pub struct NlmsFilter {
     x_power: f32,
     /// Constant added to the update step denominator to avoid division by zero.
     ε: f32,
+    /// Fraction by which the coefficients are shrunk before each update step.
+    leakage: f32,
+    /// If true, the coefficients are not adapted.
+    frozen: bool,
+    /// Exponential moving average of the squared error.
+    mse: f32,
+    /// Smoothing factor of the squared error moving average.
+    mse_smoothing: f32,
     buffer_pos: usize,
 }
 
@@ -26,20 +36,125 @@ impl NlmsFilter {
             x: vec![0.0; 2 * order],
             μ: mu,
             ε: eps,
+            leakage: 0.0,
+            frozen: false,
+            mse: 0.0,
+            mse_smoothing: DEFAULT_MSE_SMOOTHING,
             buffer_pos: 0,
             x_power: 0.0,
         }
     }
 
-    pub fn h(&mut self) -> &[f32] {
+    pub fn h(&self) -> &[f32] {
         &self.h
     }
 
-    pub fn order(&mut self) -> usize {
+    /// Sets the filter coefficients, e.g to start from a known impulse response.
+    /// The number of coefficients must be equal to the filter order.
+    pub fn set_h(&mut self, coeffs: &[f32]) {
+        if coeffs.len() != self.h.len() {
+            panic!(
+                "Expected {} NLMS filter coefficients, got {}",
+                self.h.len(),
+                coeffs.len()
+            )
+        }
+        self.h.copy_from_slice(coeffs);
+    }
+
+    pub fn order(&self) -> usize {
         self.h.len()
     }
 
+    /// Sets the leakage factor, i.e the fraction by which the filter coefficients
+    /// are scaled down before each update step. A non-zero leakage prevents the
+    /// coefficients from drifting when the input signal is weak. Must be in the range [0, 1).
+    /// Defaults to 0.
+    pub fn set_leakage(&mut self, leakage: f32) {
+        if !(0.0..1.0).contains(&leakage) {
+            panic!("NLMS leakage must be in the range [0, 1)")
+        }
+        self.leakage = leakage;
+    }
+
+    pub fn leakage(&self) -> f32 {
+        self.leakage
+    }
+
+    /// Returns an exponential moving average of the squared error, useful
+    /// for monitoring convergence.
+    pub fn mse(&self) -> f32 {
+        self.mse
+    }
+
+    /// Sets the smoothing factor `alpha` of the squared error moving average
+    /// returned by [`NlmsFilter::mse`], updated as `mse += alpha (e² - mse)`.
+    /// Must be in the range (0, 1]. Defaults to 0.01.
+    pub fn set_mse_smoothing(&mut self, alpha: f32) {
+        check_mse_smoothing(alpha);
+        self.mse_smoothing = alpha;
+    }
+
+    /// Stops (`true`) or resumes (`false`) adaptation of the filter coefficients.
+    /// While frozen, [`NlmsFilter::update`] still filters the input and returns the error.
+    pub fn freeze(&mut self, frozen: bool) {
+        self.frozen = frozen;
+    }
+
+    pub fn is_frozen(&self) -> bool {
+        self.frozen
+    }
+
+    /// Adds a new input sample and adapts the filter coefficients to reduce the
+    /// error between the filter output and the desired output `d`. Returns the error.
     pub fn update(&mut self, x: f32, d: f32) -> f32 {
+        self.push_input(x);
+
+        let order = self.order();
+        let x_newest_first = &self.x[self.buffer_pos..(self.buffer_pos + order)];
+        let e = d - filter_output(&self.h, x_newest_first, &[]);
+        if !self.frozen {
+            update_coefficients(
+                &mut self.h,
+                x_newest_first,
+                &[],
+                e,
+                self.μ,
+                self.x_power + self.ε,
+                self.leakage,
+            );
+        }
+
+        self.mse += self.mse_smoothing * (e * e - self.mse);
+
+        self.advance();
+        e
+    }
+
+    /// Processes a block of input samples `x` and desired output samples `d`,
+    /// writing the error for each sample to `e_out`. Equivalent to calling
+    /// [`NlmsFilter::update`] for each sample. All buffers must have the same length.
+    pub fn update_block(&mut self, x: &[f32], d: &[f32], e_out: &mut [f32]) {
+        check_block_lengths(x, d, e_out);
+        for ((x, d), e) in x.iter().zip(d.iter()).zip(e_out.iter_mut()) {
+            *e = self.update(*x, *d);
+        }
+    }
+
+    /// Adds a new input sample and returns the filter output, without
+    /// adapting the filter coefficients.
+    pub fn predict(&mut self, x: f32) -> f32 {
+        self.push_input(x);
+
+        let order = self.order();
+        let x_newest_first = &self.x[self.buffer_pos..(self.buffer_pos + order)];
+        let y = filter_output(&self.h, x_newest_first, &[]);
+
+        self.advance();
+        y
+    }
+
+    fn push_input(&mut self, x: f32) {
         assert!(self.buffer_pos < self.order());
         let order = self.order();
         self.x[self.buffer_pos] = x;
@@ -47,19 +162,9 @@ impl NlmsFilter {
 
         // Add new input sample to signal power
         self.x_power += x * x;
+    }
 
-        // Compute filter output y = h applied to x.
-        let mut y = 0.0;
-        for (h, x) in self.h.iter().zip(self.x.iter().skip(self.buffer_pos)) {
-            y += h * *x
-        }
-
-        let e = d - y;
-        let delta_scale = self.μ * e / (self.x_power + self.ε);
-        for (h, x) in self.h.iter_mut().zip(self.x.iter().skip(self.buffer_pos)) {
-            *h += delta_scale * *x;
-        }
-
+    fn advance(&mut self) {
         // Subtract oldest input sample from signal power and advance buffer position
         let next_buffer_pos = if self.buffer_pos == 0 {
             self.order() - 1
@@ -69,8 +174,6 @@ impl NlmsFilter {
         let x_oldest = self.x[next_buffer_pos];
         self.x_power -= x_oldest * x_oldest;
         self.buffer_pos = next_buffer_pos;
-
-        e
     }
 
     pub fn reset(&mut self) {
@@ -80,6 +183,219 @@ impl NlmsFilter {
         }
         self.buffer_pos = 0;
         self.x_power = 0.0;
+        self.mse = 0.0;
+    }
+}
+
+/// Like [`NlmsFilter`], but with a filter order known at compile time.
+/// Uses fixed size arrays and performs no heap allocations.
+pub struct FixedNlmsFilter<const ORDER: usize> {
+    /// FIR filter coefficients
+    h: [f32; ORDER],
+    /// Ring buffer of the most recent input values. Newest sample is at index `buffer_pos`.
+    x: [f32; ORDER],
+    /// Step size scale
+    μ: f32,
+    /// Running sum of current input signal power.
+    x_power: f32,
+    /// Constant added to the update step denominator to avoid division by zero.
+    ε: f32,
+    /// Fraction by which the coefficients are shrunk before each update step.
+    leakage: f32,
+    /// If true, the coefficients are not adapted.
+    frozen: bool,
+    /// Exponential moving average of the squared error.
+    mse: f32,
+    /// Smoothing factor of the squared error moving average.
+    mse_smoothing: f32,
+    buffer_pos: usize,
+}
+
+impl<const ORDER: usize> FixedNlmsFilter<ORDER> {
+    pub fn from_options(mu: f32, eps: f32) -> Self {
+        if ORDER == 0 {
+            panic!("NLMS filter order must be greater than 0")
+        }
+        FixedNlmsFilter {
+            h: [0.0; ORDER],
+            x: [0.0; ORDER],
+            μ: mu,
+            ε: eps,
+            leakage: 0.0,
+            frozen: false,
+            mse: 0.0,
+            mse_smoothing: DEFAULT_MSE_SMOOTHING,
+            buffer_pos: 0,
+            x_power: 0.0,
+        }
+    }
+
+    pub fn h(&self) -> &[f32] {
+        &self.h
+    }
+
+    /// Sets the filter coefficients. See [`NlmsFilter::set_h`].
+    pub fn set_h(&mut self, coeffs: &[f32]) {
+        if coeffs.len() != ORDER {
+            panic!(
+                "Expected {} NLMS filter coefficients, got {}",
+                ORDER,
+                coeffs.len()
+            )
+        }
+        self.h.copy_from_slice(coeffs);
+    }
+
+    pub fn order(&self) -> usize {
+        ORDER
+    }
+
+    /// Sets the leakage factor. See [`NlmsFilter::set_leakage`].
+    pub fn set_leakage(&mut self, leakage: f32) {
+        if !(0.0..1.0).contains(&leakage) {
+            panic!("NLMS leakage must be in the range [0, 1)")
+        }
+        self.leakage = leakage;
+    }
+
+    pub fn leakage(&self) -> f32 {
+        self.leakage
+    }
+
+    /// See [`NlmsFilter::mse`].
+    pub fn mse(&self) -> f32 {
+        self.mse
+    }
+
+    /// See [`NlmsFilter::set_mse_smoothing`].
+    pub fn set_mse_smoothing(&mut self, alpha: f32) {
+        check_mse_smoothing(alpha);
+        self.mse_smoothing = alpha;
+    }
+
+    /// Stops or resumes adaptation. See [`NlmsFilter::freeze`].
+    pub fn freeze(&mut self, frozen: bool) {
+        self.frozen = frozen;
+    }
+
+    pub fn is_frozen(&self) -> bool {
+        self.frozen
+    }
+
+    /// See [`NlmsFilter::update`].
+    pub fn update(&mut self, x: f32, d: f32) -> f32 {
+        self.push_input(x);
+
+        let (x_oldest_last, x_newest_first) = self.x.split_at(self.buffer_pos);
+        let e = d - filter_output(&self.h, x_newest_first, x_oldest_last);
+        if !self.frozen {
+            update_coefficients(
+                &mut self.h,
+                x_newest_first,
+                x_oldest_last,
+                e,
+                self.μ,
+                self.x_power + self.ε,
+                self.leakage,
+            );
+        }
+
+        self.mse += self.mse_smoothing * (e * e - self.mse);
+
+        self.advance();
+        e
+    }
+
+    /// See [`NlmsFilter::update_block`].
+    pub fn update_block(&mut self, x: &[f32], d: &[f32], e_out: &mut [f32]) {
+        check_block_lengths(x, d, e_out);
+        for ((x, d), e) in x.iter().zip(d.iter()).zip(e_out.iter_mut()) {
+            *e = self.update(*x, *d);
+        }
+    }
+
+    /// See [`NlmsFilter::predict`].
+    pub fn predict(&mut self, x: f32) -> f32 {
+        self.push_input(x);
+
+        let (x_oldest_last, x_newest_first) = self.x.split_at(self.buffer_pos);
+        let y = filter_output(&self.h, x_newest_first, x_oldest_last);
+
+        self.advance();
+        y
+    }
+
+    fn push_input(&mut self, x: f32) {
+        self.x[self.buffer_pos] = x;
+
+        // Add new input sample to signal power
+        self.x_power += x * x;
+    }
+
+    fn advance(&mut self) {
+        // Subtract oldest input sample from signal power and advance buffer position
+        let next_buffer_pos = if self.buffer_pos == 0 {
+            ORDER - 1
+        } else {
+            self.buffer_pos - 1
+        };
+        let x_oldest = self.x[next_buffer_pos];
+        self.x_power -= x_oldest * x_oldest;
+        self.buffer_pos = next_buffer_pos;
+    }
+
+    pub fn reset(&mut self) {
+        self.h = [0.0; ORDER];
+        self.x = [0.0; ORDER];
+        self.buffer_pos = 0;
+        self.x_power = 0.0;
+        self.mse = 0.0;
+    }
+}
+
+fn check_mse_smoothing(alpha: f32) {
+    if alpha <= 0.0 || alpha > 1.0 {
+        panic!("NLMS MSE smoothing factor must be in the range (0, 1]")
+    }
+}
+
+fn check_block_lengths(x: &[f32], d: &[f32], e_out: &[f32]) {
+    if x.len() != d.len() || x.len() != e_out.len() {
+        panic!("NLMS input, desired output and error buffers must have the same length")
+    }
+}
+
+/// Returns the filter output given the most recent input values, newest first,
+/// split into two slices.
+pub(super) fn filter_output(h: &[f32], x_head: &[f32], x_tail: &[f32]) -> f32 {
+    // Compute filter output y = h applied to x.
+    let mut y = 0.0;
+    for (h, x) in h.iter().zip(x_head.iter().chain(x_tail.iter())) {
+        y += h * *x
+    }
+    y
+}
+
+/// Performs an NLMS update step given the error `e` and the most recent
+/// input values, newest first, split into two slices.
+fn update_coefficients(
+    h: &mut [f32],
+    x_head: &[f32],
+    x_tail: &[f32],
+    e: f32,
+    mu: f32,
+    denominator: f32,
+    leakage: f32,
+) {
+    if leakage > 0.0 {
+        let scale = 1.0 - leakage;
+        for h in h.iter_mut() {
+            *h *= scale;
+        }
+    }
+    let delta_scale = mu * e / denominator;
+    for (h, x) in h.iter_mut().zip(x_head.iter().chain(x_tail.iter())) {
+        *h += delta_scale * *x;
     }
 }
 
@@ -107,4 +423,136 @@ mod tests {
             }
         }
     }
+
+    #[test]
+    fn test_fixed_order_filter() {
+        let mut filter = NlmsFilter::new(4, 0.5, 0.001);
+        let mut fixed_filter = FixedNlmsFilter::<4>::from_options(0.5, 0.001);
+        assert_eq!(fixed_filter.order(), 4);
+        for i in 0..100 {
+            let x = ((i * 7919) % 13) as f32 - 6.0;
+            let d = 0.5 * x + 0.25;
+            assert_eq!(filter.update(x, d), fixed_filter.update(x, d));
+            assert_eq!(filter.h(), fixed_filter.h());
+        }
+        fixed_filter.reset();
+        assert!(fixed_filter.h().iter().all(|h| *h == 0.0));
+    }
+
+    #[test]
+    fn test_leakage() {
+        let mut filter = NlmsFilter::new(3, 0.5, 0.001);
+        filter.set_leakage(0.1);
+        for i in 0..20 {
+            let x = (i % 5) as f32;
+            filter.update(x, 2.0 * x);
+        }
+        let initial_norm: f32 = filter.h().iter().map(|h| h.abs()).sum();
+        assert!(initial_norm > 0.1);
+
+        // With zero input, the coefficients should decay towards zero
+        let mut prev_norm = initial_norm;
+        for _ in 0..100 {
+            filter.update(0.0, 0.0);
+            let norm: f32 = filter.h().iter().map(|h| h.abs()).sum();
+            assert!(norm < prev_norm);
+            prev_norm = norm;
+        }
+        assert!(prev_norm < 1e-3 * initial_norm);
+    }
+
+    #[test]
+    #[should_panic]
+    fn test_invalid_leakage() {
+        NlmsFilter::new(3, 0.5, 0.001).set_leakage(1.0);
+    }
+
+    #[test]
+    fn test_predict_and_freeze() {
+        let mut filter = NlmsFilter::new(3, 0.5, 0.001);
+        let mut frozen_filter = NlmsFilter::new(3, 0.5, 0.001);
+        for i in 0..50 {
+            let x = (i % 7) as f32;
+            filter.update(x, 2.0 * x);
+            frozen_filter.update(x, 2.0 * x);
+        }
+        let h: Vec<f32> = filter.h().to_vec();
+
+        // Predicting and updating a frozen filter both use the current
+        // coefficients without modifying them
+        frozen_filter.freeze(true);
+        for x in [1.0, -2.0, 3.0] {
+            let y = filter.predict(x);
+            let e = frozen_filter.update(x, 5.0);
+            assert_eq!(e, 5.0 - y);
+            assert_eq!(filter.h(), &h[..]);
+            assert_eq!(frozen_filter.h(), &h[..]);
+        }
+
+        frozen_filter.freeze(false);
+        frozen_filter.update(1.0, 5.0);
+        assert_ne!(frozen_filter.h(), &h[..]);
+    }
+
+    #[test]
+    fn test_set_h() {
+        let mut filter = NlmsFilter::new(3, 0.5, 0.001);
+        filter.set_h(&[1.0, 0.5, 0.25]);
+        assert_eq!(filter.h(), &[1.0, 0.5, 0.25]);
+        assert_eq!(filter.predict(4.0), 4.0);
+        assert_eq!(filter.predict(0.0), 2.0);
+        assert_eq!(filter.predict(0.0), 1.0);
+    }
+
+    #[test]
+    #[should_panic]
+    fn test_set_h_length_mismatch() {
+        NlmsFilter::new(3, 0.5, 0.001).set_h(&[1.0, 0.5]);
+    }
+
+    #[test]
+    fn test_update_block() {
+        let x: Vec<f32> = (0..64).map(|i| ((i * 7919) % 13) as f32 - 6.0).collect();
+        let d: Vec<f32> = x.iter().map(|x| 0.5 * x + 0.25).collect();
+
+        let mut filter = NlmsFilter::new(4, 0.5, 0.001);
+        let e_expected: Vec<f32> = x
+            .iter()
+            .zip(d.iter())
+            .map(|(x, d)| filter.update(*x, *d))
+            .collect();
+
+        let mut block_filter = NlmsFilter::new(4, 0.5, 0.001);
+        let mut e = vec![0.0; x.len()];
+        block_filter.update_block(&x[..32], &d[..32], &mut e[..32]);
+        block_filter.update_block(&x[32..], &d[32..], &mut e[32..]);
+        assert_eq!(e, e_expected);
+        assert_eq!(block_filter.h(), filter.h());
+    }
+
+    #[test]
+    #[should_panic]
+    fn test_update_block_length_mismatch() {
+        let mut e = [0.0; 3];
+        NlmsFilter::new(3, 0.5, 0.001).update_block(&[0.0; 4], &[0.0; 4], &mut e);
+    }
+
+    #[test]
+    fn test_mse() {
+        let mut filter = NlmsFilter::new(3, 0.5, 0.001);
+        filter.set_mse_smoothing(1.0);
+        let e = filter.update(1.0, 2.0);
+        assert_eq!(filter.mse(), e * e);
+
+        // The MSE should approach zero as the filter converges
+        filter.set_mse_smoothing(0.1);
+        for i in 0..200 {
+            let x = ((i * 7919) % 13) as f32 - 6.0;
+            filter.update(x, 0.5 * x);
+        }
+        assert!(filter.mse() < 1e-6);
+
+        filter.reset();
+        assert_eq!(filter.mse(), 0.0);
+    }
 }
@@ -0,0 +1,105 @@
+use super::nlms_filter::filter_output;
+use alloc::{vec, vec::Vec};
+
+/// An adaptive [sign-sign least mean squares filter](https://en.wikipedia.org/wiki/Least_mean_squares_filter).
+/// The coefficient update `h += μ sign(e) sign(x)` only involves additions and
+/// subtractions, which makes it far cheaper than the [`NlmsFilter`](super::NlmsFilter)
+/// update on hardware without fast multiplication. The price is slower convergence
+/// and a residual error on the order of the step size.
+pub struct SignSignLms {
+    /// FIR filter coefficients
+    h: Vec<f32>,
+    /// Most recent input values. Newest sample is at index `buffer_pos`.
+    x: Vec<f32>,
+    /// Step size
+    μ: f32,
+    buffer_pos: usize,
+}
+
+impl SignSignLms {
+    pub fn new(order: usize, mu: f32) -> Self {
+        SignSignLms {
+            h: vec![0.0; order],
+            // Double size for x to avoid index wrapping in the update method,
+            // like in NlmsFilter.
+            x: vec![0.0; 2 * order],
+            μ: mu,
+            buffer_pos: 0,
+        }
+    }
+
+    pub fn h(&self) -> &[f32] {
+        &self.h
+    }
+
+    pub fn order(&self) -> usize {
+        self.h.len()
+    }
+
+    /// Adds a new input sample and adapts the filter coefficients to reduce the
+    /// error between the filter output and the desired output `d`. Returns the error.
+    pub fn update(&mut self, x: f32, d: f32) -> f32 {
+        assert!(self.buffer_pos < self.order());
+        let order = self.order();
+        self.x[self.buffer_pos] = x;
+        self.x[self.buffer_pos + order] = x;
+
+        let x_newest_first = &self.x[self.buffer_pos..(self.buffer_pos + order)];
+        let e = d - filter_output(&self.h, x_newest_first, &[]);
+        if e != 0.0 {
+            for (h, x) in self.h.iter_mut().zip(x_newest_first.iter()) {
+                if *x == 0.0 {
+                    continue;
+                }
+                if (e > 0.0) == (*x > 0.0) {
+                    *h += self.μ;
+                } else {
+                    *h -= self.μ;
+                }
+            }
+        }
+
+        self.buffer_pos = if self.buffer_pos == 0 {
+            order - 1
+        } else {
+            self.buffer_pos - 1
+        };
+
+        e
+    }
+
+    pub fn reset(&mut self) {
+        for value in self.h.iter_mut().chain(self.x.iter_mut()) {
+            *value = 0.0;
+        }
+        self.buffer_pos = 0;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_sign_sign_lms_iterations() {
+        let h_expected: [[f32; 3]; 6] = [
+            [0.0, 0.0, 0.0],
+            [0.5, 0.0, 0.0],
+            [1.0, 0.5, 0.0],
+            [1.5, 1.0, 0.5],
+            [1.0, 0.5, 0.0],
+            [1.0, 0.5, 0.0],
+        ];
+        let mut filter = SignSignLms::new(3, 0.5);
+        let x: [f32; 6] = [0.0, 1.0, 2.0, 3.0, 4.0, 5.0];
+        let d: [f32; 6] = [1.0, 3.0, 4.0, 8.0, 9.0, 7.0];
+        for (i, (x, d)) in x.iter().zip(d.iter()).enumerate() {
+            filter.update(*x, *d);
+            for (h, h_expected) in filter.h().iter().zip(h_expected[i].iter()) {
+                assert_eq!(*h, *h_expected);
+            }
+        }
+        filter.reset();
+        assert!(filter.h().iter().all(|h| *h == 0.0));
+    }
+}
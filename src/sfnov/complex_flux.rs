@@ -0,0 +1,155 @@
+use alloc::{boxed::Box, vec};
+use microfft::Complex32;
+use micromath::F32Ext;
+
+use crate::common::{fill_window_function, RealFft, WindowFunctionType};
+
+/// Complex domain novelty, which detects changes in both magnitude and phase.
+/// The complex spectrum of each window is predicted from the two previous
+/// windows by assuming a constant magnitude and a constant phase advance per bin.
+/// The novelty is the summed magnitude of the difference between the predicted
+/// and the actual spectrum. Unlike [`SpectralFlux`](super::SpectralFlux), this
+/// responds to soft onsets without a large change in magnitude.
+// https://www.eecs.qmul.ac.uk/~simond/pub/2003/DAFx03-onsets.pdf
+pub struct ComplexFlux {
+    fft_buffer: Box<[f32]>,
+    fft: RealFft,
+    /// Precomputed window function coefficients.
+    window_coefficients: Box<[f32]>,
+    /// The spectrum of the previous window.
+    spectrum_prev: Box<[Complex32]>,
+    /// The spectrum of the window before the previous window.
+    spectrum_prev_prev: Box<[Complex32]>,
+    novelty: f32,
+    /// The number of processed windows, up to 2.
+    processed_window_count: usize,
+}
+
+impl ComplexFlux {
+    pub fn new(window_size: usize, window_func: WindowFunctionType) -> Self {
+        let mut window_coefficients = vec![0.; window_size].into_boxed_slice();
+        fill_window_function(window_func, &mut window_coefficients);
+        let zero = Complex32::new(0., 0.);
+        ComplexFlux {
+            fft_buffer: vec![0.; window_size].into_boxed_slice(),
+            fft: RealFft::new(window_size),
+            window_coefficients,
+            spectrum_prev: vec![zero; window_size / 2].into_boxed_slice(),
+            spectrum_prev_prev: vec![zero; window_size / 2].into_boxed_slice(),
+            novelty: 0.,
+            processed_window_count: 0,
+        }
+    }
+
+    pub fn novelty(&self) -> f32 {
+        self.novelty
+    }
+
+    /// Returns the complex spectrum of the most recently processed window,
+    /// from DC up to, but not including, the Nyquist frequency.
+    pub fn spectrum(&self) -> &[Complex32] {
+        &self.spectrum_prev
+    }
+
+    pub fn clear(&mut self) {
+        self.processed_window_count = 0;
+        self.novelty = 0.;
+    }
+
+    /// Processes a window. Returns true if a novelty value was computed,
+    /// which requires two previously processed windows.
+    pub fn process_window(&mut self, window: &[f32]) -> bool {
+        for ((value, x), w) in self
+            .fft_buffer
+            .iter_mut()
+            .zip(window)
+            .zip(self.window_coefficients.iter())
+        {
+            *value = x * w;
+        }
+        let window_size = self.fft_buffer.len();
+        let spectrum = self.fft.process(&mut self.fft_buffer);
+        // Clear real-valued coefficient at the Nyquist frequency, which is packed into the
+        // imaginary part of the DC bin.
+        spectrum[0].im = 0.;
+
+        let has_novelty = self.processed_window_count == 2;
+        let mut novelty = 0.;
+        if has_novelty {
+            for ((x, x_prev), x_prev_prev) in spectrum
+                .iter()
+                .zip(self.spectrum_prev.iter())
+                .zip(self.spectrum_prev_prev.iter())
+            {
+                let predicted = predict(*x_prev, *x_prev_prev);
+                novelty += sqrt((x - predicted).norm_sqr());
+            }
+        }
+        self.novelty = novelty / (window_size as f32);
+
+        core::mem::swap(&mut self.spectrum_prev, &mut self.spectrum_prev_prev);
+        self.spectrum_prev.copy_from_slice(spectrum);
+        self.processed_window_count = 2.min(self.processed_window_count + 1);
+
+        has_novelty
+    }
+}
+
+/// Predicts a spectrum bin given its values in the two previous windows,
+/// assuming constant magnitude and phase advance.
+fn predict(x_prev: Complex32, x_prev_prev: Complex32) -> Complex32 {
+    // x_prev * e^(i(arg(x_prev) - arg(x_prev_prev)))
+    let magnitude_product_sqr = x_prev.norm_sqr() * x_prev_prev.norm_sqr();
+    if magnitude_product_sqr == 0. {
+        return x_prev;
+    }
+    let phase_advance = (x_prev * x_prev_prev.conj()) / sqrt(magnitude_product_sqr);
+    x_prev * phase_advance
+}
+
+/// Square root with the approximation error reduced by one Newton iteration.
+fn sqrt(x: f32) -> f32 {
+    let y = F32Ext::sqrt(x);
+    if y > 0. {
+        0.5 * (y + x / y)
+    } else {
+        y
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use alloc::vec::Vec;
+    use core::f32::consts::PI;
+
+    #[test]
+    fn test_phase_jump() {
+        let window_size = 512;
+        let hop_size = 256;
+        // A stationary sine with a phase jump at sample 2048
+        let signal: Vec<f32> = (0..4096)
+            .map(|i| {
+                let phase = if i < 2048 { 0. } else { 0.5 * PI };
+                (2. * PI * 0.05 * (i as f32) + phase).sin()
+            })
+            .collect();
+
+        let mut complex_flux = ComplexFlux::new(window_size, WindowFunctionType::Hann);
+        let mut novelty = Vec::new();
+        for start in (0..=(signal.len() - window_size)).step_by(hop_size) {
+            let window = &signal[start..start + window_size];
+            if complex_flux.process_window(window) {
+                novelty.push((start, complex_flux.novelty()));
+            }
+        }
+        assert!(!novelty.is_empty());
+
+        let (stationary, jump): (Vec<&(usize, f32)>, Vec<_>) = novelty
+            .iter()
+            .partition(|(start, _)| start + window_size <= 2048);
+        let max_stationary = stationary.iter().fold(0., |max: f32, n| max.max(n.1));
+        let max_jump = jump.iter().fold(0., |max: f32, n| max.max(n.1));
+        assert!(max_jump > 10. * max_stationary);
+    }
+}
@@ -0,0 +1,90 @@
+use crate::common::WindowFunctionType;
+use crate::common::{i16_to_f32, WindowProcessor};
+use crate::sfnov::{complex_flux::ComplexFlux, spectral_flux_novelty_detector::last_sample_index};
+
+/// Like [`SpectralFluxNoveltyDetector`](super::SpectralFluxNoveltyDetector),
+/// but computing [`ComplexFlux`] novelty.
+pub struct ComplexFluxNoveltyDetector {
+    window_processor: WindowProcessor,
+    flux: ComplexFlux,
+}
+
+impl ComplexFluxNoveltyDetector {
+    pub fn new(window_size: usize) -> Self {
+        ComplexFluxNoveltyDetector::from_options(
+            WindowFunctionType::Hann,
+            window_size,
+            window_size / 2,
+            1,
+        )
+    }
+
+    /// Creates a new `ComplexFluxNoveltyDetector` instance. The size arguments are
+    /// ordered like those of [`MpmPitchDetector::from_options`](crate::mpm::MpmPitchDetector::from_options).
+    pub fn from_options(
+        window_func: WindowFunctionType,
+        downsampled_window_size: usize,
+        downsampled_hop_size: usize,
+        downsampling: usize,
+    ) -> Self {
+        ComplexFluxNoveltyDetector {
+            window_processor: WindowProcessor::new(
+                downsampled_window_size,
+                downsampled_hop_size,
+                downsampling,
+            ),
+            flux: ComplexFlux::new(downsampled_window_size, window_func),
+        }
+    }
+
+    pub fn reset(&mut self) {
+        self.window_processor.reset();
+        self.flux.clear();
+    }
+
+    pub fn novelty(&self) -> &ComplexFlux {
+        &self.flux
+    }
+
+    /// Returns the number of input samples needed before the next window is analyzed.
+    /// See [`WindowProcessor::samples_until_next_window`].
+    pub fn samples_until_next_window(&self) -> usize {
+        self.window_processor.samples_until_next_window()
+    }
+
+    /// Processes an arbitrarily sized buffer of input samples. Invokes the provided
+    /// handler with the complex flux of each newly analyzed window and the index
+    /// of the last input sample in that window, in the non-downsampled sample domain,
+    /// counting from zero since creation or the last reset.
+    pub fn process<F>(&mut self, buffer: &[f32], handler: F)
+    where
+        F: FnMut(&ComplexFlux, usize),
+    {
+        self.process_with(buffer, |sample| sample, handler)
+    }
+
+    /// Like `process`, but takes 16 bit integer samples, which are converted
+    /// to `f32` samples in the range [-1, 1) as they are fed to the detector.
+    pub fn process_i16<F>(&mut self, buffer: &[i16], handler: F)
+    where
+        F: FnMut(&ComplexFlux, usize),
+    {
+        self.process_with(buffer, i16_to_f32, handler)
+    }
+
+    fn process_with<T, M, F>(&mut self, buffer: &[T], convert: M, mut handler: F)
+    where
+        T: Copy,
+        M: Fn(T) -> f32,
+        F: FnMut(&ComplexFlux, usize),
+    {
+        let sample_index = last_sample_index(&self.window_processor);
+        let flux = &mut self.flux;
+        self.window_processor
+            .process_with(buffer, convert, |window_index, window| {
+                if flux.process_window(window) {
+                    handler(flux, sample_index(window_index))
+                }
+            })
+    }
+}
@@ -1,7 +1,18 @@
+use alloc::boxed::Box;
+
+/// A function mapping normalized power values to compressed values. Object safe,
+/// so that boxed compression functions can be chosen at runtime. See
+/// [`SpectralFluxNoveltyDetector::new_boxed`](crate::sfnov::SpectralFluxNoveltyDetector::new_boxed).
 pub trait CompressionFunction {
     fn compress(&self, input: f32) -> f32;
 }
 
+impl<C: CompressionFunction + ?Sized> CompressionFunction for Box<C> {
+    fn compress(&self, input: f32) -> f32 {
+        (**self).compress(input)
+    }
+}
+
 pub struct QuarticCompression {
     a: f32,
     a_4: f32,
@@ -11,16 +22,23 @@ pub struct QuarticCompression {
 
 impl QuarticCompression {
     pub fn new(a: f32, b: f32) -> Self {
+        let mut instance = QuarticCompression {
+            a: 0.,
+            a_4: 0.,
+            b_m_a: 0.,
+            scale: 0.,
+        };
+        instance.set(a, b);
+        instance
+    }
+
+    pub fn set(&mut self, a: f32, b: f32) {
         let a_4 = a * a * a * a;
         let b_4 = b * b * b * b;
-        let b_m_a = b - a;
-        let scale = 1. / (a_4 - b_4);
-        QuarticCompression {
-            a,
-            a_4,
-            b_m_a,
-            scale,
-        }
+        self.a = a;
+        self.a_4 = a_4;
+        self.b_m_a = b - a;
+        self.scale = 1. / (a_4 - b_4);
     }
 }
 
@@ -87,6 +105,7 @@ impl CompressionFunction for HardKneeCompression {
 #[cfg(test)]
 mod tests {
     use crate::sfnov::compression_function::{CompressionFunction, QuarticCompression};
+    use alloc::boxed::Box;
 
     #[test]
     fn test_quartic_compression() {
@@ -97,4 +116,18 @@ mod tests {
         assert_eq!(c_1, 1.);
         assert_eq!(function.compress(0.6), 0.9382239);
     }
+
+    #[test]
+    fn test_quartic_compression_set() {
+        let mut function = QuarticCompression::new(-12., -2.);
+        function.set(-24., -4.);
+        assert_eq!(function.compress(0.6), 0.9382239);
+    }
+
+    #[test]
+    fn test_boxed_compression() {
+        let function = QuarticCompression::new(-24., -4.);
+        let boxed: Box<dyn CompressionFunction> = Box::new(QuarticCompression::new(-24., -4.));
+        assert_eq!(boxed.compress(0.6), function.compress(0.6));
+    }
 }
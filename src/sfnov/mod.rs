@@ -1,10 +1,15 @@
 //! [Audio onset detection](https://en.wikipedia.org/wiki/Onset_(audio)) using
 //! [spectral flux novelty](https://krishnasubramani.web.illinois.edu/data/Energy-Weighted%20Multi-Band%20Novelty%20Functions%20for%20Onset%20Detection%20in%20Piano%20Music.pdf).
 //!
+mod complex_flux;
+mod complex_flux_novelty_detector;
 mod compression_function;
+mod onset_picker;
 mod spectral_flux;
 mod spectral_flux_novelty_detector;
 
+pub use complex_flux::ComplexFlux;
+pub use complex_flux_novelty_detector::ComplexFluxNoveltyDetector;
 pub use compression_function::{CompressionFunction, HardKneeCompression, QuarticCompression};
-pub use spectral_flux::SpectralFlux;
+pub use spectral_flux::{spectral_flux_between, SpectralFlux};
 pub use spectral_flux_novelty_detector::SpectralFluxNoveltyDetector;
@@ -0,0 +1,106 @@
+use crate::common::parabolic_peak;
+use alloc::{vec, vec::Vec};
+
+/// Picks onsets from a stream of novelty values. A novelty value is
+/// considered an onset if it is a local maximum and exceeds the mean
+/// plus a number of standard deviations of the most recent values.
+pub(crate) struct OnsetPicker {
+    /// Ring buffer of the most recent novelty values.
+    history: Vec<f32>,
+    /// The ring buffer index of the most recent novelty value.
+    position: usize,
+    /// The number of values in the history buffer.
+    count: usize,
+    /// The number of standard deviations above the mean an onset must exceed.
+    threshold: f32,
+    /// The most recent novelty value.
+    prev: f32,
+    /// The second most recent novelty value.
+    prev_prev: f32,
+}
+
+impl OnsetPicker {
+    pub(crate) fn new(window_length: usize, threshold: f32) -> Self {
+        if window_length == 0 {
+            panic!("Onset window length must be greater than 0")
+        }
+        OnsetPicker {
+            history: vec![0.; window_length],
+            position: 0,
+            count: 0,
+            threshold,
+            prev: 0.,
+            prev_prev: 0.,
+        }
+    }
+
+    pub(crate) fn threshold(&self) -> f32 {
+        self.threshold
+    }
+
+    pub(crate) fn set_threshold(&mut self, threshold: f32) {
+        self.threshold = threshold;
+    }
+
+    pub(crate) fn window_length(&self) -> usize {
+        self.history.len()
+    }
+
+    pub(crate) fn reset(&mut self) {
+        for value in self.history.iter_mut() {
+            *value = 0.;
+        }
+        self.position = 0;
+        self.count = 0;
+        self.prev = 0.;
+        self.prev_prev = 0.;
+    }
+
+    /// Adds a novelty value. If the previous novelty value was an onset, returns
+    /// the interpolated position of the novelty peak relative to the previous
+    /// value, in windows, in the range [-0.5, 0.5].
+    pub(crate) fn process(&mut self, novelty: f32) -> Option<f32> {
+        let len = self.history.len();
+        self.position = (self.position + 1) % len;
+        self.history[self.position] = novelty;
+        self.count = len.min(self.count + 1);
+
+        let candidate = self.prev;
+        let is_local_max = candidate > self.prev_prev && candidate >= novelty;
+        let (peak_offset, _) = parabolic_peak(self.prev_prev, candidate, novelty);
+        self.prev_prev = self.prev;
+        self.prev = novelty;
+        if !is_local_max {
+            return None;
+        }
+
+        let values = &self.history[..self.count];
+        let mean = values.iter().sum::<f32>() / (self.count as f32);
+        let variance =
+            values.iter().map(|v| (v - mean) * (v - mean)).sum::<f32>() / (self.count as f32);
+        // Compare squared deviations to avoid computing the standard deviation
+        let deviation = candidate - mean;
+        if deviation > 0. && deviation * deviation > self.threshold * self.threshold * variance {
+            Some(peak_offset)
+        } else {
+            None
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_peak_offset() {
+        let mut picker = OnsetPicker::new(8, 1.0);
+        // Samples of y = 2 - (x - 0.25)^2 around the peak
+        for novelty in [0., 0., 0., 0.4375, 1.9375] {
+            assert_eq!(picker.process(novelty), None);
+        }
+        let peak_offset = picker.process(1.4375).unwrap();
+        assert!((peak_offset - 0.25).abs() <= f32::EPSILON);
+        assert_eq!(picker.process(0.), None);
+    }
+}
@@ -1,7 +1,8 @@
 use alloc::{boxed::Box, vec};
+use core::iter::once;
 
 use crate::{
-    common::{real_fft, apply_window_function},
+    common::{fill_window_function, RealFft, WindowFunctionType},
     sfnov::compression_function::CompressionFunction,
 };
 
@@ -9,8 +10,25 @@ use crate::{
 pub struct SpectralFlux {
     power_0: Box<[f32]>,
     power_1: Box<[f32]>,
+    /// Uncompressed power spectrum of the most recent window.
+    power_linear: Box<[f32]>,
     d_power: Box<[f32]>,
+    window_func: WindowFunctionType,
+    /// Precomputed window function coefficients.
+    window_coefficients: Box<[f32]>,
+    fft: RealFft,
+    /// Power spectrum bin indices separating the frequency bands.
+    band_edges: Box<[usize]>,
     novelty: f32,
+    /// Novelty with the moving average subtracted, half-wave rectified.
+    novelty_normalized: f32,
+    /// Most recent novelty values, used to compute the moving average.
+    /// Empty if normalization is disabled.
+    novelty_history: Box<[f32]>,
+    /// The index of the most recent value in `novelty_history`.
+    novelty_history_pos: usize,
+    /// The number of values in `novelty_history`.
+    novelty_history_count: usize,
     prev_is_1: bool,
     has_processed_second_window: bool,
 }
@@ -18,62 +36,185 @@ pub struct SpectralFlux {
 struct AllocatedBuffers {
     power_0: Box<[f32]>,
     power_1: Box<[f32]>,
+    power_linear: Box<[f32]>,
     d_power: Box<[f32]>,
+    window_coefficients: Box<[f32]>,
 }
 
 impl AllocatedBuffers {
-    fn new(window_size: usize) -> Self {
+    fn new(window_size: usize, window_func: WindowFunctionType) -> Self {
+        let mut window_coefficients = vec![0.; window_size].into_boxed_slice();
+        fill_window_function(window_func, &mut window_coefficients);
         AllocatedBuffers {
             power_0: vec![0.; window_size / 2].into_boxed_slice(),
             power_1: vec![0.; window_size / 2].into_boxed_slice(),
+            power_linear: vec![0.; window_size / 2].into_boxed_slice(),
             d_power: vec![0.; window_size].into_boxed_slice(),
+            window_coefficients,
         }
     }
 }
 
 impl SpectralFlux {
-    pub fn new(window_size: usize) -> Self {
-        let buffers = AllocatedBuffers::new(window_size);
+    pub fn new(window_size: usize, window_func: WindowFunctionType) -> Self {
+        SpectralFlux::from_options(window_size, window_func, &[])
+    }
+
+    /// Creates a new `SpectralFlux` instance computing energy weighted multi-band novelty.
+    /// The half-wave rectified flux is computed for each band, weighted by the
+    /// energy of the band relative to the total energy and summed.
+    /// # Arguments
+    ///
+    /// * `window_size` - The window size.
+    /// * `window_func` - The window function to apply before computing the power spectrum.
+    /// * `band_edges` - Increasing power spectrum bin indices, in the range (0, window_size / 2), where one band ends and the next begins. An empty slice gives a single band spanning the entire spectrum.
+    pub fn from_options(
+        window_size: usize,
+        window_func: WindowFunctionType,
+        band_edges: &[usize],
+    ) -> Self {
+        validate_band_edges(band_edges, window_size);
+        let buffers = AllocatedBuffers::new(window_size, window_func);
         SpectralFlux {
             power_0: buffers.power_0,
             power_1: buffers.power_1,
+            power_linear: buffers.power_linear,
             d_power: buffers.d_power,
+            window_func,
+            window_coefficients: buffers.window_coefficients,
+            fft: RealFft::new(window_size),
+            band_edges: band_edges.into(),
             novelty: 0.,
+            novelty_normalized: 0.,
+            novelty_history: Box::new([]),
+            novelty_history_pos: 0,
+            novelty_history_count: 0,
             prev_is_1: true,
             has_processed_second_window: false,
         }
     }
 
     pub fn reallocate(&mut self, window_size: usize) {
-        let buffers = AllocatedBuffers::new(window_size);
+        validate_band_edges(&self.band_edges, window_size);
+        let buffers = AllocatedBuffers::new(window_size, self.window_func);
         self.power_0 = buffers.power_0;
         self.power_1 = buffers.power_1;
+        self.power_linear = buffers.power_linear;
         self.d_power = buffers.d_power;
+        self.window_coefficients = buffers.window_coefficients;
+        self.fft = RealFft::new(window_size);
+    }
+
+    pub fn window_function(&self) -> WindowFunctionType {
+        self.window_func
+    }
+
+    /// Sets the window function applied before computing the power spectrum.
+    pub fn set_window_function(&mut self, window_func: WindowFunctionType) {
+        self.window_func = window_func;
+        fill_window_function(window_func, &mut self.window_coefficients);
+    }
+
+    pub fn band_edges(&self) -> &[usize] {
+        &self.band_edges
     }
 
     pub fn novelty(&self) -> f32 {
         self.novelty
     }
 
+    /// Returns the novelty minus the moving average of the most recent novelty
+    /// values, half-wave rectified. This removes the slowly varying baseline
+    /// of the novelty function. Equal to [`SpectralFlux::novelty`] if
+    /// the moving average length is 0.
+    pub fn novelty_normalized(&self) -> f32 {
+        self.novelty_normalized
+    }
+
+    /// Sets the number of novelty values to compute the moving average used by
+    /// [`SpectralFlux::novelty_normalized`] over. 0, the default, disables normalization.
+    pub fn set_moving_average_length(&mut self, window_count: usize) {
+        self.novelty_history = vec![0.; window_count].into_boxed_slice();
+        self.novelty_history_pos = 0;
+        self.novelty_history_count = 0;
+        self.novelty_normalized = self.novelty;
+    }
+
+    pub fn moving_average_length(&self) -> usize {
+        self.novelty_history.len()
+    }
+
     pub fn clear(&mut self) {
         self.prev_is_1 = true;
         self.has_processed_second_window = false;
         self.novelty = 0.;
+        self.novelty_normalized = 0.;
+        self.novelty_history_pos = 0;
+        self.novelty_history_count = 0;
     }
 
+    /// Returns the compressed power spectrum of the most recent window.
     pub fn power_spectrum(&self) -> &[f32] {
+        // prev_is_1 is toggled after processing a window
         if self.prev_is_1 {
+            &self.power_1
+        } else {
             &self.power_0
+        }
+    }
+
+    /// Returns the power spectrum of the most recent window before compression.
+    pub fn power_spectrum_linear(&self) -> &[f32] {
+        &self.power_linear
+    }
+
+    /// Returns the center frequency in Hz of a power spectrum bin, e.g for
+    /// labeling the axis of a plot of `power_spectrum`. `sample_rate` is the sample rate
+    /// of the analyzed windows, which for downsampled input is the effective sample rate.
+    pub fn bin_frequency(&self, bin: usize, sample_rate: f32) -> f32 {
+        (bin as f32) * sample_rate / (self.d_power.len() as f32)
+    }
+
+    /// Returns the [spectral centroid](https://en.wikipedia.org/wiki/Spectral_centroid) in Hz
+    /// of the most recent window, i.e the power weighted mean frequency,
+    /// computed from the uncompressed power spectrum. 0 for silent windows.
+    pub fn spectral_centroid(&self, sample_rate: f32) -> f32 {
+        let bin_width = sample_rate / (self.d_power.len() as f32);
+        let mut weighted_sum = 0.;
+        let mut total_power = 0.;
+        for (k, power) in self.power_linear.iter().enumerate() {
+            weighted_sum += (k as f32) * power;
+            total_power += power;
+        }
+        if total_power > 0. {
+            bin_width * weighted_sum / total_power
         } else {
-            &self.power_1
+            0.
+        }
+    }
+
+    /// Returns the spectral rolloff in Hz of the most recent window, i.e the frequency
+    /// of the lowest bin below which a given fraction, for example 0.85, of the total power
+    /// is contained. Computed from the uncompressed power spectrum. 0 for silent windows.
+    pub fn spectral_rolloff(&self, sample_rate: f32, fraction: f32) -> f32 {
+        let bin_width = sample_rate / (self.d_power.len() as f32);
+        let total_power: f32 = self.power_linear.iter().sum();
+        let threshold = fraction * total_power;
+        let mut cumulative_power = 0.;
+        for (k, power) in self.power_linear.iter().enumerate() {
+            cumulative_power += power;
+            if cumulative_power >= threshold && total_power > 0. {
+                return bin_width * (k as f32);
+            }
         }
+        0.
     }
 
     pub fn power_spectrum_prev(&self) -> &[f32] {
         if self.prev_is_1 {
-            &self.power_1
-        } else {
             &self.power_0
+        } else {
+            &self.power_1
         }
     }
 
@@ -81,10 +222,9 @@ impl SpectralFlux {
         &self.d_power
     }
 
-    pub fn process_window<C: CompressionFunction>(
+    pub fn process_window<C: CompressionFunction + ?Sized>(
         &mut self,
         window: &[f32],
-        window_func: crate::common::WindowFunctionType,
         compression_func: &C,
     ) -> bool {
         let (power, power_prev) = if self.prev_is_1 {
@@ -97,18 +237,24 @@ impl SpectralFlux {
             self.has_processed_second_window = true;
         }
 
-        self.d_power.copy_from_slice(window);
-        apply_window_function(window_func, &mut self.d_power);
-        let fft = real_fft(&mut self.d_power);
-        // Clear real-valued coefficient at the Nyquist frequency, which is packed into the
-        // imaginary part of the DC bin.
-        fft[0].im = 0.;
-
-        for (power, z) in power.iter_mut().zip(fft) {
+        for ((value, x), w) in self
+            .d_power
+            .iter_mut()
+            .zip(window)
+            .zip(self.window_coefficients.iter())
+        {
+            *value = x * w;
+        }
+        for ((power, power_linear), p) in power
+            .iter_mut()
+            .zip(self.power_linear.iter_mut())
+            .zip(self.fft.power_spectrum(&mut self.d_power))
+        {
+            *power_linear = *p;
             // magnitude is compressed in https://www.audiolabs-erlangen.de/resources/MIR/FMP/C6/C6S1_NoveltySpectral.html
             // TODO: should be compressing the norm as opposed to the norm squared if
             //       an efficient approximation can be found.
-            *power = compression_func.compress(z.norm_sqr());
+            *power = compression_func.compress(*p);
         }
 
         let mut novelty = 0.;
@@ -121,9 +267,258 @@ impl SpectralFlux {
                     novelty += delta;
                 }
             }
+
+            if !self.band_edges.is_empty() {
+                novelty = 0.;
+                let total_energy: f32 = power.iter().sum();
+                if total_energy > 0. {
+                    let mut band_start = 0;
+                    for band_end in self.band_edges.iter().copied().chain(once(power.len())) {
+                        let band_energy: f32 = power[band_start..band_end].iter().sum();
+                        let band_flux: f32 = self.d_power[band_start..band_end]
+                            .iter()
+                            .filter(|delta| **delta > 0.)
+                            .sum();
+                        novelty += band_energy / total_energy * band_flux;
+                        band_start = band_end;
+                    }
+                }
+            }
         }
         self.novelty = novelty / (self.d_power.len() as f32);
+        self.novelty_normalized = self.novelty;
+        let history_len = self.novelty_history.len();
+        if history_len > 0 && self.has_processed_second_window {
+            self.novelty_history_pos = (self.novelty_history_pos + 1) % history_len;
+            self.novelty_history[self.novelty_history_pos] = self.novelty;
+            self.novelty_history_count = history_len.min(self.novelty_history_count + 1);
+            let mean = self.novelty_history[..self.novelty_history_count]
+                .iter()
+                .sum::<f32>()
+                / (self.novelty_history_count as f32);
+            self.novelty_normalized = (self.novelty - mean).max(0.);
+        }
         self.prev_is_1 = !self.prev_is_1;
         self.has_processed_second_window
     }
-}
\ No newline at end of file
+}
+
+/// Computes the spectral flux novelty between two windows without keeping any state,
+/// e.g for processing independent chunks of a file in parallel. Takes the uncompressed
+/// power spectra of the previous and current window, as returned by
+/// [`power_spectrum`](crate::common::power_spectrum) after applying a window function.
+/// Returns the same value as [`SpectralFlux::novelty`] would for the current
+/// window, using a single band.
+pub fn spectral_flux_between<C: CompressionFunction + ?Sized>(
+    power_prev: &[f32],
+    power_curr: &[f32],
+    compression_func: &C,
+) -> f32 {
+    if power_prev.len() != power_curr.len() {
+        panic!("Power spectra must have the same length")
+    }
+    let mut novelty = 0.;
+    for (prev, curr) in power_prev.iter().zip(power_curr.iter()) {
+        let delta = compression_func.compress(*curr) - compression_func.compress(*prev);
+        if delta > 0. {
+            novelty += delta;
+        }
+    }
+    // Normalize by the window size, which is twice the number of bins
+    novelty / ((2 * power_curr.len()) as f32)
+}
+
+fn validate_band_edges(band_edges: &[usize], window_size: usize) {
+    let mut prev_edge = 0;
+    for edge in band_edges.iter() {
+        if *edge <= prev_edge || *edge >= window_size / 2 {
+            panic!(
+                "Spectral flux band edges must be increasing and in the range (0, window_size / 2)"
+            )
+        }
+        prev_edge = *edge;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::common::{apply_window_function, power_spectrum};
+    use crate::sfnov::HardKneeCompression;
+    use alloc::vec;
+    use alloc::vec::Vec;
+
+    fn novelty_values(flux: &mut SpectralFlux, windows: &[Vec<f32>]) -> Vec<f32> {
+        let compression_func = HardKneeCompression::new();
+        windows
+            .iter()
+            .map(|window| {
+                flux.process_window(window, &compression_func);
+                flux.novelty()
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_multi_band_novelty() {
+        let window_size = 256;
+        let sine = |frequency: f32, amplitude: f32| -> Vec<f32> {
+            (0..window_size)
+                .map(|i| amplitude * (frequency * (i as f32)).sin())
+                .collect()
+        };
+        // A low frequency tone, then the same tone with a quiet high frequency onset
+        let low = sine(0.1, 0.5);
+        let low_and_high: Vec<f32> = low
+            .iter()
+            .zip(sine(2.0, 0.05).iter())
+            .map(|(a, b)| a + b)
+            .collect();
+        let windows = [low.clone(), low.clone(), low_and_high];
+
+        let mut single_band = SpectralFlux::new(window_size, WindowFunctionType::Hann);
+        let mut single_band_explicit =
+            SpectralFlux::from_options(window_size, WindowFunctionType::Hann, &[]);
+        let mut multi_band =
+            SpectralFlux::from_options(window_size, WindowFunctionType::Hann, &[16, 64]);
+        assert_eq!(multi_band.band_edges(), &[16, 64]);
+
+        let single_band_novelty = novelty_values(&mut single_band, &windows);
+        assert_eq!(
+            single_band_novelty,
+            novelty_values(&mut single_band_explicit, &windows)
+        );
+        let multi_band_novelty = novelty_values(&mut multi_band, &windows);
+
+        // No change between the first windows
+        assert_eq!(single_band_novelty[1], 0.);
+        assert_eq!(multi_band_novelty[1], 0.);
+        // The quiet high frequency band is weighted down by its low energy
+        assert!(single_band_novelty[2] > 0.);
+        assert!(multi_band_novelty[2] > 0.);
+        assert!(multi_band_novelty[2] < single_band_novelty[2]);
+    }
+
+    #[test]
+    fn test_novelty_normalized() {
+        let window_size = 256;
+        let windows: Vec<Vec<f32>> = (0..20)
+            .map(|i| {
+                // Slowly growing amplitude, with a jump at window 15
+                let amplitude = 0.1 * (i as f32) + if i >= 15 { 2.0 } else { 0.0 };
+                (0..window_size)
+                    .map(|j| amplitude * (0.3 * (j as f32)).sin())
+                    .collect()
+            })
+            .collect();
+
+        let mut flux = SpectralFlux::new(window_size, WindowFunctionType::Hann);
+        let compression_func = HardKneeCompression::new();
+        for window in windows.iter() {
+            flux.process_window(window, &compression_func);
+            assert_eq!(flux.novelty_normalized(), flux.novelty());
+        }
+
+        flux.clear();
+        flux.set_moving_average_length(4);
+        assert_eq!(flux.moving_average_length(), 4);
+        let mut normalized = Vec::new();
+        for window in windows.iter() {
+            flux.process_window(window, &compression_func);
+            assert!(flux.novelty_normalized() >= 0.);
+            assert!(flux.novelty_normalized() <= flux.novelty());
+            normalized.push(flux.novelty_normalized());
+        }
+        // The jump stands out clearly from the slowly increasing baseline
+        for (i, value) in normalized.iter().enumerate() {
+            if i != 15 {
+                assert!(normalized[15] > 10. * value);
+            }
+        }
+    }
+
+    #[test]
+    fn test_power_spectrum_linear() {
+        let window_size = 64;
+        let window: Vec<f32> = (0..window_size).map(|i| (0.5 * i as f32).sin()).collect();
+        let mut flux = SpectralFlux::new(window_size, WindowFunctionType::Hann);
+        let compression_func = HardKneeCompression::new();
+        flux.process_window(&window, &compression_func);
+
+        let mut expected = window.clone();
+        apply_window_function(WindowFunctionType::Hann, &mut expected);
+        let expected = power_spectrum(&mut expected);
+        assert_eq!(flux.power_spectrum_linear(), &expected[..]);
+        for (compressed, linear) in flux
+            .power_spectrum()
+            .iter()
+            .zip(flux.power_spectrum_linear())
+        {
+            assert_eq!(*compressed, compression_func.compress(*linear));
+        }
+    }
+
+    #[test]
+    fn test_spectral_centroid_and_rolloff() {
+        let sample_rate = 44100.;
+        let window_size = 1024;
+        let bin_width = sample_rate / (window_size as f32);
+        let mut flux = SpectralFlux::new(window_size, WindowFunctionType::Hann);
+        let compression_func = HardKneeCompression::new();
+
+        flux.process_window(&vec![0.; window_size], &compression_func);
+        assert_eq!(flux.spectral_centroid(sample_rate), 0.);
+        assert_eq!(flux.spectral_rolloff(sample_rate, 0.85), 0.);
+
+        // Two tones at bin 50 and 150, the upper with half the amplitude,
+        // i.e a quarter of the power
+        let window: Vec<f32> = (0..window_size)
+            .map(|i| {
+                let phase = 2. * core::f32::consts::PI * (i as f32) / (window_size as f32);
+                (50. * phase).sin() + 0.5 * (150. * phase).sin()
+            })
+            .collect();
+        flux.process_window(&window, &compression_func);
+        let expected_centroid = bin_width * (50. + 0.25 * 150.) / 1.25;
+        assert!((flux.spectral_centroid(sample_rate) - expected_centroid).abs() < 0.5 * bin_width);
+        assert!((flux.spectral_rolloff(sample_rate, 0.5) - 50. * bin_width).abs() <= bin_width);
+        assert!((flux.spectral_rolloff(sample_rate, 0.95) - 150. * bin_width).abs() <= bin_width);
+    }
+
+    #[test]
+    fn test_spectral_flux_between() {
+        let window_size = 256;
+        let windows: Vec<Vec<f32>> = (0..4)
+            .map(|i| {
+                (0..window_size)
+                    .map(|j| ((i + 1) as f32) * (0.05 * ((i + 1) * j) as f32).sin())
+                    .collect()
+            })
+            .collect();
+        let compression_func = HardKneeCompression::new();
+        let power_spectra: Vec<Vec<f32>> = windows
+            .iter()
+            .map(|window| {
+                let mut buffer = window.clone();
+                apply_window_function(WindowFunctionType::Hann, &mut buffer);
+                power_spectrum(&mut buffer).to_vec()
+            })
+            .collect();
+
+        let mut flux = SpectralFlux::new(window_size, WindowFunctionType::Hann);
+        flux.process_window(&windows[0], &compression_func);
+        for i in 1..windows.len() {
+            flux.process_window(&windows[i], &compression_func);
+            let novelty =
+                spectral_flux_between(&power_spectra[i - 1], &power_spectra[i], &compression_func);
+            assert!(novelty > 0.);
+            assert!((novelty - flux.novelty()).abs() <= 1e-6 * novelty);
+        }
+    }
+
+    #[test]
+    #[should_panic]
+    fn test_invalid_band_edges() {
+        SpectralFlux::from_options(256, WindowFunctionType::Hann, &[64, 16]);
+    }
+}
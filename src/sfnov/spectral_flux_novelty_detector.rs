@@ -1,35 +1,97 @@
+use alloc::{boxed::Box, vec::Vec};
+
 use crate::common::WindowFunctionType;
-use crate::common::WindowProcessor;
+use crate::common::{downmix_interleaved, i16_to_f32, RealFft, WindowProcessor};
 use crate::sfnov::{
     compression_function::{CompressionFunction, HardKneeCompression},
+    onset_picker::OnsetPicker,
     spectral_flux::SpectralFlux,
 };
+use crate::Error;
+
+const DEFAULT_ONSET_THRESHOLD: f32 = 1.5;
+const DEFAULT_ONSET_WINDOW: usize = 16;
+const PRE_EMPHASIS_CHUNK_SIZE: usize = 256;
 
 pub struct SpectralFluxNoveltyDetector<C: CompressionFunction> {
     window_processor: WindowProcessor,
     flux: SpectralFlux,
-    window_func: WindowFunctionType,
     compression_func: C,
+    onset_picker: OnsetPicker,
+    /// The input sample rate in Hz, if set.
+    sample_rate: Option<f32>,
+    /// The minimum distance in input samples between onsets.
+    min_onset_interval: usize,
+    /// The sample index of the most recently reported onset, if any.
+    last_onset_sample_index: Option<usize>,
+    /// The pre-emphasis filter coefficient. 0 disables the filter.
+    pre_emphasis: f32,
+    /// The most recent input sample, before pre-emphasis.
+    pre_emphasis_state: f32,
 }
 
 impl SpectralFluxNoveltyDetector<HardKneeCompression> {
     pub fn new(window_size: usize) -> Self {
-        SpectralFluxNoveltyDetector {
-            window_processor: WindowProcessor::new(1, window_size, window_size / 2),
-            window_func: WindowFunctionType::Hann,
+        SpectralFluxNoveltyDetector::try_new(window_size)
+            .unwrap_or_else(|error| panic!("{}", error))
+    }
+
+    /// Like `new`, but returns an error instead of panicking if the window size is
+    /// invalid or not a supported FFT size.
+    pub fn try_new(window_size: usize) -> Result<Self, Error> {
+        let window_processor = WindowProcessor::try_new(window_size, window_size / 2, 1)?;
+        RealFft::try_new(window_size)?;
+        Ok(SpectralFluxNoveltyDetector {
+            window_processor,
             compression_func: HardKneeCompression::new(),
-            flux: SpectralFlux::new(window_size),
-        }
+            flux: SpectralFlux::new(window_size, WindowFunctionType::Hann),
+            onset_picker: OnsetPicker::new(DEFAULT_ONSET_WINDOW, DEFAULT_ONSET_THRESHOLD),
+            sample_rate: None,
+            min_onset_interval: 0,
+            last_onset_sample_index: None,
+            pre_emphasis: 0.0,
+            pre_emphasis_state: 0.0,
+        })
+    }
+}
+
+impl SpectralFluxNoveltyDetector<Box<dyn CompressionFunction>> {
+    /// Like `new`, but with a boxed compression function, so that detectors with
+    /// different compression functions have the same type. The compression function
+    /// can be replaced at runtime through `compression_function`.
+    pub fn new_boxed(window_size: usize, compression_func: Box<dyn CompressionFunction>) -> Self {
+        SpectralFluxNoveltyDetector::from_options(
+            WindowFunctionType::Hann,
+            compression_func,
+            window_size,
+            window_size / 2,
+            1,
+        )
     }
 }
 
 impl<C: CompressionFunction> SpectralFluxNoveltyDetector<C> {
+    /// Creates a new `SpectralFluxNoveltyDetector` instance. The size arguments are
+    /// ordered like those of [`MpmPitchDetector::from_options`](crate::mpm::MpmPitchDetector::from_options).
+    /// # Arguments
+    ///
+    /// * `window_func` - The window function applied to each window before computing its power spectrum.
+    /// * `compression_func` - The function used to compress the power spectrum.
+    /// * `downsampled_window_size` - The window size _after downsampling_. Must be a supported FFT size.
+    /// * `downsampled_hop_size` - The distance, _after downsampling_, between the start of windows.
+    /// * `downsampling` - The downsampling factor (1 corresponds to no downsampling). Since sizes
+    ///   are given after downsampling, each window spans `downsampling * downsampled_window_size`
+    ///   input samples.
+    ///
+    /// Downsampling keeps every `downsampling`:th input sample without any low pass filtering
+    /// by default, so input content above the downsampled Nyquist frequency aliases into
+    /// the analyzed spectrum. See `set_anti_aliasing_filter`.
     pub fn from_options(
         window_func: WindowFunctionType,
         compression_func: C,
         downsampled_window_size: usize,
-        downsampling: usize,
         downsampled_hop_size: usize,
+        downsampling: usize,
     ) -> Self {
         SpectralFluxNoveltyDetector {
             window_processor: WindowProcessor::new(
@@ -37,36 +99,666 @@ impl<C: CompressionFunction> SpectralFluxNoveltyDetector<C> {
                 downsampled_hop_size,
                 downsampling,
             ),
-            window_func,
             compression_func,
-            flux: SpectralFlux::new(downsampled_window_size),
+            flux: SpectralFlux::new(downsampled_window_size, window_func),
+            onset_picker: OnsetPicker::new(DEFAULT_ONSET_WINDOW, DEFAULT_ONSET_THRESHOLD),
+            sample_rate: None,
+            min_onset_interval: 0,
+            last_onset_sample_index: None,
+            pre_emphasis: 0.0,
+            pre_emphasis_state: 0.0,
         }
     }
 
-    pub fn compression_function(&mut self) -> &C {
+    /// Returns the compression function, e.g for adjusting its parameters.
+    pub fn compression_function(&mut self) -> &mut C {
         &mut self.compression_func
     }
 
     pub fn reset(&mut self) {
         self.window_processor.reset();
-        self.flux.clear()
+        self.flux.clear();
+        self.onset_picker.reset();
+        self.last_onset_sample_index = None;
+        self.pre_emphasis_state = 0.0;
+    }
+
+    /// Enables or disables low pass filtering before downsampling. Disabled by default.
+    /// See [`WindowProcessor::set_anti_aliasing_filter`].
+    pub fn set_anti_aliasing_filter(&mut self, enabled: bool) {
+        self.window_processor.set_anti_aliasing_filter(enabled);
+    }
+
+    /// Returns true if low pass filtering before downsampling is enabled.
+    pub fn anti_aliasing_filter(&self) -> bool {
+        self.window_processor.anti_aliasing_filter()
+    }
+
+    /// Sets the coefficient `a` of the pre-emphasis filter `y[n] = x[n] - a * x[n - 1]`,
+    /// which is applied to input samples before they are windowed. Pre-emphasis boosts
+    /// high frequencies, which increases the novelty of high frequency transients like
+    /// hi-hats and consonants. Must be in the range [0, 1], where values around 0.95 are
+    /// common. Defaults to 0, which disables the filter.
+    pub fn set_pre_emphasis(&mut self, a: f32) {
+        if !(0.0..=1.0).contains(&a) {
+            panic!("Pre-emphasis coefficient must be in the range [0, 1]")
+        }
+        self.pre_emphasis = a;
+    }
+
+    pub fn pre_emphasis(&self) -> f32 {
+        self.pre_emphasis
+    }
+
+    pub fn window_function(&self) -> WindowFunctionType {
+        self.flux.window_function()
+    }
+
+    /// Sets the window function applied to each window before computing its power spectrum.
+    pub fn set_window_function(&mut self, window_func: WindowFunctionType) {
+        self.flux.set_window_function(window_func);
+    }
+
+    /// Sets the number of windows to compute the novelty moving average over.
+    /// The moving average is subtracted from the novelty and the result is
+    /// half-wave rectified and made available through [`SpectralFlux::novelty_normalized`].
+    /// 0, the default, disables normalization.
+    pub fn set_novelty_normalization(&mut self, window_count: usize) {
+        self.flux.set_moving_average_length(window_count);
+    }
+
+    pub fn novelty_normalization(&self) -> usize {
+        self.flux.moving_average_length()
+    }
+
+    /// Sets the onset detection threshold used by [`SpectralFluxNoveltyDetector::process_onsets`],
+    /// i.e the number of standard deviations above the mean of the recent novelty
+    /// values a local novelty maximum must exceed to be considered an onset. Defaults to 1.5.
+    pub fn set_onset_threshold(&mut self, k: f32) {
+        self.onset_picker.set_threshold(k);
+    }
+
+    pub fn onset_threshold(&self) -> f32 {
+        self.onset_picker.threshold()
+    }
+
+    /// Sets the number of novelty values used to compute the onset detection
+    /// threshold mean and standard deviation. Defaults to 16. Resets the onset detection state.
+    pub fn set_onset_window(&mut self, len: usize) {
+        self.onset_picker = OnsetPicker::new(len, self.onset_picker.threshold());
+    }
+
+    pub fn onset_window(&self) -> usize {
+        self.onset_picker.window_length()
+    }
+
+    /// Sets the minimum distance in input samples between onsets picked by
+    /// [`SpectralFluxNoveltyDetector::process_onsets`]. Onsets closer than this
+    /// to the previously reported onset are suppressed, e.g to avoid multiple
+    /// onsets for a single percussive hit. Defaults to 0, i.e no suppression.
+    pub fn set_min_onset_interval(&mut self, samples: usize) {
+        self.min_onset_interval = samples;
+    }
+
+    /// Like `set_min_onset_interval`, but takes the interval in seconds.
+    /// Panics if the sample rate has not been set using `set_sample_rate`.
+    pub fn set_min_onset_interval_seconds(&mut self, seconds: f32) {
+        let sample_rate = match self.sample_rate {
+            Some(sample_rate) => sample_rate,
+            None => panic!("The sample rate must be set to convert seconds to samples"),
+        };
+        self.min_onset_interval = (seconds * sample_rate + 0.5) as usize;
+    }
+
+    pub fn min_onset_interval(&self) -> usize {
+        self.min_onset_interval
     }
 
     pub fn novelty(&self) -> &SpectralFlux {
         &self.flux
     }
 
-    pub fn process<F>(&mut self, buffer: &[f32], mut handler: F)
+    /// Sets the input sample rate in Hz, which is needed to map power spectrum bins
+    /// to frequencies. Not set by default, since novelty detection does not depend on it.
+    pub fn set_sample_rate(&mut self, sample_rate: f32) {
+        self.sample_rate = Some(sample_rate);
+    }
+
+    /// Returns the input sample rate set using `set_sample_rate`, if any.
+    pub fn sample_rate(&self) -> Option<f32> {
+        self.sample_rate
+    }
+
+    /// Returns the sample rate of the analyzed windows, i.e the input
+    /// sample rate divided by the downsampling factor, if the sample rate is set.
+    pub fn effective_sample_rate(&self) -> Option<f32> {
+        self.sample_rate
+            .map(|sample_rate| sample_rate / (self.window_processor.downsampling() as f32))
+    }
+
+    /// Returns the center frequency in Hz of each bin of [`SpectralFlux::power_spectrum`],
+    /// taking downsampling into account. Panics if the sample rate has not been set
+    /// using `set_sample_rate`.
+    pub fn bin_frequencies(&self) -> impl Iterator<Item = f32> + '_ {
+        let sample_rate = match self.effective_sample_rate() {
+            Some(sample_rate) => sample_rate,
+            None => panic!("The sample rate must be set to compute bin frequencies"),
+        };
+        let flux = &self.flux;
+        (0..flux.power_spectrum().len()).map(move |bin| flux.bin_frequency(bin, sample_rate))
+    }
+
+    /// Returns the number of input samples needed before the next window is analyzed.
+    /// See [`WindowProcessor::samples_until_next_window`].
+    pub fn samples_until_next_window(&self) -> usize {
+        self.window_processor.samples_until_next_window()
+    }
+
+    /// Processes an arbitrarily sized buffer of input samples. Invokes the provided
+    /// handler with the spectral flux of each newly analyzed window and the index
+    /// of the last input sample in that window, in the non-downsampled sample domain,
+    /// counting from zero since creation or the last reset.
+    pub fn process<F>(&mut self, buffer: &[f32], handler: F)
+    where
+        F: FnMut(&SpectralFlux, usize),
+    {
+        self.process_with(buffer, |sample| sample, handler)
+    }
+
+    /// Like `process`, but takes 16 bit integer samples, which are converted
+    /// to `f32` samples in the range [-1, 1) as they are fed to the detector.
+    pub fn process_i16<F>(&mut self, buffer: &[i16], handler: F)
+    where
+        F: FnMut(&SpectralFlux, usize),
+    {
+        self.process_with(buffer, i16_to_f32, handler)
+    }
+
+    /// Like `process`, but takes interleaved samples with the given number of channels,
+    /// which are downmixed to mono as they are fed to the detector. The downmix is a simple
+    /// average of the channels of each frame. Sample indices passed to the handler count
+    /// frames rather than interleaved samples. Panics if `channels` is 0 or if the buffer
+    /// length is not a multiple of `channels`.
+    pub fn process_interleaved<F>(&mut self, buffer: &[f32], channels: usize, mut handler: F)
     where
-        F: FnMut(&SpectralFlux),
+        F: FnMut(&SpectralFlux, usize),
     {
+        downmix_interleaved(buffer, channels, |mono| self.process(mono, &mut handler))
+    }
+
+    /// Processes an arbitrarily sized buffer of input samples and picks onsets
+    /// from the resulting novelty values. For each detected onset, invokes the provided
+    /// handler with the index of the last input sample of the window at the novelty peak,
+    /// in the non-downsampled sample domain, and a fractional offset in samples to add
+    /// to that index to get the peak position estimated using parabolic interpolation
+    /// of the novelty values. Onsets are reported one window late, since
+    /// a novelty value must be compared to the next one to be identified as a peak.
+    /// See also `set_min_onset_interval`.
+    pub fn process_onsets<F>(&mut self, buffer: &[f32], mut handler: F)
+    where
+        F: FnMut(usize, f32),
+    {
+        self.process_onsets_with(buffer, |sample_index, peak_offset, _| {
+            handler(sample_index, peak_offset)
+        })
+    }
+
+    /// Picks onsets from an entire buffer and returns the time in seconds of each onset,
+    /// including the interpolated peak offset, and the novelty value at the peak.
+    /// A convenience for offline analysis, e.g of audio files. Panics if the
+    /// sample rate has not been set using `set_sample_rate`.
+    pub fn analyze_onsets(&mut self, buffer: &[f32]) -> Vec<(f64, f32)> {
+        let sample_rate = match self.sample_rate {
+            Some(sample_rate) => sample_rate as f64,
+            None => panic!("The sample rate must be set to compute onset timestamps"),
+        };
+        let mut onsets = Vec::new();
+        self.process_onsets_with(buffer, |sample_index, peak_offset, novelty| {
+            let timestamp = (sample_index as f64 + peak_offset as f64) / sample_rate;
+            onsets.push((timestamp, novelty))
+        });
+        onsets
+    }
+
+    /// Like `process_onsets`, but also passes the novelty value at the peak to the handler.
+    fn process_onsets_with<F>(&mut self, buffer: &[f32], mut handler: F)
+    where
+        F: FnMut(usize, f32, f32),
+    {
+        if self.pre_emphasis == 0.0 {
+            return self.process_onset_windows(buffer, handler);
+        }
+        let mut emphasized = [0.0; PRE_EMPHASIS_CHUNK_SIZE];
+        for chunk in buffer.chunks(PRE_EMPHASIS_CHUNK_SIZE) {
+            let emphasized = &mut emphasized[..chunk.len()];
+            self.apply_pre_emphasis(chunk, |sample| sample, emphasized);
+            self.process_onset_windows(emphasized, &mut handler);
+        }
+    }
+
+    fn process_onset_windows<F>(&mut self, buffer: &[f32], mut handler: F)
+    where
+        F: FnMut(usize, f32, f32),
+    {
+        let onset_picker = &mut self.onset_picker;
+        let sample_index = last_sample_index(&self.window_processor);
+        let samples_per_hop = (self.window_processor.downsampling()
+            * self.window_processor.downsampled_hop_size()) as f32;
+        let flux = &mut self.flux;
+        let compression_func = &self.compression_func;
+        let min_onset_interval = self.min_onset_interval;
+        let last_onset_sample_index = &mut self.last_onset_sample_index;
+        self.window_processor.process_with(
+            buffer,
+            |sample| sample,
+            |window_index, window| {
+                // The novelty of the previous window, which is the peak if an onset is picked.
+                let prev_novelty = flux.novelty();
+                if flux.process_window(window, compression_func) {
+                    if let Some(peak_offset) = onset_picker.process(flux.novelty()) {
+                        let onset_sample_index = sample_index(window_index - 1);
+                        let is_suppressed = matches!(
+                            *last_onset_sample_index,
+                            Some(last) if onset_sample_index - last < min_onset_interval
+                        );
+                        if !is_suppressed {
+                            *last_onset_sample_index = Some(onset_sample_index);
+                            handler(
+                                onset_sample_index,
+                                peak_offset * samples_per_hop,
+                                prev_novelty,
+                            )
+                        }
+                    }
+                }
+            },
+        )
+    }
+
+    fn process_with<T, M, F>(&mut self, buffer: &[T], convert: M, mut handler: F)
+    where
+        T: Copy,
+        M: Fn(T) -> f32,
+        F: FnMut(&SpectralFlux, usize),
+    {
+        if self.pre_emphasis == 0.0 {
+            return self.process_windows(buffer, convert, handler);
+        }
+        let mut emphasized = [0.0; PRE_EMPHASIS_CHUNK_SIZE];
+        for chunk in buffer.chunks(PRE_EMPHASIS_CHUNK_SIZE) {
+            let emphasized = &mut emphasized[..chunk.len()];
+            self.apply_pre_emphasis(chunk, &convert, emphasized);
+            self.process_windows(emphasized, |sample| sample, &mut handler);
+        }
+    }
+
+    /// Writes the converted and pre-emphasized input samples to `result`,
+    /// which must have the same length as `buffer`.
+    fn apply_pre_emphasis<T, M>(&mut self, buffer: &[T], convert: M, result: &mut [f32])
+    where
+        T: Copy,
+        M: Fn(T) -> f32,
+    {
+        for (result, sample) in result.iter_mut().zip(buffer) {
+            let sample = convert(*sample);
+            *result = sample - self.pre_emphasis * self.pre_emphasis_state;
+            self.pre_emphasis_state = sample;
+        }
+    }
+
+    fn process_windows<T, M, F>(&mut self, buffer: &[T], convert: M, mut handler: F)
+    where
+        T: Copy,
+        M: Fn(T) -> f32,
+        F: FnMut(&SpectralFlux, usize),
+    {
+        let sample_index = last_sample_index(&self.window_processor);
         let flux = &mut self.flux;
-        let window_func = self.window_func;
         let compression_func = &self.compression_func;
-        self.window_processor.process(buffer, |window| {
-            if flux.process_window(window, window_func, compression_func) {
-                handler(&flux)
+        self.window_processor
+            .process_with(buffer, convert, |window_index, window| {
+                if flux.process_window(window, compression_func) {
+                    handler(&flux, sample_index(window_index))
+                }
+            })
+    }
+}
+
+/// Returns a function mapping a window index to the index of the last
+/// input sample in that window, in the non-downsampled sample domain.
+pub(crate) fn last_sample_index(window_processor: &WindowProcessor) -> impl Fn(usize) -> usize {
+    let downsampling = window_processor.downsampling();
+    let downsampled_window_size = window_processor.downsampled_window_size();
+    let downsampled_hop_size = window_processor.downsampled_hop_size();
+    move |window_index| {
+        downsampling * (downsampled_window_size - 1 + window_index * downsampled_hop_size)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::sfnov::QuarticCompression;
+    use alloc::vec;
+    use alloc::vec::Vec;
+    use rand::{rngs::StdRng, Rng, SeedableRng};
+
+    #[test]
+    fn test_try_new() {
+        assert!(SpectralFluxNoveltyDetector::try_new(1024).is_ok());
+        assert_eq!(
+            SpectralFluxNoveltyDetector::try_new(0).err(),
+            Some(Error::InvalidWindowSize(0))
+        );
+        assert_eq!(
+            SpectralFluxNoveltyDetector::try_new(1000).err(),
+            Some(Error::UnsupportedFftSize(1000))
+        );
+    }
+
+    #[test]
+    fn test_downsampled_window_size() {
+        let downsampling = 4;
+        let mut detector = SpectralFluxNoveltyDetector::from_options(
+            WindowFunctionType::Hann,
+            HardKneeCompression::new(),
+            256,
+            128,
+            downsampling,
+        );
+        detector.set_anti_aliasing_filter(true);
+        assert!(detector.anti_aliasing_filter());
+        assert_eq!(detector.window_processor.downsampled_window_size(), 256);
+        assert_eq!(detector.window_processor.downsampled_hop_size(), 128);
+        assert_eq!(detector.samples_until_next_window(), downsampling * 255 + 1);
+
+        let signal = vec![0.0; 4096];
+        let mut sample_indices = Vec::new();
+        detector.process(&signal, |flux, sample_index| {
+            assert_eq!(flux.power_spectrum().len(), 128);
+            sample_indices.push(sample_index);
+        });
+        // The first window only provides the previous power spectrum
+        assert_eq!(sample_indices[0], downsampling * (255 + 128));
+        assert_eq!(sample_indices[1], downsampling * (255 + 2 * 128));
+    }
+
+    #[test]
+    fn test_bin_frequencies() {
+        let mut detector = SpectralFluxNoveltyDetector::from_options(
+            WindowFunctionType::Hann,
+            HardKneeCompression::new(),
+            256,
+            128,
+            2,
+        );
+        assert_eq!(detector.effective_sample_rate(), None);
+        detector.set_sample_rate(48000.0);
+        assert_eq!(detector.effective_sample_rate(), Some(24000.0));
+        let frequencies: Vec<f32> = detector.bin_frequencies().collect();
+        assert_eq!(frequencies.len(), detector.novelty().power_spectrum().len());
+        assert_eq!(frequencies[0], 0.0);
+        assert_eq!(frequencies[1], 24000.0 / 256.0);
+        assert_eq!(frequencies[127], 127.0 * 24000.0 / 256.0);
+    }
+
+    #[test]
+    #[should_panic]
+    fn test_bin_frequencies_without_sample_rate() {
+        let detector = SpectralFluxNoveltyDetector::new(256);
+        detector.bin_frequencies().count();
+    }
+
+    #[test]
+    fn test_process_interleaved() {
+        let window_size = 256;
+        let mut rng = StdRng::seed_from_u64(123);
+        let signal: Vec<f32> = (0..8 * window_size)
+            .map(|_| rng.gen_range(-1.0..=1.0))
+            .collect();
+        let interleaved: Vec<f32> = signal
+            .iter()
+            .flat_map(|sample| [*sample, *sample, *sample])
+            .collect();
+
+        let mut detector = SpectralFluxNoveltyDetector::new(window_size);
+        let mut expected = Vec::new();
+        detector.process(&signal, |flux, sample_index| {
+            expected.push((flux.novelty(), sample_index))
+        });
+        let mut detector = SpectralFluxNoveltyDetector::new(window_size);
+        let mut novelties = Vec::new();
+        detector.process_interleaved(&interleaved, 3, |flux, sample_index| {
+            novelties.push((flux.novelty(), sample_index))
+        });
+        assert_eq!(novelties, expected);
+    }
+
+    #[test]
+    fn test_boxed_compression_function() {
+        let window_size = 512;
+        let mut rng = StdRng::seed_from_u64(123);
+        let signal: Vec<f32> = (0..8 * window_size)
+            .map(|_| rng.gen_range(-1.0..=1.0))
+            .collect();
+
+        // Detectors with different compression functions can be stored together
+        let mut detectors = vec![
+            SpectralFluxNoveltyDetector::new_boxed(
+                window_size,
+                Box::new(HardKneeCompression::new()),
+            ),
+            SpectralFluxNoveltyDetector::new_boxed(
+                window_size,
+                Box::new(QuarticCompression::new(-24., -4.)),
+            ),
+        ];
+        let mut unboxed = SpectralFluxNoveltyDetector::new(window_size);
+        let mut expected = Vec::new();
+        unboxed.process(&signal, |flux, _| expected.push(flux.novelty()));
+        let mut novelties = Vec::new();
+        detectors[0].process(&signal, |flux, _| novelties.push(flux.novelty()));
+        assert_eq!(novelties, expected);
+
+        // The compression function can be swapped
+        *detectors[1].compression_function() = Box::new(HardKneeCompression::new());
+        novelties.clear();
+        detectors[1].process(&signal, |flux, _| novelties.push(flux.novelty()));
+        assert_eq!(novelties, expected);
+    }
+
+    #[test]
+    fn test_process_onsets() {
+        let window_size = 512;
+        let onset_positions = [4000, 12000, 20000, 28000];
+        let mut rng = StdRng::seed_from_u64(123);
+        let mut signal: Vec<f32> = (0..32000)
+            .map(|_| 0.001 * rng.gen_range(-1.0..=1.0))
+            .collect();
+        for position in onset_positions {
+            // Exponentially decaying noise burst
+            let mut gain: f32 = 1.;
+            for sample in signal[position..position + 4000].iter_mut() {
+                *sample += gain * rng.gen_range(-1.0..=1.0);
+                gain *= 0.999;
             }
-        })
+        }
+
+        let mut detector = SpectralFluxNoveltyDetector::new(window_size);
+        let mut onsets = Vec::new();
+        detector.process_onsets(&signal, |sample_index, offset| {
+            onsets.push((sample_index, offset))
+        });
+        assert_eq!(onsets.len(), onset_positions.len());
+        let hop_size = (window_size / 2) as f32;
+        for ((onset, offset), position) in onsets.iter().zip(onset_positions.iter()) {
+            assert!(*onset >= *position);
+            assert!(*onset < position + window_size);
+            assert!(offset.abs() <= 0.5 * hop_size);
+        }
+
+        // analyze_onsets reports the same onsets in seconds
+        let sample_rate = 16000.0;
+        let mut detector = SpectralFluxNoveltyDetector::new(window_size);
+        detector.set_sample_rate(sample_rate);
+        let timestamped_onsets = detector.analyze_onsets(&signal);
+        assert_eq!(timestamped_onsets.len(), onsets.len());
+        for ((timestamp, novelty), (onset, offset)) in timestamped_onsets.iter().zip(onsets.iter())
+        {
+            let expected = (*onset as f64 + *offset as f64) / (sample_rate as f64);
+            assert!((timestamp - expected).abs() < 1e-9);
+            assert!(*novelty > 0.0);
+        }
+        let mut detector = SpectralFluxNoveltyDetector::new(window_size);
+        detector.set_sample_rate(sample_rate);
+        let chunked_onsets: Vec<(f64, f32)> = signal
+            .chunks(window_size / 2)
+            .flat_map(|chunk| detector.analyze_onsets(chunk))
+            .collect();
+        assert_eq!(chunked_onsets, timestamped_onsets);
+
+        // Onsets within the minimum interval of the previously reported onset are suppressed
+        let mut detector = SpectralFluxNoveltyDetector::new(window_size);
+        detector.set_sample_rate(sample_rate);
+        detector.set_min_onset_interval_seconds(0.625);
+        assert_eq!(detector.min_onset_interval(), 10000);
+        let mut debounced_onsets = Vec::new();
+        detector.process_onsets(&signal, |sample_index, offset| {
+            debounced_onsets.push((sample_index, offset))
+        });
+        assert_eq!(debounced_onsets, vec![onsets[0], onsets[2]]);
+
+        // Reset clears the previous onset
+        detector.reset();
+        debounced_onsets.clear();
+        detector.process_onsets(&signal[..8000], |sample_index, offset| {
+            debounced_onsets.push((sample_index, offset))
+        });
+        assert_eq!(debounced_onsets, vec![onsets[0]]);
+    }
+
+    #[test]
+    fn test_set_window_function() {
+        let window_size = 256;
+        let signal: Vec<f32> = (0..2048)
+            .map(|i| if i < 1024 { 0. } else { (0.3 * i as f32).sin() })
+            .collect();
+        let novelty_values = |detector: &mut SpectralFluxNoveltyDetector<_>| {
+            let mut values = Vec::new();
+            detector.process(&signal, |flux, _| values.push(flux.novelty()));
+            values
+        };
+
+        let mut detector = SpectralFluxNoveltyDetector::new(window_size);
+        assert_eq!(detector.window_function(), WindowFunctionType::Hann);
+        let hann = novelty_values(&mut detector);
+
+        detector.reset();
+        detector.set_window_function(WindowFunctionType::Blackman);
+        assert_eq!(detector.window_function(), WindowFunctionType::Blackman);
+        assert_ne!(novelty_values(&mut detector), hann);
+
+        detector.reset();
+        detector.set_window_function(WindowFunctionType::Hann);
+        assert_eq!(novelty_values(&mut detector), hann);
+    }
+
+    #[test]
+    fn test_pre_emphasis() {
+        let window_size = 512;
+        // A low frequency sine with a short high frequency click
+        let click_range = 4000..4016;
+        let mut signal: Vec<f32> = (0..8 * window_size)
+            .map(|i| 0.5 * (2.0 * core::f32::consts::PI * (i as f32) / 400.0).sin())
+            .collect();
+        for (i, sample) in signal[click_range.clone()].iter_mut().enumerate() {
+            *sample += if i % 2 == 0 { 0.05 } else { -0.05 };
+        }
+        // Returns the ratio of the novelty of the window containing the click
+        // to the highest novelty of the other windows
+        let click_novelty_ratio = |detector: &mut SpectralFluxNoveltyDetector<
+            HardKneeCompression,
+        >| {
+            let mut click_novelty: f32 = 0.0;
+            let mut max_novelty: f32 = 0.0;
+            detector.process(&signal, |flux, sample_index| {
+                if sample_index >= click_range.end && sample_index < click_range.start + window_size
+                {
+                    click_novelty = flux.novelty();
+                } else {
+                    max_novelty = max_novelty.max(flux.novelty());
+                }
+            });
+            click_novelty / max_novelty
+        };
+
+        let mut detector = SpectralFluxNoveltyDetector::new(window_size);
+        assert_eq!(detector.pre_emphasis(), 0.0);
+        assert!(click_novelty_ratio(&mut detector) < 1.0);
+        let mut detector = SpectralFluxNoveltyDetector::new(window_size);
+        detector.set_pre_emphasis(0.95);
+        assert!(click_novelty_ratio(&mut detector) > 10.0);
+
+        // Pre-emphasis state is carried across buffers
+        let mut detector = SpectralFluxNoveltyDetector::new(window_size);
+        detector.set_pre_emphasis(0.95);
+        let mut expected = Vec::new();
+        detector.process(&signal, |flux, _| expected.push(flux.novelty()));
+        detector.reset();
+        let mut novelties = Vec::new();
+        for chunk in signal.chunks(100) {
+            detector.process(chunk, |flux, _| novelties.push(flux.novelty()));
+        }
+        assert_eq!(novelties, expected);
+    }
+
+    #[test]
+    #[should_panic(expected = "Pre-emphasis coefficient must be in the range [0, 1]")]
+    fn test_invalid_pre_emphasis() {
+        SpectralFluxNoveltyDetector::new(256).set_pre_emphasis(1.5);
+    }
+
+    #[test]
+    fn test_compression_function() {
+        let window_size = 256;
+        let signal: Vec<f32> = (0..2048)
+            .map(|i| if i < 1024 { 0. } else { (0.3 * i as f32).sin() })
+            .collect();
+        let novelty_values = |detector: &mut SpectralFluxNoveltyDetector<_>| {
+            let mut values = Vec::new();
+            detector.process(&signal, |flux, _| values.push(flux.novelty()));
+            values
+        };
+
+        let mut detector = SpectralFluxNoveltyDetector::new(window_size);
+        let default_novelty = novelty_values(&mut detector);
+
+        detector.reset();
+        detector.compression_function().set(0.5, 0.5);
+        assert_ne!(novelty_values(&mut detector), default_novelty);
+
+        detector.reset();
+        detector.compression_function().set(0.025, 0.9);
+        assert_eq!(novelty_values(&mut detector), default_novelty);
+    }
+
+    #[test]
+    fn test_sample_index() {
+        let window_size = 256;
+        let hop_size = window_size / 2;
+        let mut detector = SpectralFluxNoveltyDetector::new(window_size);
+        let signal: Vec<f32> = (0..4000).map(|i| (0.1 * i as f32).sin()).collect();
+        let mut sample_indices = Vec::new();
+        for chunk in signal.chunks(100) {
+            detector.process(chunk, |_, sample_index| sample_indices.push(sample_index));
+        }
+        assert!(!sample_indices.is_empty());
+        for (i, sample_index) in sample_indices.iter().enumerate() {
+            // The first window only primes the previous power spectrum
+            let window_index = i + 1;
+            let expected = window_size - 1 + window_index * hop_size;
+            assert_eq!(*sample_index, expected);
+            assert!(*sample_index < signal.len());
+        }
     }
 }